@@ -333,6 +333,8 @@ pub enum BasicType {
     Bool,    // changed from Boolean
     Void,    // changed from Nothing
     Any,
+    Date,
+    Duration,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -387,6 +389,44 @@ impl Node {
             Node::InspectStatement(s) => s.location.clone(),
         }
     }
+
+    /// the variant name, e.g. "ConditionalStatement"
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Node::AssignStatement(_) => "AssignStatement",
+            Node::DeclareStatement(_) => "DeclareStatement",
+            Node::ConditionalStatement(_) => "ConditionalStatement",
+            Node::LoopStatement(_) => "LoopStatement",
+            Node::ReturnStatement(_) => "ReturnStatement",
+            Node::ImportStatement(_) => "ImportStatement",
+            Node::ExpressionStatement(_) => "ExpressionStatement",
+            Node::ThrowStatement(_) => "ThrowStatement",
+            Node::TryCatchStatement(_) => "TryCatchStatement",
+            Node::UsingStatement(_) => "UsingStatement",
+            Node::SetStatement(_) => "SetStatement",
+            Node::LiteralExpression(_) => "LiteralExpression",
+            Node::VariableExpression(_) => "VariableExpression",
+            Node::CallExpression(_) => "CallExpression",
+            Node::OperationExpression(_) => "OperationExpression",
+            Node::ConditionalExpression(_) => "ConditionalExpression",
+            Node::AccessExpression(_) => "AccessExpression",
+            Node::IndexExpression(_) => "IndexExpression",
+            Node::FunctionDeclaration(_) => "FunctionDeclaration",
+            Node::TypeDeclaration(_) => "TypeDeclaration",
+            Node::ModuleDeclaration(_) => "ModuleDeclaration",
+            Node::ClassDeclaration(_) => "ClassDeclaration",
+            Node::StructDeclaration(_) => "StructDeclaration",
+            Node::ObjectCreation(_) => "ObjectCreation",
+            Node::MethodCall(_) => "MethodCall",
+            Node::Program(_) => "Program",
+            Node::IndexSetStatement(_) => "IndexSetStatement",
+            Node::StartExpression(_) => "StartExpression",
+            Node::WaitExpression(_) => "WaitExpression",
+            Node::RunConcurrentlyStatement(_) => "RunConcurrentlyStatement",
+            Node::TestStatement(_) => "TestStatement",
+            Node::InspectStatement(_) => "InspectStatement",
+        }
+    }
 }
 
 impl fmt::Display for Node {
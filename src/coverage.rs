@@ -0,0 +1,194 @@
+// coverage - which statements ran during `layman test --coverage` and `layman verify --coverage`
+//
+// each test program runs in a process of its own, so the test runner names a data file in the
+// LAYMAN_COVERAGE environment variable. every program that finds it set appends the statements
+// it contains and how often each ran; the runner then adds them up by file and line, prints a
+// summary, writes LCOV and checks the minimum
+
+use crate::ast::{Location, Node};
+use crate::evaluator::hook::{self, ExecutionHook};
+use crate::evaluator::Evaluator;
+use crate::types::Environment;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// the environment variable naming the data file programs append to
+pub const DATA_VARIABLE: &str = "LAYMAN_COVERAGE";
+
+#[derive(clap::Args)]
+pub struct CoverageArgs {
+    /// Record which lines the tests run, and print a summary per file
+    #[arg(long)]
+    coverage: bool,
+    /// Where to write the coverage as LCOV
+    #[arg(long, default_value = "lcov.info")]
+    lcov: String,
+    /// Fail when less than this percentage of lines ran (implies --coverage)
+    #[arg(long)]
+    min_coverage: Option<f64>,
+    /// Also count the standard library and installed packages
+    #[arg(long)]
+    include_libraries: bool,
+}
+
+/// counts the statements a program runs
+pub struct Recorder {
+    hits: Mutex<HashMap<(String, usize, usize), u64>>,
+}
+
+impl ExecutionHook for Recorder {
+    fn before_statement(&self, _statement: &Node, location: &Location, _environment: &Arc<Environment>) {
+        let key = (location.file.clone(), location.line, location.column);
+        *self.hits.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+}
+
+/// start counting when this program runs under a test runner collecting coverage
+pub fn record(evaluator: &mut Evaluator) -> Option<Arc<Recorder>> {
+    std::env::var_os(DATA_VARIABLE)?;
+    let recorder = Arc::new(Recorder { hits: Mutex::new(HashMap::new()) });
+    evaluator.set_hook(Arc::clone(&recorder) as Arc<dyn ExecutionHook>);
+    Some(recorder)
+}
+
+impl Recorder {
+    /// append every statement of `program` with its count, one "file TAB line TAB column TAB count" line each
+    pub fn save(&self, program: &Node) {
+        let Some(path) = std::env::var_os(DATA_VARIABLE) else {
+            return;
+        };
+        let mut counts = self.hits.lock().unwrap().clone();
+        let mut locations = Vec::new();
+        statements(program, &mut locations);
+        for location in locations {
+            counts.entry((location.file, location.line, location.column)).or_insert(0);
+        }
+        let data: String =
+            counts.iter().map(|((file, line, column), count)| format!("{}\t{}\t{}\t{}\n", file, line, column, count)).collect();
+        // one write per program, so programs finishing together do not interleave their lines
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = file.write_all(data.as_bytes());
+        }
+    }
+}
+
+/// the locations of every statement in `node`, including those in blocks, functions and methods
+fn statements(node: &Node, locations: &mut Vec<Location>) {
+    if let Some(location) = hook::statement_location(node) {
+        locations.push(location);
+    }
+    let mut blocks: Vec<&Node> = Vec::new();
+    match node {
+        Node::Program(program) => blocks.extend(&program.statements),
+        Node::ConditionalStatement(s) => {
+            blocks.push(&s.then_branch);
+            blocks.extend(s.else_branch.as_deref());
+        }
+        Node::LoopStatement(s) => blocks.push(&s.body),
+        Node::TryCatchStatement(s) => {
+            blocks.push(&s.try_block);
+            blocks.extend(s.catch_block.as_deref());
+        }
+        Node::UsingStatement(s) => blocks.push(&s.body),
+        Node::TestStatement(s) => blocks.push(&s.body),
+        Node::InspectStatement(s) => blocks.extend(s.cases.iter().map(|case| &*case.body)),
+        Node::RunConcurrentlyStatement(s) => blocks.extend(&s.statements),
+        Node::FunctionDeclaration(f) => blocks.push(&f.body),
+        Node::ClassDeclaration(c) => blocks.extend(c.methods.iter().map(|method| &*method.body)),
+        _ => {}
+    }
+    for block in blocks {
+        statements(block, locations);
+    }
+}
+
+/// run `tests` with coverage collected if `args` asks for it, then report it
+pub fn collect(args: &CoverageArgs, tests: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+    if !args.coverage && args.min_coverage.is_none() {
+        return tests();
+    }
+    let data = std::env::temp_dir().join(format!("layman-coverage-{}.data", std::process::id()));
+    let _ = fs::remove_file(&data);
+    std::env::set_var(DATA_VARIABLE, &data);
+    let result = tests();
+    std::env::remove_var(DATA_VARIABLE);
+
+    let text = fs::read_to_string(&data).unwrap_or_default();
+    let _ = fs::remove_file(&data);
+    // add up the runs of each statement over every program, then count a line with several
+    // statements as often as the most run of them
+    let mut statements: HashMap<(String, usize, usize), u64> = HashMap::new();
+    for record in text.lines() {
+        let fields: Vec<&str> = record.split('\t').collect();
+        let [file, line, column, count] = fields[..] else {
+            continue;
+        };
+        let (Ok(line), Ok(column), Ok(count)) = (line.parse(), column.parse(), count.parse::<u64>()) else {
+            continue;
+        };
+        *statements.entry((file.to_string(), line, column)).or_insert(0) += count;
+    }
+    let mut files: BTreeMap<String, BTreeMap<usize, u64>> = BTreeMap::new();
+    for ((file, line, _), count) in statements {
+        if !args.include_libraries && is_library(&file) {
+            continue;
+        }
+        let hits = files.entry(file).or_default().entry(line).or_insert(0);
+        *hits = (*hits).max(count);
+    }
+
+    fs::write(&args.lcov, lcov(&files)).map_err(|e| format!("Failed to write {}: {}", args.lcov, e))?;
+    let percent = print_summary(&files);
+    println!("Coverage written to {}", PathBuf::from(&args.lcov).display());
+    result?;
+    match args.min_coverage {
+        Some(minimum) if percent < minimum => {
+            Err(format!("Coverage is {:.1}%, below the minimum of {}%", percent, minimum))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// a file of the standard library or of an installed package, found where imports look for them
+fn is_library(file: &str) -> bool {
+    let root = std::env::current_dir().unwrap_or_default();
+    let path = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+    ["std", "modules"].iter().any(|dir| fs::canonicalize(root.join(dir)).is_ok_and(|dir| path.starts_with(dir)))
+}
+
+/// one record per file: a DA line for each line with a statement, then the totals
+fn lcov(files: &BTreeMap<String, BTreeMap<usize, u64>>) -> String {
+    let mut out = String::new();
+    for (file, lines) in files {
+        out.push_str("TN:\n");
+        out.push_str(&format!("SF:{}\n", file));
+        for (line, count) in lines {
+            out.push_str(&format!("DA:{},{}\n", line, count));
+        }
+        out.push_str(&format!("LF:{}\n", lines.len()));
+        out.push_str(&format!("LH:{}\n", lines.values().filter(|count| **count > 0).count()));
+        out.push_str("end_of_record\n");
+    }
+    out
+}
+
+/// print lines run out of lines with statements, per file and in total; returns the total
+/// percentage, which is 0 when no statements were recorded
+fn print_summary(files: &BTreeMap<String, BTreeMap<usize, u64>>) -> f64 {
+    let percent = |hit: usize, found: usize| if found == 0 { 0.0 } else { hit as f64 * 100.0 / found as f64 };
+    let width = files.keys().map(|file| file.len()).max().unwrap_or(0).max("File".len());
+    println!("\n{:<width$}  {:>6}  {:>6}  {:>7}", "File", "Lines", "Run", "Cover");
+    let (mut total_hit, mut total_found) = (0, 0);
+    for (file, lines) in files {
+        let hit = lines.values().filter(|count| **count > 0).count();
+        println!("{:<width$}  {:>6}  {:>6}  {:>6.1}%", file, lines.len(), hit, percent(hit, lines.len()));
+        total_hit += hit;
+        total_found += lines.len();
+    }
+    let total = percent(total_hit, total_found);
+    println!("{:<width$}  {:>6}  {:>6}  {:>6.1}%", "Total", total_found, total_hit, total);
+    total
+}
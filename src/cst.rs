@@ -0,0 +1,129 @@
+// concrete syntax tree - the parser's statements laid over the lossless token stream
+// every character of the source lives in a token or its trivia, so a tree renders back to its file byte for byte
+
+use crate::ast::Node;
+use crate::lexer::{Lexer, SyntaxError, Token, TriviaKind};
+use crate::parser::{Parser, StatementSpan};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxKind {
+    /// the whole file
+    File,
+    /// a statement, named by its AST node (e.g. "ConditionalStatement")
+    Statement(&'static str),
+    /// a line the parser skipped as prose
+    Prose,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    /// nested statements and the tokens between them, in source order
+    pub children: Vec<SyntaxElement>,
+}
+
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub ast: Node,
+}
+
+/// lex and parse a file into its concrete syntax tree (and the AST the parser built)
+pub fn parse(source: &str, file: &str) -> Result<SyntaxTree, SyntaxError> {
+    let tokens = Lexer::new(source, file.to_string()).tokenize()?;
+    let mut parser = Parser::new(tokens.clone());
+    let ast = parser.parse()?;
+
+    let mut spans: Vec<&StatementSpan> = parser.statement_spans().iter().filter(|s| !s.tokens.is_empty()).collect();
+    // outer statements before the ones they contain
+    spans.sort_by(|a, b| {
+        a.tokens.start.cmp(&b.tokens.start).then(b.tokens.end.cmp(&a.tokens.end)).then(a.depth.cmp(&b.depth))
+    });
+
+    let mut next = 0;
+    let children = build(&tokens, &spans, 0..tokens.len(), &mut next);
+    Ok(SyntaxTree { root: SyntaxNode { kind: SyntaxKind::File, children }, ast })
+}
+
+fn build(tokens: &[Token], spans: &[&StatementSpan], range: std::ops::Range<usize>, next: &mut usize) -> Vec<SyntaxElement> {
+    let mut children = Vec::new();
+    let mut position = range.start;
+    while let Some(span) = spans.get(*next) {
+        if span.tokens.start >= range.end {
+            break;
+        }
+        *next += 1;
+        if span.tokens.start < position || span.tokens.end > range.end {
+            continue; // overlaps a sibling; its tokens stay with the enclosing node
+        }
+        children.extend(tokens[position..span.tokens.start].iter().cloned().map(SyntaxElement::Token));
+        let kind = if span.skipped { SyntaxKind::Prose } else { SyntaxKind::Statement(span.kind) };
+        let inner = build(tokens, spans, span.tokens.clone(), next);
+        children.push(SyntaxElement::Node(SyntaxNode { kind, children: inner }));
+        position = span.tokens.end;
+    }
+    children.extend(tokens[position..range.end].iter().cloned().map(SyntaxElement::Token));
+    children
+}
+
+impl SyntaxNode {
+    /// the source this node covers, trivia included
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(|t| t.full_text()).collect()
+    }
+
+    /// every token under this node, in source order
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Token(token) => tokens.push(token),
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+            }
+        }
+        tokens
+    }
+
+    pub fn first_token(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(node) => node.first_token(),
+        })
+    }
+
+    /// the statements directly inside this node
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// the comment lines directly above this node, up to the first blank line (e.g. a doc comment)
+    pub fn leading_comments(&self) -> Vec<&str> {
+        let Some(token) = self.first_token() else {
+            return Vec::new();
+        };
+        let mut comments = Vec::new();
+        // line breaks since the last comment, counting up from the node; two make a blank line
+        let mut breaks = 0;
+        for trivia in token.leading_trivia.iter().rev() {
+            match trivia.kind {
+                TriviaKind::Comment => {
+                    comments.push(trivia.text.as_str());
+                    breaks = 0;
+                }
+                TriviaKind::LineBreak if breaks == 1 => break,
+                TriviaKind::LineBreak => breaks += 1,
+                _ => {}
+            }
+        }
+        comments.reverse();
+        comments
+    }
+}
@@ -0,0 +1,259 @@
+// the Debug Adapter Protocol, so editors can drive the debugger
+// messages use the same Content-Length framing as the language server; lines count from 1
+
+use super::{scopes, variables, Event, Resume, Session};
+use crate::ast::Node;
+use crate::evaluator::hook::describe;
+use crate::evaluator::Evaluator;
+use crate::lsp::protocol::{read_message, write_message};
+use crate::stdlib::io::ScriptedInput;
+use crate::types::{Environment, Value};
+use serde_json::{json, Value as Json};
+use std::io::{self, Stdout};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// the program has one thread the editor can see
+const THREAD_ID: u64 = 1;
+
+/// writes responses and events; shared with the program thread, which sends events as it runs
+struct Output {
+    writer: Mutex<Stdout>,
+    seq: AtomicU64,
+}
+
+impl Output {
+    fn send(&self, mut message: Json) {
+        message["seq"] = json!(self.seq.fetch_add(1, Ordering::SeqCst) + 1);
+        let _ = write_message(&mut *self.writer.lock().unwrap(), &message);
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+}
+
+/// something the editor can expand: the variables of a scope, or the items of a value
+enum Handle {
+    Scope(Arc<Environment>),
+    Value(Value),
+}
+
+struct Adapter {
+    output: Arc<Output>,
+    session: Arc<Session>,
+    /// the checked program, from `launch` until `configurationDone` starts it
+    pending: Option<(Node, bool)>,
+    launched: bool,
+    /// what each variablesReference stands for; a reference is its index plus one
+    handles: Vec<Handle>,
+}
+
+/// serve one debugging session on stdin and stdout; returns the exit code for the process
+pub fn run() -> Result<i32, String> {
+    let output = Arc::new(Output { writer: Mutex::new(io::stdout()), seq: AtomicU64::new(0) });
+    let session = Session::new({
+        let output = Arc::clone(&output);
+        move |event| match event {
+            Event::Stopped(reason, _) => output.event(
+                "stopped",
+                json!({ "reason": reason.name(), "threadId": THREAD_ID, "allThreadsStopped": true }),
+            ),
+            Event::Output(text) => output.event("output", json!({ "category": "stdout", "output": text })),
+            Event::Exited(code, error) => {
+                if let Some(error) = error {
+                    output.event("output", json!({ "category": "stderr", "output": format!("Error: {}\n", error) }));
+                }
+                output.event("exited", json!({ "exitCode": code }));
+                output.event("terminated", json!({}));
+            }
+        }
+    });
+    let mut adapter = Adapter { output, session, pending: None, launched: false, handles: Vec::new() };
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Some(message) = read_message(&mut input)? {
+        if message["type"] != "request" {
+            continue;
+        }
+        let command = message["command"].as_str().unwrap_or("");
+        let arguments = &message["arguments"];
+        let result = adapter.handle(command, arguments);
+        let response = match &result {
+            Ok(body) => json!({ "success": true, "body": body }),
+            Err(error) => json!({ "success": false, "message": error }),
+        };
+        let mut response = response;
+        response["type"] = json!("response");
+        response["request_seq"] = message["seq"].clone();
+        response["command"] = json!(command);
+        adapter.output.send(response);
+
+        match command {
+            "initialize" => adapter.output.event("initialized", json!({})),
+            "disconnect" | "terminate" => return Ok(0),
+            _ => {}
+        }
+    }
+    // the editor went away; let the program finish rather than leave it stopped forever
+    if adapter.launched {
+        adapter.session.detach();
+        adapter.session.wait_until_finished();
+    }
+    Ok(0)
+}
+
+impl Adapter {
+    fn handle(&mut self, command: &str, arguments: &Json) -> Result<Json, String> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsTerminateRequest": true,
+            })),
+            "launch" => {
+                let program = arguments["program"].as_str().ok_or("launch needs a 'program' path")?;
+                let source = std::fs::read_to_string(program).map_err(|e| format!("Failed to read file {}: {}", program, e))?;
+                let ast = crate::check_source(program, &source).map_err(|diagnostics| {
+                    diagnostics.iter().map(|d| d.render(&crate::read_source)).collect::<Vec<_>>().join("\n")
+                })?;
+                self.pending = Some((ast, arguments["stopOnEntry"].as_bool().unwrap_or(false)));
+                Ok(json!({}))
+            }
+            "setBreakpoints" => {
+                let path = arguments["source"]["path"].as_str().ok_or("setBreakpoints needs a source path")?;
+                let lines: Vec<usize> = arguments["breakpoints"]
+                    .as_array()
+                    .map(|breakpoints| breakpoints.iter().filter_map(|b| b["line"].as_u64()).map(|l| l as usize).collect())
+                    .unwrap_or_default();
+                self.session.set_breakpoints(&PathBuf::from(path), &lines);
+                let verified: Vec<Json> = lines.iter().map(|line| json!({ "verified": true, "line": line })).collect();
+                Ok(json!({ "breakpoints": verified }))
+            }
+            "configurationDone" => {
+                if let Some((program, stop_on_entry)) = self.pending.take() {
+                    let mut evaluator = Evaluator::new();
+                    // stdin carries the protocol, so the program reads no input
+                    evaluator.set_input(Arc::new(ScriptedInput::new("")));
+                    self.session.launch(program, evaluator, stop_on_entry);
+                    self.launched = true;
+                }
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => {
+                self.wait_until_stopped()?;
+                let frames: Vec<Json> = self
+                    .session
+                    .frames()
+                    .iter()
+                    .enumerate()
+                    .map(|(id, frame)| {
+                        let location = frame.location.as_ref();
+                        let path = location.map(|l| l.file.clone()).unwrap_or_default();
+                        let name = PathBuf::from(&path).file_name().map(|n| n.to_string_lossy().to_string());
+                        json!({
+                            "id": id,
+                            "name": frame.name,
+                            "source": { "name": name, "path": path },
+                            "line": location.map_or(0, |l| l.line),
+                            "column": location.map_or(0, |l| l.column + 1),
+                        })
+                    })
+                    .collect();
+                Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+            }
+            "scopes" => {
+                self.wait_until_stopped()?;
+                let frame = self.frame(arguments)?;
+                let scopes: Vec<Json> = scopes(&frame)
+                    .into_iter()
+                    .map(|(name, environment)| {
+                        let reference = self.remember(Handle::Scope(environment));
+                        json!({ "name": name, "variablesReference": reference, "expensive": false })
+                    })
+                    .collect();
+                Ok(json!({ "scopes": scopes }))
+            }
+            "variables" => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+                let children = match self.handles.get(reference.wrapping_sub(1)) {
+                    Some(Handle::Scope(environment)) => variables(environment),
+                    Some(Handle::Value(value)) => children(value),
+                    None => return Err(format!("Unknown variablesReference {}", reference)),
+                };
+                let variables: Vec<Json> = children.into_iter().map(|(name, value)| self.variable(&name, value)).collect();
+                Ok(json!({ "variables": variables }))
+            }
+            "evaluate" => {
+                self.wait_until_stopped()?;
+                let name = arguments["expression"].as_str().unwrap_or("").trim().to_string();
+                let frame = self.frame(arguments)?;
+                let value = frame.environment.as_ref().and_then(|environment| environment.get(&name));
+                let value = value.ok_or_else(|| format!("'{}' is not defined here", name))?;
+                let variable = self.variable(&name, value);
+                Ok(json!({ "result": variable["value"], "variablesReference": variable["variablesReference"] }))
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                self.handles.clear();
+                self.session.resume(match command {
+                    "next" => Resume::StepOver,
+                    "stepIn" => Resume::StepIn,
+                    "stepOut" => Resume::StepOut,
+                    _ => Resume::Continue,
+                });
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "pause" => {
+                self.session.pause();
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => Ok(json!({})),
+            other => Err(format!("Unsupported request '{}'", other)),
+        }
+    }
+
+    /// requests about the stack need the program stopped; scripted clients send them before it is
+    fn wait_until_stopped(&self) -> Result<(), String> {
+        if self.launched && self.session.wait_until_stopped() {
+            Ok(())
+        } else {
+            Err("The program is not stopped".to_string())
+        }
+    }
+
+    fn frame(&self, arguments: &Json) -> Result<super::Frame, String> {
+        let id = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+        self.session.frames().into_iter().nth(id).ok_or_else(|| format!("Unknown frame {}", id))
+    }
+
+    fn remember(&mut self, handle: Handle) -> usize {
+        self.handles.push(handle);
+        self.handles.len()
+    }
+
+    /// a variable as the editor shows it; lists, dictionaries and objects can be expanded
+    fn variable(&mut self, name: &str, value: Value) -> Json {
+        let text = describe(&value);
+        let reference = match value {
+            Value::List(_) | Value::Dictionary(_) | Value::Object(_) => self.remember(Handle::Value(value)),
+            _ => 0,
+        };
+        json!({ "name": name, "value": text, "variablesReference": reference })
+    }
+}
+
+/// the items of a list (numbered from 1, as layman counts them), the entries of a dictionary, or the properties of an object
+fn children(value: &Value) -> Vec<(String, Value)> {
+    let mut children: Vec<(String, Value)> = match value {
+        Value::List(items) => {
+            return items.lock().unwrap().iter().enumerate().map(|(i, item)| (format!("item {}", i + 1), item.clone())).collect();
+        }
+        Value::Dictionary(entries) => entries.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        Value::Object(object) => object.lock().unwrap().properties.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        _ => Vec::new(),
+    };
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    children
+}
@@ -0,0 +1,473 @@
+// debugger - pause a running program at breakpoints and step through it
+//
+// the program runs on its own thread with a `Session` as the evaluator's hook. before each
+// statement the session decides whether to stop; while stopped, the front end (the terminal
+// prompt here, or the Debug Adapter Protocol in dap.rs) reads the call stack and the scopes
+// of each frame, then resumes it with continue or a step.
+
+pub mod dap;
+
+use crate::ast::{Location, Node};
+use crate::evaluator::hook::{describe, ExecutionHook};
+use crate::evaluator::Evaluator;
+use crate::types::{Environment, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+/// why the program stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Entry,
+    Breakpoint,
+    Step,
+    Pause,
+}
+
+impl StopReason {
+    /// the name the Debug Adapter Protocol uses
+    pub fn name(self) -> &'static str {
+        match self {
+            StopReason::Entry => "entry",
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+            StopReason::Pause => "pause",
+        }
+    }
+}
+
+/// what the running program reports to the front end
+pub enum Event {
+    Stopped(StopReason, Location),
+    /// a line the program printed
+    Output(String),
+    /// the program finished, with its exit code and the error that ended it, if any
+    Exited(i32, Option<String>),
+}
+
+/// how to carry on after a stop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    /// stop at the next statement, even inside a function it calls
+    StepIn,
+    /// stop at the next statement of this function or its callers
+    StepOver,
+    /// stop once this function has returned
+    StepOut,
+}
+
+/// when to stop next, by call depth
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Run,
+    Entry,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+    Pause,
+}
+
+/// one call on the stack
+#[derive(Clone)]
+pub struct Frame {
+    pub name: String,
+    /// the statement running in this frame; None until its first statement starts
+    pub location: Option<Location>,
+    /// the innermost scope of that statement; its parents are the enclosing scopes
+    pub environment: Option<Arc<Environment>>,
+}
+
+struct State {
+    /// breakpoint lines by canonical file path
+    breakpoints: HashMap<PathBuf, BTreeSet<usize>>,
+    /// canonical paths of the files statements came from, so each is resolved once
+    paths: HashMap<String, PathBuf>,
+    mode: Mode,
+    /// outermost first
+    frames: Vec<Frame>,
+    stopped: bool,
+    finished: bool,
+    /// the thread running the program; background tasks on other threads are not debugged
+    thread: Option<ThreadId>,
+}
+
+pub struct Session {
+    state: Mutex<State>,
+    /// signalled whenever the program stops, resumes or finishes
+    changed: Condvar,
+    events: Box<dyn Fn(Event) + Send + Sync>,
+}
+
+impl Session {
+    pub fn new(events: impl Fn(Event) + Send + Sync + 'static) -> Arc<Self> {
+        Arc::new(Session {
+            state: Mutex::new(State {
+                breakpoints: HashMap::new(),
+                paths: HashMap::new(),
+                mode: Mode::Run,
+                frames: Vec::new(),
+                stopped: false,
+                finished: false,
+                thread: None,
+            }),
+            changed: Condvar::new(),
+            events: Box::new(events),
+        })
+    }
+
+    /// replace the breakpoints of one file
+    pub fn set_breakpoints(&self, file: &Path, lines: &[usize]) {
+        let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.state.lock().unwrap().breakpoints.insert(path, lines.iter().copied().collect());
+    }
+
+    /// the breakpoints of every file, in path and line order
+    pub fn breakpoints(&self) -> Vec<(PathBuf, usize)> {
+        let state = self.state.lock().unwrap();
+        let mut all: Vec<(PathBuf, usize)> =
+            state.breakpoints.iter().flat_map(|(path, lines)| lines.iter().map(|line| (path.clone(), *line))).collect();
+        all.sort();
+        all
+    }
+
+    /// run the program on a new thread; it reports back through the session's events
+    pub fn launch(self: &Arc<Self>, program: Node, mut evaluator: Evaluator, stop_on_entry: bool) -> thread::JoinHandle<()> {
+        let session = Arc::clone(self);
+        {
+            let mut state = self.state.lock().unwrap();
+            state.mode = if stop_on_entry { Mode::Entry } else { Mode::Run };
+        }
+        evaluator.set_output_callback({
+            let session = Arc::clone(self);
+            move |line| (session.events)(Event::Output(format!("{}\n", line)))
+        });
+        evaluator.set_hook(Arc::clone(self) as Arc<dyn ExecutionHook>);
+        thread::spawn(move || {
+            {
+                let mut state = session.state.lock().unwrap();
+                state.thread = Some(thread::current().id());
+                state.frames = vec![Frame { name: "<main>".to_string(), location: None, environment: None }];
+            }
+            let result = evaluator.evaluate(&program);
+            let code = evaluator.exit_code().unwrap_or(if result.is_err() { 1 } else { 0 });
+            // report the exit before waking anyone waiting for it, so it is the last thing they see
+            (session.events)(Event::Exited(code, result.err().map(String::from)));
+            {
+                let mut state = session.state.lock().unwrap();
+                state.finished = true;
+                state.stopped = false;
+                state.frames.clear();
+            }
+            session.changed.notify_all();
+        })
+    }
+
+    /// carry on after a stop; does nothing while the program runs
+    pub fn resume(&self, how: Resume) {
+        let mut state = self.state.lock().unwrap();
+        if !state.stopped {
+            return;
+        }
+        let depth = state.frames.len();
+        state.mode = match how {
+            Resume::Continue => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+        };
+        state.stopped = false;
+        self.changed.notify_all();
+    }
+
+    /// stop at the next statement
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.stopped && !state.finished {
+            state.mode = Mode::Pause;
+        }
+    }
+
+    /// drop every breakpoint and let the program run to its end
+    pub fn detach(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.breakpoints.clear();
+        state.mode = Mode::Run;
+        state.stopped = false;
+        self.changed.notify_all();
+    }
+
+    /// block until the program is stopped; false when it finished instead
+    pub fn wait_until_stopped(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while !state.stopped && !state.finished {
+            state = self.changed.wait(state).unwrap();
+        }
+        state.stopped
+    }
+
+    /// block until the program has finished
+    pub fn wait_until_finished(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.finished {
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// the call stack, innermost call first
+    pub fn frames(&self) -> Vec<Frame> {
+        self.state.lock().unwrap().frames.iter().rev().cloned().collect()
+    }
+
+    fn on_program_thread(&self, state: &State) -> bool {
+        state.thread == Some(thread::current().id())
+    }
+}
+
+impl ExecutionHook for Session {
+    fn before_statement(&self, _statement: &Node, location: &Location, environment: &Arc<Environment>) {
+        let mut state = self.state.lock().unwrap();
+        if !self.on_program_thread(&state) {
+            return;
+        }
+        let depth = state.frames.len();
+        if let Some(frame) = state.frames.last_mut() {
+            frame.location = Some(location.clone());
+            frame.environment = Some(Arc::clone(environment));
+        }
+
+        let path = match state.paths.get(&location.file) {
+            Some(path) => path.clone(),
+            None => {
+                let file = Path::new(&location.file);
+                let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+                state.paths.insert(location.file.clone(), path.clone());
+                path
+            }
+        };
+        let at_breakpoint = state.breakpoints.get(&path).is_some_and(|lines| lines.contains(&location.line));
+        let reason = match state.mode {
+            Mode::Entry => Some(StopReason::Entry),
+            Mode::Pause => Some(StopReason::Pause),
+            _ if at_breakpoint => Some(StopReason::Breakpoint),
+            Mode::StepIn => Some(StopReason::Step),
+            Mode::StepOver(from) if depth <= from => Some(StopReason::Step),
+            Mode::StepOut(from) if depth < from => Some(StopReason::Step),
+            _ => None,
+        };
+        let Some(reason) = reason else {
+            return;
+        };
+
+        state.stopped = true;
+        state.mode = Mode::Run;
+        self.changed.notify_all();
+        drop(state);
+        (self.events)(Event::Stopped(reason, location.clone()));
+
+        let mut state = self.state.lock().unwrap();
+        while state.stopped {
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn enter_function(&self, name: &str, _arguments: &[(String, Value)]) {
+        let mut state = self.state.lock().unwrap();
+        if self.on_program_thread(&state) {
+            state.frames.push(Frame { name: name.to_string(), location: None, environment: None });
+        }
+    }
+
+    fn exit_function(&self, _name: &str, _result: Option<&Value>) {
+        let mut state = self.state.lock().unwrap();
+        if self.on_program_thread(&state) {
+            state.frames.pop();
+        }
+    }
+}
+
+/// the scopes of a frame, innermost first, each named as editors show them
+pub fn scopes(frame: &Frame) -> Vec<(&'static str, Arc<Environment>)> {
+    let mut scopes = Vec::new();
+    let mut current = frame.environment.clone();
+    while let Some(environment) = current {
+        current = environment.parent.clone();
+        let name = match (scopes.is_empty(), current.is_none()) {
+            (_, true) => "Globals",
+            (true, false) => "Locals",
+            (false, false) => "Enclosing",
+        };
+        scopes.push((name, environment));
+    }
+    scopes
+}
+
+/// the variables of one scope, sorted by name
+pub fn variables(environment: &Environment) -> Vec<(String, Value)> {
+    let mut variables: Vec<(String, Value)> = environment
+        .variables
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| !name.starts_with("__"))
+        .map(|(name, (value, _))| (name.clone(), value.clone()))
+        .collect();
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    variables
+}
+
+/// `layman debug file.lay`: run the program under a prompt that reads debugger commands
+pub fn run_terminal(filename: &str, program: Node, evaluator: Evaluator) -> Result<i32, String> {
+    let (sender, events) = mpsc::channel();
+    let session = Session::new(move |event| {
+        let _ = sender.send(event);
+    });
+    let source = std::fs::read_to_string(filename).unwrap_or_default();
+    let lines: Vec<&str> = source.lines().collect();
+    println!("Debugging {}. Type `help` for the commands.", filename);
+    session.launch(program, evaluator, true);
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    for event in events {
+        match event {
+            Event::Output(text) => {
+                print!("{}", text);
+                io::stdout().flush().map_err(|e| e.to_string())?;
+            }
+            Event::Exited(code, error) => {
+                if let Some(error) = error {
+                    println!("Error: {}", error);
+                }
+                println!("Program exited with code {}", code);
+                return Ok(code);
+            }
+            Event::Stopped(reason, location) => {
+                let at = match reason {
+                    StopReason::Breakpoint => "Breakpoint at ",
+                    _ => "",
+                };
+                let text = if location.file == filename { lines.get(location.line - 1).copied() } else { None };
+                println!("{}{}:{}: {}", at, location.file, location.line, text.unwrap_or("").trim());
+                if !prompt(&session, &mut input, filename)? {
+                    return Ok(0);
+                }
+            }
+        }
+    }
+    Ok(0)
+}
+
+const HELP: &str = "\
+break <line> | break <file>:<line>   stop when the line is about to run (b)
+delete <line> | delete <file>:<line> remove a breakpoint
+breakpoints                          list the breakpoints
+continue                             run to the next breakpoint (c)
+step                                 run one statement, going into calls (s)
+next                                 run one statement, stepping over calls (n)
+finish                               run until the current function returns (f)
+stack                                show the call stack (bt)
+vars [frame]                         show the variables of a frame, innermost scope first (v)
+print <name>                         show one variable (p)
+quit                                 stop the program and leave (q)";
+
+/// read commands while the program is stopped; false when the user quits
+fn prompt(session: &Session, input: &mut impl BufRead, filename: &str) -> Result<bool, String> {
+    loop {
+        print!("(debug) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Ok(false);
+        }
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next();
+        let resume = match command {
+            "" => continue,
+            "c" | "continue" => Resume::Continue,
+            "s" | "step" => Resume::StepIn,
+            "n" | "next" => Resume::StepOver,
+            "f" | "finish" => Resume::StepOut,
+            "q" | "quit" => return Ok(false),
+            "help" | "h" => {
+                println!("{}", HELP);
+                continue;
+            }
+            "b" | "break" | "delete" => {
+                match argument.and_then(|text| breakpoint_place(text, filename)) {
+                    Some((file, line)) => {
+                        let mut lines: Vec<usize> =
+                            session.breakpoints().into_iter().filter(|(path, _)| *path == file).map(|(_, line)| line).collect();
+                        lines.retain(|l| *l != line);
+                        if command != "delete" {
+                            lines.push(line);
+                            println!("Breakpoint at {}:{}", file.display(), line);
+                        }
+                        session.set_breakpoints(&file, &lines);
+                    }
+                    None => println!("Expected a line number, or <file>:<line>"),
+                }
+                continue;
+            }
+            "breakpoints" => {
+                for (file, line) in session.breakpoints() {
+                    println!("{}:{}", file.display(), line);
+                }
+                continue;
+            }
+            "bt" | "stack" => {
+                for (index, frame) in session.frames().iter().enumerate() {
+                    let place = frame.location.as_ref().map_or(String::new(), |l| format!(" at {}:{}", l.file, l.line));
+                    println!("#{} {}{}", index, frame.name, place);
+                }
+                continue;
+            }
+            "v" | "vars" => {
+                let index = argument.and_then(|text| text.parse::<usize>().ok()).unwrap_or(0);
+                match session.frames().get(index) {
+                    Some(frame) => {
+                        for (name, environment) in scopes(frame) {
+                            println!("{}:", name);
+                            for (variable, value) in variables(&environment) {
+                                println!("  {} = {}", variable, describe(&value));
+                            }
+                        }
+                    }
+                    None => println!("No frame #{}", index),
+                }
+                continue;
+            }
+            "p" | "print" => {
+                let frames = session.frames();
+                let found = argument.zip(frames.first()).and_then(|(name, frame)| frame.environment.as_ref()?.get(name));
+                match (argument, found) {
+                    (Some(name), Some(value)) => println!("{} = {}", name, describe(&value)),
+                    (Some(name), None) => println!("'{}' is not defined here", name),
+                    (None, _) => println!("Expected a variable name"),
+                }
+                continue;
+            }
+            other => {
+                println!("Unknown command '{}'; type `help` for the commands", other);
+                continue;
+            }
+        };
+        session.resume(resume);
+        return Ok(true);
+    }
+}
+
+/// "12" (in the program's own file) or "lib.lay:12"
+fn breakpoint_place(text: &str, filename: &str) -> Option<(PathBuf, usize)> {
+    let (file, line) = match text.rsplit_once(':') {
+        Some((file, line)) => (file, line),
+        None => (filename, text),
+    };
+    let line = line.parse().ok().filter(|line| *line > 0)?;
+    let file = Path::new(file);
+    Some((file.canonicalize().unwrap_or_else(|_| file.to_path_buf()), line))
+}
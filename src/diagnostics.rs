@@ -0,0 +1,207 @@
+// diagnostics - problems found in layman code, in one shape for every stage
+// each has a stable code, a severity, the span it points at, and optional labels and notes;
+// the CLI renders them under the offending source line, or as one JSON object per line for tools
+
+use crate::ast::Location;
+use crate::evaluator::RuntimeError;
+use crate::lexer::SyntaxError;
+use crate::typechecker::TypeError;
+use serde::Serialize;
+
+/// stable codes, one per kind of problem. a code is never reused or renumbered
+pub mod codes {
+    /// a file that cannot be read
+    pub const IO: &str = "E0001";
+    /// a character or literal that is not valid layman
+    pub const LEX: &str = "E0002";
+    /// a line the parser cannot make sense of
+    pub const SYNTAX: &str = "E0003";
+    /// a command-line option with a value that cannot be used
+    pub const INVALID_OPTION: &str = "E0004";
+    /// a file the formatter cannot rewrite without changing what it means
+    pub const FORMAT: &str = "E0005";
+    /// an import that cannot be found or loaded
+    pub const IMPORT: &str = "E0101";
+    /// a value of the wrong type
+    pub const TYPE_MISMATCH: &str = "E0201";
+    /// a variable, function, class or type that does not exist
+    pub const UNKNOWN_NAME: &str = "E0202";
+    /// a property or method the value does not have
+    pub const UNKNOWN_MEMBER: &str = "E0203";
+    /// a call with the wrong number of arguments
+    pub const ARGUMENT_COUNT: &str = "E0204";
+    /// a change to a constant or frozen value
+    pub const IMMUTABLE: &str = "E0205";
+    /// a literal pattern or conversion that can never work
+    pub const INVALID_LITERAL: &str = "E0206";
+    /// a `maybe` value used without checking that it exists
+    pub const MAYBE_NOTHING: &str = "E0207";
+    /// an error raised while the program runs
+    pub const RUNTIME: &str = "E0301";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// a place in a file; line and column both count from 1
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// the source a diagnostic points at; `end` is just past the last character
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Span {
+    pub file: String,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// `length` characters from a 1-based line and column
+    pub fn new(file: &str, line: usize, column: usize, length: usize) -> Self {
+        Span {
+            file: file.to_string(),
+            start: Position { line, column },
+            end: Position { line, column: column + length.max(1) },
+        }
+    }
+
+    /// the token an AST location points at (AST columns count from 0)
+    pub fn from_location(location: &Location) -> Self {
+        let length = location.source.as_deref().map_or(1, |source| source.trim_end().chars().count());
+        Span::new(&location.file, location.line, location.column + 1, length)
+    }
+}
+
+/// a secondary span with a note about it, e.g. where a variable was declared
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    /// None when the problem is not tied to a place in the source (e.g. a missing file)
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code: code.to_string(),
+            severity,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, code, message)
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn from_type_error(error: &TypeError) -> Self {
+        let diagnostic = Diagnostic::error(error.code, error.message.clone()).with_span(Span::from_location(&error.location));
+        match &error.note {
+            Some(note) => diagnostic.with_note(note.clone()),
+            None => diagnostic,
+        }
+    }
+
+    /// a lexer or parser error, underlining the token it is about
+    pub fn from_syntax_error(code: &str, error: &SyntaxError) -> Self {
+        let location = &error.location;
+        Diagnostic::error(code, error.message.clone()).with_span(Span::new(&location.file, location.line, location.column + 1, error.length))
+    }
+
+    /// an error raised while the program ran, underlining the node it came from
+    pub fn from_runtime_error(error: &RuntimeError) -> Self {
+        let mut diagnostic = Diagnostic::error(codes::RUNTIME, error.message.clone());
+        if let Some(location) = &error.location {
+            diagnostic = diagnostic.with_span(Span::from_location(location));
+        }
+        diagnostic.notes.extend(error.note.clone());
+        diagnostic
+    }
+
+    /// one line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// the message, then each span under its source line; `read` gives the text of a file
+    pub fn render(&self, read: &dyn Fn(&str) -> Option<String>) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let mut out = format!("{}[{}]: {}\n", severity, self.code, self.message);
+
+        let mut marks: Vec<(&Span, char, &str)> = Vec::new();
+        if let Some(span) = &self.span {
+            marks.push((span, '^', ""));
+        }
+        marks.extend(self.labels.iter().map(|label| (&label.span, '-', label.message.as_str())));
+        let width = marks.iter().map(|(span, _, _)| span.start.line.to_string().len()).max().unwrap_or(0);
+        let gutter = " ".repeat(width);
+
+        let mut current_file: Option<&str> = None;
+        for (index, (span, marker, message)) in marks.iter().enumerate() {
+            let arrow = if index == 0 { "-->" } else { ":::" };
+            if current_file != Some(span.file.as_str()) || index == 0 {
+                out.push_str(&format!("{}{} {}:{}:{}\n", gutter, arrow, span.file, span.start.line, span.start.column));
+                current_file = Some(span.file.as_str());
+            }
+            let line = read(&span.file).and_then(|text| text.lines().nth(span.start.line.wrapping_sub(1)).map(str::to_string));
+            let Some(line) = line else {
+                continue;
+            };
+            let chars: Vec<char> = line.chars().collect();
+            let start = (span.start.column - 1).min(chars.len());
+            let end = if span.end.line == span.start.line { span.end.column - 1 } else { chars.len() };
+            let end = end.clamp(start + 1, chars.len().max(start + 1));
+            // keep tabs so the markers line up under the text
+            let indent: String = chars[..start].iter().map(|c| if *c == '\t' { '\t' } else { ' ' }).collect();
+            let markers = marker.to_string().repeat(end - start);
+            let label = if message.is_empty() { String::new() } else { format!(" {}", message) };
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{:>width$} | {}\n", span.start.line, line, width = width));
+            out.push_str(&format!("{} | {}{}{}\n", gutter, indent, markers, label));
+        }
+        if !self.notes.is_empty() && !marks.is_empty() {
+            out.push_str(&format!("{} |\n", gutter));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        out
+    }
+}
@@ -0,0 +1,571 @@
+// doc - `layman doc`: API documentation from the comments above declarations
+//
+// a block of `#` comment lines directly above `define function`, `define class` or `define type`
+// documents it. every file is a module with a page listing its functions (with the types the
+// checker worked out for them), its classes with their properties and methods, and its types with
+// their variants; class and type names link to the page of the module that defines them.
+// ``` fenced examples in a doc comment run as tests with --test, and their `=> ` lines are the
+// output the example must print
+
+use crate::ast::{ClassDeclaration, FunctionDeclaration, Node, Program, TypeDeclaration};
+use crate::diagnostics::{codes, Diagnostic};
+use crate::evaluator::Evaluator;
+use crate::cst::{self, SyntaxNode};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::resolver::ImportResolver;
+use crate::typechecker::{FunctionSignature, TypeChecker};
+use crate::types::RuntimeType;
+use crate::MessageFormat;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+}
+
+/// a run of text in a paragraph or list item
+enum Inline {
+    Text(String),
+    Code(String),
+    /// to a module's page, or to a declaration on it
+    Link { text: String, module: String, anchor: Option<String> },
+}
+
+enum Block {
+    Heading { level: usize, text: String, id: String },
+    Paragraph(Vec<Inline>),
+    List(Vec<Vec<Inline>>),
+    Code(String),
+}
+
+/// one file of the package
+struct Module {
+    name: String,
+    file: String,
+    ast: Node,
+    checker: TypeChecker,
+    /// the doc comment of each declaration, by the line the declaration starts on
+    docs: HashMap<usize, Vec<String>>,
+}
+
+/// a fenced example from a doc comment
+struct Example {
+    module: usize,
+    /// the declaration it documents
+    item: String,
+    /// the line of its opening fence
+    line: usize,
+    code: String,
+}
+
+/// `layman doc`: write a page per module and an index to `output`, or run the examples with `test`
+pub fn document(paths: &[String], format: DocFormat, output: &str, test: bool, message_format: MessageFormat) -> Result<(), String> {
+    let modules = load_modules(paths, message_format)?;
+    // where each class and type is declared, for links
+    let mut declared: HashMap<String, String> = HashMap::new();
+    for module in &modules {
+        for statement in top_level(&module.ast) {
+            match statement {
+                Node::ClassDeclaration(c) => declared.insert(c.name.clone(), module.name.clone()),
+                Node::TypeDeclaration(t) => declared.insert(t.name.clone(), module.name.clone()),
+                _ => None,
+            };
+        }
+    }
+    let mut writer = Writer { modules: &modules, declared, examples: Vec::new() };
+    let pages: Vec<(String, Vec<Block>)> = (0..modules.len()).map(|index| writer.module_page(index)).collect();
+
+    if test {
+        return run_examples(&modules, &writer.examples);
+    }
+    fs::create_dir_all(output).map_err(|e| format!("Could not create {}: {}", output, e))?;
+    let mut index = vec![Block::Heading { level: 1, text: "API documentation".to_string(), id: "api".to_string() }];
+    index.push(Block::List(
+        modules
+            .iter()
+            .map(|module| {
+                let mut item = vec![link(&module.name, &module.name, None)];
+                for (position, name) in top_level(&module.ast).iter().filter_map(declared_name).enumerate() {
+                    item.push(Inline::Text(if position == 0 { ": " } else { ", " }.to_string()));
+                    item.push(link(name, &module.name, Some(&anchor(name))));
+                }
+                item
+            })
+            .collect(),
+    ));
+    let mut all = vec![("index".to_string(), index)];
+    all.extend(pages);
+    for (name, blocks) in &all {
+        let text = match format {
+            DocFormat::Markdown => markdown(blocks),
+            DocFormat::Html => html(name, blocks),
+        };
+        let path = Path::new(output).join(format!("{}.{}", page_name(name), format.extension()));
+        fs::write(&path, text).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    }
+    println!("Documented {} module(s) in {}", modules.len(), output);
+    Ok(())
+}
+
+/// parse and type check every file; a module is named by its path under the directory given
+fn load_modules(paths: &[String], message_format: MessageFormat) -> Result<Vec<Module>, String> {
+    let mut modules = Vec::new();
+    let mut failures = 0;
+    for path in paths {
+        let root = Path::new(path);
+        let mut files = Vec::new();
+        crate::formatter::collect_files(root, &mut files)?;
+        files.sort();
+        for file in files {
+            let relative = if root.is_dir() { file.strip_prefix(root).unwrap_or(&file) } else { Path::new(file.file_name().unwrap_or_default()) };
+            let name = relative.with_extension("").to_string_lossy().replace('\\', "/");
+            match load_module(name, &file) {
+                Ok(module) => modules.push(module),
+                Err(diagnostic) => {
+                    crate::report(&diagnostic, message_format);
+                    failures += 1;
+                }
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{} file(s) could not be documented", failures));
+    }
+    Ok(modules)
+}
+
+fn load_module(name: String, path: &Path) -> Result<Module, Box<Diagnostic>> {
+    let file = path.to_string_lossy().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|e| Box::new(Diagnostic::error(codes::IO, format!("Could not read {}: {}", file, e))))?;
+    let tree = cst::parse(&source, &file).map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::SYNTAX, &e)))?;
+    let mut docs = HashMap::new();
+    doc_comments(&tree.root, &mut docs);
+    let ast = tree.ast;
+
+    // types come from the whole program, imports included; a file with type errors is still documented
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+    let mut checker = TypeChecker::new();
+    if let Ok(Node::Program(program)) = ImportResolver::new(base_dir).resolve_and_bundle(&ast) {
+        let _ = checker.check_program(&program);
+    }
+    Ok(Module { name, file, ast, checker, docs })
+}
+
+/// the comments directly above each statement, nested ones included
+fn doc_comments(node: &SyntaxNode, docs: &mut HashMap<usize, Vec<String>>) {
+    for child in node.child_nodes() {
+        let comments = child.leading_comments();
+        if let (Some(token), false) = (child.first_token(), comments.is_empty()) {
+            docs.insert(token.location.line, comments.iter().map(|c| c.to_string()).collect());
+        }
+        doc_comments(child, docs);
+    }
+}
+
+fn top_level(ast: &Node) -> &[Node] {
+    match ast {
+        Node::Program(program) => &program.statements,
+        _ => std::slice::from_ref(ast),
+    }
+}
+
+/// the name of a function, class or type declaration
+fn declared_name(statement: &Node) -> Option<&String> {
+    match statement {
+        Node::FunctionDeclaration(f) => Some(&f.name),
+        Node::ClassDeclaration(c) => Some(&c.name),
+        Node::TypeDeclaration(t) => Some(&t.name),
+        _ => None,
+    }
+}
+
+/// "geometry/circle" is written to geometry.circle.md
+fn page_name(module: &str) -> String {
+    module.replace('/', ".")
+}
+
+fn link(text: &str, module: &str, anchor: Option<&str>) -> Inline {
+    Inline::Link { text: text.to_string(), module: module.to_string(), anchor: anchor.map(str::to_string) }
+}
+
+fn anchor(name: &str) -> String {
+    name.to_lowercase().replace('.', "-")
+}
+
+struct Writer<'a> {
+    modules: &'a [Module],
+    declared: HashMap<String, String>,
+    examples: Vec<Example>,
+}
+
+impl Writer<'_> {
+    fn module_page(&mut self, index: usize) -> (String, Vec<Block>) {
+        let module = &self.modules[index];
+        let mut blocks = vec![
+            Block::Heading { level: 1, text: format!("Module {}", module.name), id: "module".to_string() },
+            Block::Paragraph(vec![Inline::Code(module.file.clone())]),
+        ];
+        let statements = top_level(&module.ast);
+        let imports: Vec<Inline> = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Node::ImportStatement(import) => Some(self.module_link(&import.module_name)),
+                _ => None,
+            })
+            .collect();
+        if !imports.is_empty() {
+            let mut paragraph = vec![Inline::Text("Imports: ".to_string())];
+            for (position, import) in imports.into_iter().enumerate() {
+                if position > 0 {
+                    paragraph.push(Inline::Text(", ".to_string()));
+                }
+                paragraph.push(import);
+            }
+            blocks.push(Block::Paragraph(paragraph));
+        }
+
+        let functions: Vec<&FunctionDeclaration> =
+            statements.iter().filter_map(|s| if let Node::FunctionDeclaration(f) = s { Some(f) } else { None }).collect();
+        let classes: Vec<&ClassDeclaration> =
+            statements.iter().filter_map(|s| if let Node::ClassDeclaration(c) = s { Some(c) } else { None }).collect();
+        let types: Vec<&TypeDeclaration> =
+            statements.iter().filter_map(|s| if let Node::TypeDeclaration(t) = s { Some(t) } else { None }).collect();
+
+        if !functions.is_empty() {
+            blocks.push(Block::Heading { level: 2, text: "Functions".to_string(), id: "functions".to_string() });
+            for function in functions {
+                blocks.push(Block::Heading { level: 3, text: function.name.clone(), id: anchor(&function.name) });
+                let signature = module.checker.function_signature(&function.name);
+                blocks.push(self.signature(index, function, signature.as_ref()));
+                self.doc(index, &function.name, function.location.line, &mut blocks);
+            }
+        }
+        if !classes.is_empty() {
+            blocks.push(Block::Heading { level: 2, text: "Classes".to_string(), id: "classes".to_string() });
+            for class in classes {
+                self.class(index, class, &mut blocks);
+            }
+        }
+        if !types.is_empty() {
+            blocks.push(Block::Heading { level: 2, text: "Types".to_string(), id: "types".to_string() });
+            for declaration in types {
+                blocks.push(Block::Heading { level: 3, text: declaration.name.clone(), id: anchor(&declaration.name) });
+                self.doc(index, &declaration.name, declaration.location.line, &mut blocks);
+                let variants = declaration
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let mut item = vec![Inline::Code(variant.name.clone())];
+                        for (position, (field, ty)) in variant.fields.iter().enumerate() {
+                            item.push(Inline::Text(if position == 0 { " with " } else { " and " }.to_string()));
+                            item.push(Inline::Code(field.clone()));
+                            item.push(Inline::Text(" as ".to_string()));
+                            item.push(self.named_type(ty));
+                        }
+                        item
+                    })
+                    .collect();
+                blocks.push(Block::Paragraph(vec![Inline::Text("Variants:".to_string())]));
+                blocks.push(Block::List(variants));
+            }
+        }
+        (module.name.clone(), blocks)
+    }
+
+    fn class(&mut self, index: usize, class: &ClassDeclaration, blocks: &mut Vec<Block>) {
+        let module = &self.modules[index];
+        blocks.push(Block::Heading { level: 3, text: class.name.clone(), id: anchor(&class.name) });
+        if let Some(parent) = &class.extends {
+            blocks.push(Block::Paragraph(vec![Inline::Text("Extends ".to_string()), self.named_type(parent)]));
+        }
+        self.doc(index, &class.name, class.location.line, blocks);
+        let members = module.checker.class_members(&class.name);
+        if !class.properties.is_empty() {
+            let properties = class
+                .properties
+                .iter()
+                .map(|property| {
+                    let ty = members.as_ref().and_then(|members| members.properties.get(&property.name));
+                    let mut item = vec![Inline::Code(property.name.clone()), Inline::Text(" as ".to_string())];
+                    item.extend(self.type_inlines(index, ty.unwrap_or(&RuntimeType::Any)));
+                    item
+                })
+                .collect();
+            blocks.push(Block::Paragraph(vec![Inline::Text("Properties:".to_string())]));
+            blocks.push(Block::List(properties));
+        }
+        for method in &class.methods {
+            let name = format!("{}.{}", class.name, method.name);
+            blocks.push(Block::Heading { level: 4, text: name.clone(), id: anchor(&name) });
+            let signature = members.as_ref().and_then(|members| members.methods.get(&method.name));
+            blocks.push(self.signature(index, method, signature));
+            self.doc(index, &name, method.location.line, blocks);
+        }
+    }
+
+    /// "takes `width` as number and `height` as number, returns number"
+    fn signature(&self, index: usize, function: &FunctionDeclaration, signature: Option<&FunctionSignature>) -> Block {
+        let parameters: Vec<(String, RuntimeType)> = match signature {
+            Some(signature) => signature.parameters.clone(),
+            None => function.parameters.iter().map(|p| (p.name.clone(), RuntimeType::Any)).collect(),
+        };
+        let mut inlines = vec![Inline::Text("takes ".to_string())];
+        // a method's `self` is the object it is called on, not an argument
+        let parameters: Vec<&(String, RuntimeType)> = parameters.iter().filter(|(name, _)| name != "self").collect();
+        if parameters.is_empty() {
+            inlines.push(Inline::Text("nothing".to_string()));
+        }
+        for (position, (name, ty)) in parameters.into_iter().enumerate() {
+            if position > 0 {
+                inlines.push(Inline::Text(" and ".to_string()));
+            }
+            inlines.push(Inline::Code(name.clone()));
+            inlines.push(Inline::Text(" as ".to_string()));
+            // the checker leaves an untyped method parameter as nothing; it takes any value
+            let ty = if *ty == RuntimeType::Void { &RuntimeType::Any } else { ty };
+            inlines.extend(self.type_inlines(index, ty));
+        }
+        inlines.push(Inline::Text(", returns ".to_string()));
+        match signature {
+            Some(signature) => inlines.extend(self.type_inlines(index, &signature.return_type)),
+            None => inlines.push(Inline::Text("any".to_string())),
+        }
+        Block::Paragraph(inlines)
+    }
+
+    /// a type as it reads, with the classes and types in it linked to where they are declared
+    fn type_inlines(&self, index: usize, ty: &RuntimeType) -> Vec<Inline> {
+        let prefixed = |prefix: &str, inner: &RuntimeType| {
+            let mut inlines = vec![Inline::Text(prefix.to_string())];
+            inlines.extend(self.type_inlines(index, inner));
+            inlines
+        };
+        match ty {
+            RuntimeType::Object(name) | RuntimeType::Class(name) | RuntimeType::Variant(name) => vec![self.named_type(name)],
+            RuntimeType::List(inner) => prefixed("list of ", inner),
+            RuntimeType::Maybe(inner) => prefixed("maybe ", inner),
+            RuntimeType::Future(inner) => prefixed("future of ", inner),
+            ty => vec![Inline::Text(self.modules[index].checker.type_to_string(ty))],
+        }
+    }
+
+    fn named_type(&self, name: &str) -> Inline {
+        match self.declared.get(name) {
+            Some(module) => link(name, module, Some(&anchor(name))),
+            None => Inline::Text(name.to_string()),
+        }
+    }
+
+    /// an import as a link to the module's page, when it is part of this package
+    fn module_link(&self, import: &str) -> Inline {
+        let name = import.trim_start_matches("./").trim_end_matches(".lay");
+        match self.modules.iter().find(|m| m.name == name || m.name.ends_with(&format!("/{}", name))) {
+            Some(module) => link(&module.name, &module.name, None),
+            None => Inline::Code(import.to_string()),
+        }
+    }
+
+    /// the doc comment above `line`: paragraphs, and fenced examples that are kept for --test
+    fn doc(&mut self, index: usize, item: &str, line: usize, blocks: &mut Vec<Block>) {
+        let Some(comments) = self.modules[index].docs.get(&line) else {
+            return;
+        };
+        // the comment lines run up to the declaration, so the first is this far above it
+        let first = line - comments.len();
+        let mut paragraph: Vec<String> = Vec::new();
+        let mut fence: Option<(usize, Vec<String>)> = None;
+        for (number, comment) in (first..line).zip(comments) {
+            let text = comment.trim_start_matches('#');
+            let text = text.strip_prefix(' ').unwrap_or(text);
+            // directives for `layman lint` are not documentation
+            if fence.is_none() && text.trim_start().starts_with("lint:") {
+                continue;
+            }
+            if text.trim_start().starts_with("```") {
+                match fence.take() {
+                    Some((start, code)) => {
+                        let code = code.join("\n") + "\n";
+                        blocks.push(Block::Code(code.clone()));
+                        self.examples.push(Example { module: index, item: item.to_string(), line: start, code });
+                    }
+                    None => {
+                        flush(&mut paragraph, blocks);
+                        fence = Some((number, Vec::new()));
+                    }
+                }
+            } else if let Some((_, code)) = &mut fence {
+                code.push(text.to_string());
+            } else if text.trim().is_empty() {
+                flush(&mut paragraph, blocks);
+            } else {
+                paragraph.push(text.trim().to_string());
+            }
+        }
+        flush(&mut paragraph, blocks);
+    }
+}
+
+fn flush(paragraph: &mut Vec<String>, blocks: &mut Vec<Block>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(vec![Inline::Text(paragraph.join(" "))]));
+        paragraph.clear();
+    }
+}
+
+fn markdown(blocks: &[Block]) -> String {
+    let inline = |inlines: &[Inline]| -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => text.clone(),
+                Inline::Code(code) => format!("`{}`", code),
+                Inline::Link { text, module, anchor } => {
+                    format!("[{}]({}.md{})", text, page_name(module), anchor.as_ref().map_or(String::new(), |a| format!("#{}", a)))
+                }
+            })
+            .collect()
+    };
+    let mut out = Vec::new();
+    for block in blocks {
+        out.push(match block {
+            Block::Heading { level, text, id } => format!("{} {} <a id=\"{}\"></a>", "#".repeat(*level), text, id),
+            Block::Paragraph(inlines) => inline(inlines),
+            Block::List(items) => items.iter().map(|item| format!("- {}", inline(item))).collect::<Vec<_>>().join("\n"),
+            Block::Code(code) => format!("```\n{}```", code),
+        });
+    }
+    out.join("\n\n") + "\n"
+}
+
+fn html(title: &str, blocks: &[Block]) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    // `code` in doc comments, as in Markdown
+    let text = |text: &str| {
+        text.split('`')
+            .enumerate()
+            .map(|(i, part)| if i % 2 == 1 { format!("<code>{}</code>", escape(part)) } else { escape(part) })
+            .collect::<String>()
+    };
+    let inline = |inlines: &[Inline]| -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(t) => text(t),
+                Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+                Inline::Link { text, module, anchor } => format!(
+                    "<a href=\"{}.html{}\">{}</a>",
+                    page_name(module),
+                    anchor.as_ref().map_or(String::new(), |a| format!("#{}", a)),
+                    escape(text)
+                ),
+            })
+            .collect()
+    };
+    let mut body = String::new();
+    for block in blocks {
+        body.push_str(&match block {
+            Block::Heading { level, text, id } => format!("<h{0} id=\"{1}\">{2}</h{0}>\n", level, id, escape(text)),
+            Block::Paragraph(inlines) => format!("<p>{}</p>\n", inline(inlines)),
+            Block::List(items) => {
+                let items: String = items.iter().map(|item| format!("<li>{}</li>\n", inline(item))).collect();
+                format!("<ul>\n{}</ul>\n", items)
+            }
+            Block::Code(code) => format!("<pre><code>{}</code></pre>\n", escape(code)),
+        });
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: sans-serif; max-width: 48em; margin: 2em auto; line-height: 1.5; }}\n\
+         code, pre {{ background: #f4f4f4; }}\npre {{ padding: 0.5em; }}\n</style>\n</head>\n<body>\n\
+         <p><a href=\"index.html\">Index</a></p>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+/// run every example after the declarations of its module; `=> ` lines are the output it must print
+fn run_examples(modules: &[Module], examples: &[Example]) -> Result<(), String> {
+    let mut failed = 0;
+    for example in examples {
+        let module = &modules[example.module];
+        let place = format!("{}:{} ({})", module.file, example.line, example.item);
+        match run_example(module, example) {
+            Ok(()) => println!("test {} ... ok", place),
+            Err(e) => {
+                println!("test {} ... FAILED\n  {}", place, e.replace('\n', "\n  "));
+                failed += 1;
+            }
+        }
+    }
+    println!("\n{} example(s): {} passed, {} failed", examples.len(), examples.len() - failed, failed);
+    if failed > 0 {
+        return Err(format!("{} example(s) failed", failed));
+    }
+    Ok(())
+}
+
+fn run_example(module: &Module, example: &Example) -> Result<(), String> {
+    let (expected, code): (Vec<&str>, Vec<&str>) = example.code.lines().partition(|line| line.trim_start().starts_with("=> "));
+    let expected: Vec<&str> = expected.iter().map(|line| line.trim_start().trim_start_matches("=> ")).collect();
+    let label = format!("{} example on line {}", module.file, example.line);
+    let tokens = Lexer::new(&(code.join("\n") + "\n"), label).tokenize()?;
+    let example_ast = Parser::new(tokens).parse()?;
+
+    // the module's declarations, without the statements it runs itself
+    let mut statements: Vec<Node> = top_level(&module.ast)
+        .iter()
+        .filter(|s| {
+            matches!(
+                s,
+                Node::FunctionDeclaration(_)
+                    | Node::ClassDeclaration(_)
+                    | Node::TypeDeclaration(_)
+                    | Node::StructDeclaration(_)
+                    | Node::ImportStatement(_)
+            )
+        })
+        .cloned()
+        .collect();
+    statements.extend(top_level(&example_ast).iter().cloned());
+    let location = match &module.ast {
+        Node::Program(program) => program.location.clone(),
+        node => node.location(),
+    };
+    let program = Node::Program(Program { location, statements });
+
+    let base_dir = Path::new(&module.file).parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+    let bundled = ImportResolver::new(base_dir).resolve_and_bundle(&program).map_err(|e| e.message)?;
+    if let Node::Program(program) = &bundled {
+        TypeChecker::new().check_program(program).map_err(|errors| {
+            errors.iter().map(|e| format!("line {}: {}", e.location.line, e.message)).collect::<Vec<_>>().join("\n")
+        })?;
+    }
+
+    let printed = Arc::new(Mutex::new(Vec::new()));
+    let mut evaluator = Evaluator::new();
+    evaluator.set_output_callback({
+        let printed = Arc::clone(&printed);
+        move |line| printed.lock().unwrap().push(line.to_string())
+    });
+    evaluator.evaluate(&bundled)?;
+    let printed = printed.lock().unwrap();
+    if !expected.is_empty() && *printed != expected {
+        return Err(format!("expected:\n{}\nprinted:\n{}", expected.join("\n"), printed.join("\n")));
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// error - what went wrong while a program ran, and where
+// errors start as a message; the evaluator locates each at the innermost node it passes through
+
+use crate::ast::{Location, Node};
+use crate::lexer::SyntaxError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// the node that failed; None until the error leaves it
+    pub location: Option<Location>,
+    /// a hint shown under the message, such as a close match for a misspelt name
+    pub note: Option<String>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        RuntimeError { message: message.into(), location: None, note: None }
+    }
+
+    pub fn with_note(mut self, note: Option<String>) -> Self {
+        self.note = note;
+        self
+    }
+
+    /// the error at `node`, unless a node inside it has already located it
+    pub fn at(mut self, node: &Node) -> Self {
+        if self.location.is_none() {
+            self.location = Some(node.location());
+        }
+        self
+    }
+}
+
+/// the standard library reports errors as text
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        RuntimeError::new(message)
+    }
+}
+
+/// a file run or loaded while the program runs could not be read as layman
+impl From<SyntaxError> for RuntimeError {
+    fn from(error: SyntaxError) -> Self {
+        RuntimeError { message: error.message, location: Some(error.location), note: None }
+    }
+}
+
+/// "file:line:column: message" like a syntax error, or the message alone when it has no location
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}:{}:{}: {}", location.file, location.line, location.column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// for callers that pass errors on as text
+impl From<RuntimeError> for String {
+    fn from(error: RuntimeError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,58 @@
+// hook - lets tools watch a program while the evaluator runs it
+// the debugger and the tracer plug in here; without a hook the evaluator does no extra work
+
+use crate::ast::{LiteralExpression, LiteralValue, Location, Node};
+use crate::types::{Environment, Value};
+use std::sync::Arc;
+
+/// told about each statement and call as the program runs. the calls happen on the thread
+/// running the code, so a hook may block it (a debugger waiting at a breakpoint does)
+pub trait ExecutionHook: Send + Sync {
+    /// a statement is about to run in `environment`
+    fn before_statement(&self, statement: &Node, location: &Location, environment: &Arc<Environment>);
+    /// a user function or method is about to run its body, with these parameter values
+    fn enter_function(&self, _name: &str, _arguments: &[(String, Value)]) {}
+    /// the function entered last has finished, with its result, or None when it failed
+    fn exit_function(&self, _name: &str, _result: Option<&Value>) {}
+    /// a variable was bound to `new`; `old` is the value it replaced, if it had one
+    fn variable_changed(&self, _name: &str, _old: Option<&Value>, _new: &Value) {}
+    /// the condition of the `if` at `location` was worked out; `has_otherwise` tells whether
+    /// a false condition runs an `otherwise` branch or nothing
+    fn branch_taken(&self, _location: &Location, _condition: bool, _has_otherwise: bool) {}
+}
+
+/// a value as tools watching the program show it; text is quoted so it stands apart from
+/// numbers and names
+pub fn describe(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    }
+}
+
+/// where a statement starts, for the statements a hook is told about; None for the nodes that
+/// are not statements of their own (expressions, declarations, and lines skipped as prose)
+pub fn statement_location(node: &Node) -> Option<Location> {
+    match node {
+        // the statement's own location can be the line break before it; its expression's is exact
+        Node::ExpressionStatement(stmt) => match stmt.expression.as_ref() {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }) => None,
+            expression => Some(expression.location()),
+        },
+        Node::AssignStatement(_)
+        | Node::DeclareStatement(_)
+        | Node::SetStatement(_)
+        | Node::IndexSetStatement(_)
+        | Node::ConditionalStatement(_)
+        | Node::LoopStatement(_)
+        | Node::ReturnStatement(_)
+        | Node::ImportStatement(_)
+        | Node::ThrowStatement(_)
+        | Node::TryCatchStatement(_)
+        | Node::UsingStatement(_)
+        | Node::InspectStatement(_)
+        | Node::RunConcurrentlyStatement(_)
+        | Node::TestStatement(_) => Some(node.location()),
+        _ => None,
+    }
+}
@@ -1,9 +1,12 @@
 // evaluator - executes AST nodes
 // interprets the layman AST
 
+pub mod error;
+pub mod hook;
+
 use crate::ast::*;
-use crate::types::{Value, ObjectData, Environment};
-use crate::{lexer, parser, resolver, typechecker};
+use crate::types::{frozen, Value, ObjectData, Environment, Shared, DICTIONARY_METHODS, LIST_METHODS};
+use crate::{lexer, parser, resolver, suggest, typechecker};
 use std::collections::HashMap;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -12,12 +15,23 @@ use std::path::Path;
 use std::thread;
 use std::sync::{Arc, Mutex};
 use crate::types::FutureState;
+use crate::stdlib::http::{PlainHttpTransport, Transport};
+use crate::stdlib::io::{InputSource, StdinInput};
+use crate::stdlib::time::{Clock, SystemClock};
+pub use error::RuntimeError;
+use hook::ExecutionHook;
 
 #[derive(Clone)]
 pub struct Evaluator {
     environment: Arc<Environment>,
     loaded_modules: Arc<Mutex<HashMap<String, Arc<Environment>>>>,
     output_callback: Option<Arc<dyn Fn(&str) + Send + Sync>>,
+    clock: Arc<dyn Clock>,
+    input: Arc<dyn InputSource>,
+    transport: Arc<dyn Transport>,
+    program_arguments: Arc<Vec<String>>,
+    exit_code: Arc<Mutex<Option<i32>>>,
+    hook: Option<Arc<dyn ExecutionHook>>,
 }
 
 impl Default for Evaluator {
@@ -34,6 +48,12 @@ impl Evaluator {
             environment: Environment::new(),
             loaded_modules: Arc::new(Mutex::new(HashMap::new())),
             output_callback: None,
+            clock: Arc::new(SystemClock),
+            input: Arc::new(StdinInput),
+            transport: Arc::new(PlainHttpTransport::default()),
+            program_arguments: Arc::new(Vec::new()),
+            exit_code: Arc::new(Mutex::new(None)),
+            hook: None,
         }
     }
     
@@ -45,6 +65,12 @@ impl Evaluator {
             environment: Environment::new(),
             loaded_modules: Arc::new(Mutex::new(HashMap::new())),
             output_callback: Some(Arc::new(callback)),
+            clock: Arc::new(SystemClock),
+            input: Arc::new(StdinInput),
+            transport: Arc::new(PlainHttpTransport::default()),
+            program_arguments: Arc::new(Vec::new()),
+            exit_code: Arc::new(Mutex::new(None)),
+            hook: None,
         }
     }
     
@@ -54,8 +80,75 @@ impl Evaluator {
     {
         self.output_callback = Some(Arc::new(callback));
     }
+
+    /// replace the clock that `now`, `today` and the time library read from
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// replace where `ask`, `read all input` and `the lines of input` read from
+    #[allow(dead_code)]
+    pub fn set_input(&mut self, input: Arc<dyn InputSource>) {
+        self.input = input;
+    }
+
+    /// replace how `fetch` and `send` reach the network
+    #[allow(dead_code)]
+    pub fn set_transport(&mut self, transport: Arc<dyn Transport>) {
+        self.transport = transport;
+    }
+
+    /// watch the program as it runs, statement by statement (see `hook`)
+    pub fn set_hook(&mut self, hook: Arc<dyn ExecutionHook>) {
+        self.hook = Some(hook);
+    }
+
+    /// arguments passed after the program (`layman run file.lay -- a b c`)
+    pub fn set_program_arguments(&mut self, arguments: Vec<String>) {
+        self.program_arguments = Arc::new(arguments);
+    }
+
+    /// the status requested by `exit with code N`, once the program has exited
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock().unwrap()
+    }
+
+    /// an evaluator for a nested scope (function body, module, background task)
+    /// that shares this evaluator's module cache, output, input, network and clock
+    fn child(&self, environment: Arc<Environment>) -> Evaluator {
+        Evaluator {
+            environment,
+            ..self.clone()
+        }
+    }
+
+    /// "did you mean" for a name that is not defined, from the names in scope
+    fn name_hint(&self, name: &str) -> Option<String> {
+        let names = self.environment.names();
+        suggest::hint(name, names.iter().map(String::as_str))
+    }
     
-     fn _evaluate_print(&mut self, args: &[Node]) -> Result<Value, String> {
+    /// one pass of a `for each` body with `iterator` bound to `item` in a new scope; the
+    /// returned value when the body ran a `return`
+    fn run_iteration(&mut self, iterator: &str, item: Value, body: &Node) -> Result<Option<Value>, RuntimeError> {
+        let loop_env = Environment::with_parent(self.environment.clone());
+        if let Some(hook) = &self.hook {
+            hook.variable_changed(iterator, None, &item);
+        }
+        loop_env.define(iterator.to_string(), item, true);
+
+        let previous_env = self.environment.clone();
+        self.environment = loop_env;
+        let result = self.evaluate(body);
+        self.environment = previous_env;
+
+        match result? {
+            val @ Value::Return(_) => Ok(Some(val)),
+            _ => Ok(None),
+        }
+    }
+
+     fn _evaluate_print(&mut self, args: &[Node]) -> Result<Value, RuntimeError> {
         // println!("DEBUG: evaluate_print with {} args", args.len());
         let mut output = String::new();
         for (i, arg_node) in args.iter().enumerate() {
@@ -74,7 +167,18 @@ impl Evaluator {
         Ok(Value::Void)
     }
 
-    pub fn evaluate(&mut self, node: &Node) -> Result<Value, String> {
+    /// run a node; an error that comes out of it carries the location of the innermost node it
+    /// came through
+    pub fn evaluate(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        self.evaluate_node(node).map_err(|e| e.at(node))
+    }
+
+    fn evaluate_node(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        if let Some(hook) = &self.hook {
+            if let Some(location) = hook::statement_location(node) {
+                hook.before_statement(node, &location, &self.environment);
+            }
+        }
         match node {
             Node::Program(program) => {
                 let mut result = Value::Void;
@@ -89,23 +193,31 @@ impl Evaluator {
             }
             
             Node::AssignStatement(assign) => {
-                let value = self.evaluate(&assign.expression)?;
-                // try to update existing variable in scope chain
+                let value = frozen::bind(self.evaluate(&assign.expression)?, &assign.identifier, assign.is_mutable);
+                let old = self.hook.as_ref().and_then(|_| self.environment.get(&assign.identifier));
                 // try to update existing variable in scope chain
                 match self.environment.assign(assign.identifier.clone(), value.clone()) {
-                    Ok(true) => Ok(value),
+                    Ok(true) => {}
                     Ok(false) => {
                         // if not found, define in current scope
                         // use assign.is_mutable to determine if it's a constant declaration
                         self.environment.define(assign.identifier.clone(), value.clone(), assign.is_mutable);
-                        Ok(value)
                     }
-                    Err(e) => Err(e),
+                    Err(e) => return Err(e.into()),
+                }
+                if let Some(hook) = &self.hook {
+                    hook.variable_changed(&assign.identifier, old.as_ref(), &value);
                 }
+                Ok(value)
             }
             
             Node::DeclareStatement(decl) => {
-                let value = self.evaluate(&decl.value)?;
+                let value = frozen::bind(self.evaluate(&decl.value)?, &decl.name, decl.is_mutable);
+                if let Some(hook) = &self.hook {
+                    // declaring a name again in the same scope replaces it; an outer one is only shadowed
+                    let old = self.environment.variables.lock().unwrap().get(&decl.name).map(|(old, _)| old.clone());
+                    hook.variable_changed(&decl.name, old.as_ref(), &value);
+                }
                 self.environment.define(decl.name.clone(), value, decl.is_mutable);
                 Ok(Value::Void)
             }
@@ -117,14 +229,16 @@ impl Evaluator {
                 
                 match object {
                     Value::Object(data) => {
+                        frozen::check_mutable(&data)?;
                         data.lock().unwrap().properties.insert(set_stmt.property.clone(), value.clone());
                         Ok(value)
                     }
                     Value::Dictionary(map) => {
+                        frozen::check_mutable(&map)?;
                         map.lock().unwrap().insert(set_stmt.property.clone(), value.clone());
                         Ok(value)
                     }
-                    _ => Err("Cannot set property on non-object".to_string()),
+                    _ => Err("Cannot set property on non-object".to_string().into()),
                 }
             }
             
@@ -139,28 +253,30 @@ impl Evaluator {
                 
                 match object {
                     Value::List(items) => {
+                        frozen::check_mutable(&items)?;
                         let idx = match index {
                             Value::Number(n) => n as usize,
-                            _ => return Err("List index must be a number".to_string()),
+                            _ => return Err("List index must be a number".to_string().into()),
                         };
                         
                         if idx == 0 {
-                            return Err("List index starts at 1".to_string());
+                            return Err("List index starts at 1".to_string().into());
                         }
                         
                         if idx > items.lock().unwrap().len() {
-                            return Err(format!("List index {} out of bounds (length {})", idx, items.lock().unwrap().len()));
+                            return Err(format!("List index {} out of bounds (length {})", idx, items.lock().unwrap().len()).into());
                         }
                         
                         items.lock().unwrap()[idx - 1] = value.clone();
                         Ok(value)
                     }
                     Value::Dictionary(map) => {
+                        frozen::check_mutable(&map)?;
                         let key = index.to_string();
                         map.lock().unwrap().insert(key, value.clone());
                         Ok(value)
                     }
-                    _ => Err("Cannot index assign non-list/dictionary".to_string()),
+                    _ => Err("Cannot index assign non-list/dictionary".to_string().into()),
                 }
             }
             
@@ -179,9 +295,13 @@ impl Evaluator {
                     Value::Return(_) => false, // should not happen in condition
                     Value::Future(_) => true,
                     Value::Variant(_, _, _) => true,
+                    Value::Date(_) | Value::Duration(_) => true,
                     Value::Nothing => false,
                 };
                 
+                if let Some(hook) = &self.hook {
+                    hook.branch_taken(&cond.location, is_true, cond.else_branch.is_some());
+                }
                 if is_true {
                     self.evaluate(&cond.then_branch)
                 } else if let Some(else_branch) = &cond.else_branch {
@@ -195,11 +315,22 @@ impl Evaluator {
                 match &loop_stmt.loop_type {
                     LoopType::ForEach => {
                         if let (Some(_iterator), Some(collection)) = (&loop_stmt.iterator, &loop_stmt.collection) {
+                            // the lines of input are read as the loop reaches them, so each line is
+                            // handled as it arrives and the input is never held all at once
+                            if matches!(collection.as_ref(), Node::CallExpression(call) if call.function_name == "__io_input_lines") {
+                                let input = self.input.clone();
+                                while let Some(line) = input.read_line() {
+                                    if let Some(val) = self.run_iteration(_iterator, Value::String(line), &loop_stmt.body)? {
+                                        return Ok(val);
+                                    }
+                                }
+                                return Ok(Value::Void);
+                            }
                             let collection_value = self.evaluate(collection)?;
                             if let Value::List(items) = collection_value {
                                 const MAX_ITEMS: usize = 100000; // prevent infinite loops
                                 if items.lock().unwrap().len() > MAX_ITEMS {
-                                    return Err(format!("for each loop has too many items (max {})", MAX_ITEMS));
+                                    return Err(format!("for each loop has too many items (max {})", MAX_ITEMS).into());
                                 }
                                 // we need to clone the items to avoid holding a borrow across iterations if possible,
                                 // or just iterate over the borrow. But we modify environment, so we can't hold borrow?
@@ -208,28 +339,16 @@ impl Evaluator {
                                 // So we should clone the vector or the items.
                                 let items_vec: Vec<Value> = items.lock().unwrap().clone();
                                 for item in items_vec {
-                                    // create new scope
-                                    let loop_env = Environment::with_parent(self.environment.clone());
-                                    // define iterator (mutable by default for loops?)
-                                    loop_env.define(_iterator.clone(), item, true);
-                                    
-                                    // execute body in new scope
-                                    let previous_env = self.environment.clone();
-                                    self.environment = loop_env;
-                                    let result = self.evaluate(&loop_stmt.body);
-                                    self.environment = previous_env;
-                                    
-                                    let val = result?;
-                                    if let Value::Return(_) = val {
+                                    if let Some(val) = self.run_iteration(_iterator, item, &loop_stmt.body)? {
                                         return Ok(val);
                                     }
                                 }
                                 Ok(Value::Void)
                             } else {
-                                Err("for each requires a list".to_string())
+                                Err("for each requires a list".to_string().into())
                             }
                         } else {
-                            Err("for each requires iterator and collection".to_string())
+                            Err("for each requires iterator and collection".to_string().into())
                         }
                     }
                     LoopType::While => {
@@ -239,7 +358,7 @@ impl Evaluator {
                             loop {
                                 iterations += 1;
                                 if iterations > MAX_ITERATIONS {
-                                    return Err("while loop exceeded maximum iterations (100000)".to_string());
+                                    return Err("while loop exceeded maximum iterations (100000)".to_string().into());
                                 }
                                 
                                 let cond_value = self.evaluate(condition)?;
@@ -256,6 +375,7 @@ impl Evaluator {
                                     Value::Return(_) => false,
                                     Value::Future(_) => true,
                                     Value::Variant(_, _, _) => true,
+                                    Value::Date(_) | Value::Duration(_) => true,
                                     Value::Nothing => false,
                                 };
                                 if !is_true {
@@ -268,7 +388,7 @@ impl Evaluator {
                             }
                             Ok(Value::Void)
                         } else {
-                            Err("while loop requires condition".to_string())
+                            Err("while loop requires condition".to_string().into())
                         }
                     }
                     LoopType::Repeat => {
@@ -308,7 +428,10 @@ impl Evaluator {
                     Some(val) => {
                         Ok(val)
                     },
-                    None => Err(format!("Variable '{}' not defined", var.identifier)),
+                    None => match crate::stdlib::time::read_clock(&var.identifier, self.clock.as_ref()) {
+                        Some(time) => Ok(time),
+                        None => Err(RuntimeError::new(format!("Variable '{}' not defined", var.identifier)).with_note(self.name_hint(&var.identifier))),
+                    },
                 }
             }
             
@@ -334,7 +457,8 @@ impl Evaluator {
                         if let Some(value) = data.properties.get(&access.property) {
                             Ok(value.clone())
                         } else {
-                            Err(format!("Property '{}' not found on object", access.property))
+                            let hint = suggest::hint(&access.property, data.properties.keys().map(String::as_str));
+                            Err(RuntimeError::new(format!("Property '{}' not found on object", access.property)).with_note(hint))
                         }
                     }
                     Value::Dictionary(map) => {
@@ -347,7 +471,7 @@ impl Evaluator {
                         if let Some(value) = map.lock().unwrap().get(&key) {
                             Ok(value.clone())
                         } else {
-                            Ok(Value::Void) // or error?
+                            Err(crate::stdlib::dictionary::missing_key(&key).into())
                         }
                     }
                     Value::List(list) => {
@@ -355,10 +479,10 @@ impl Evaluator {
                          if matches!(key.as_str(), "size" | "length" | "count") {
                              Ok(Value::Number(list.lock().unwrap().len() as f64))
                          } else {
-                             Err(format!("Property '{}' not found on List", key))
+                             Err(RuntimeError::new(format!("Property '{}' not found on List", key)).with_note(suggest::hint(&key, ["size", "length", "count"])))
                          }
                     }
-                    _ => Err(format!("Cannot access property '{}' on non-object/dictionary/list: {}", access.property, object.to_string())),
+                    _ => Err(format!("Cannot access property '{}' on non-object/dictionary/list: {}", access.property, object.to_string()).into()),
                 }
             }
             
@@ -373,15 +497,15 @@ impl Evaluator {
                         // Let's support 1-based for now as it's more "layman".
                         let idx = match index {
                             Value::Number(n) => n as usize,
-                            _ => return Err("List index must be a number".to_string()),
+                            _ => return Err("List index must be a number".to_string().into()),
                         };
                         
                         if idx == 0 {
-                            return Err("List index starts at 1".to_string());
+                            return Err("List index starts at 1".to_string().into());
                         }
                         
                         if idx > items.lock().unwrap().len() {
-                            return Err(format!("List index {} out of bounds (length {})", idx, items.lock().unwrap().len()));
+                            return Err(format!("List index {} out of bounds (length {})", idx, items.lock().unwrap().len()).into());
                         }
                         
                         Ok(items.lock().unwrap()[idx - 1].clone())
@@ -391,10 +515,10 @@ impl Evaluator {
                         if let Some(value) = map.lock().unwrap().get(&key) {
                             Ok(value.clone())
                         } else {
-                            Ok(Value::Void)
+                            Err(crate::stdlib::dictionary::missing_key(&key).into())
                         }
                     }
-                    _ => Err("Cannot index non-list/dictionary".to_string()),
+                    _ => Err("Cannot index non-list/dictionary".to_string().into()),
                 }
             }
             
@@ -456,7 +580,7 @@ impl Evaluator {
                     properties,
                 };
                 
-                Ok(Value::Object(Arc::new(Mutex::new(object_data))))
+                Ok(Value::Object(Shared::new(object_data)))
             }
             
             Node::MethodCall(method_call) => {
@@ -488,7 +612,7 @@ impl Evaluator {
                                 
                                 if method_call.arguments.len() != params_to_bind.len() {
                                     return Err(format!("Method '{}' expects {} arguments but got {}", 
-                                        method_call.method_name, params_to_bind.len(), method_call.arguments.len()));
+                                        method_call.method_name, params_to_bind.len(), method_call.arguments.len()).into());
                                 }
                                 
                                 for (param, arg_node) in params_to_bind.iter().zip(method_call.arguments.iter()) {
@@ -496,23 +620,22 @@ impl Evaluator {
                                     method_env.define(param.name.clone(), arg_value, true);
                                 }
                                 
-                                let mut method_eval = Evaluator {
-                                    environment: method_env,
-                                    loaded_modules: self.loaded_modules.clone(),
-                                    output_callback: self.output_callback.clone(),
-                                };
+                                let mut method_eval = self.child(method_env);
                                 
-                                let result = method_eval.evaluate(&method_decl.body)?;
+                                let parameters = params_to_bind.iter().map(|p| p.name.as_str());
+                                let name = format!("{}.{}", class_name, method_call.method_name);
+                                let result = method_eval.evaluate_body(&name, parameters, &method_decl.body)?;
                                 if let Value::Return(val) = result {
                                     Ok(*val)
                                 } else {
                                     Ok(result)
                                 }
                             } else {
-                                Err(format!("Method '{}' not found in class '{}'", method_call.method_name, class_name))
+                                let hint = suggest::hint(&method_call.method_name, methods.keys().map(String::as_str));
+                                Err(RuntimeError::new(format!("Method '{}' not found in class '{}'", method_call.method_name, class_name)).with_note(hint))
                             }
                         } else {
-                            Err(format!("Class '{}' not found", class_name))
+                            Err(format!("Class '{}' not found", class_name).into())
                         }
                     }
                     Value::List(list) => {
@@ -520,16 +643,17 @@ impl Evaluator {
                         match method_call.method_name.as_str() {
                             "add" => {
                                 if method_call.arguments.len() != 1 {
-                                    return Err("List.add expects 1 argument".to_string());
+                                    return Err("List.add expects 1 argument".to_string().into());
                                 }
                                 let item = self.evaluate(&method_call.arguments[0])?;
+                                frozen::check_mutable(list)?;
                                 list.lock().unwrap().push(item);
                                 Ok(Value::Void)
                             }
                             "length" | "count" | "size" => {
                                 Ok(Value::Number(list.lock().unwrap().len() as f64))
                             }
-                            _ => Err(format!("Method '{}' not found on List", method_call.method_name))
+                            _ => Err(RuntimeError::new(format!("Method '{}' not found on List", method_call.method_name)).with_note(suggest::hint(&method_call.method_name, LIST_METHODS.iter().copied()))),
                         }
                     }
                     Value::Dictionary(map) => {
@@ -540,29 +664,50 @@ impl Evaluator {
                             }
                             "remove" => {
                                 if method_call.arguments.len() != 1 {
-                                    return Err("Dictionary.remove expects 1 argument (key)".to_string());
+                                    return Err("Dictionary.remove expects 1 argument (key)".to_string().into());
                                 }
                                 let key_val = self.evaluate(&method_call.arguments[0])?;
                                 let key = key_val.to_string();
+                                frozen::check_mutable(map)?;
                                 map.lock().unwrap().remove(&key);
                                 Ok(Value::Void)
                             }
-                            "keys" => {
-                                let keys: Vec<Value> = map.lock().unwrap().keys()
-                                    .map(|k| Value::String(k.clone()))
-                                    .collect();
-                                Ok(Value::List(Arc::new(Mutex::new(keys))))
+                            "keys" => Ok(crate::stdlib::dictionary::keys(&map.lock().unwrap())),
+                            "values" => Ok(crate::stdlib::dictionary::values(&map.lock().unwrap())),
+                            "entries" => Ok(crate::stdlib::dictionary::entries(&map.lock().unwrap())),
+                            "contains_key" => {
+                                if method_call.arguments.len() != 1 {
+                                    return Err("Dictionary.contains_key expects 1 argument (key)".to_string().into());
+                                }
+                                let key = self.evaluate(&method_call.arguments[0])?.to_string();
+                                Ok(Value::Bool(map.lock().unwrap().contains_key(&key)))
+                            }
+                            "get" => {
+                                if method_call.arguments.len() != 2 {
+                                    return Err("Dictionary.get expects 2 arguments (key, default)".to_string().into());
+                                }
+                                let key = self.evaluate(&method_call.arguments[0])?.to_string();
+                                let default = self.evaluate(&method_call.arguments[1])?;
+                                Ok(map.lock().unwrap().get(&key).cloned().unwrap_or(default))
+                            }
+                            "merge" => {
+                                if method_call.arguments.len() != 1 {
+                                    return Err("Dictionary.merge expects 1 argument (dictionary)".to_string().into());
+                                }
+                                let other = self.evaluate(&method_call.arguments[0])?;
+                                // copy first: merging a dictionary with itself must not lock it twice
+                                let base = map.lock().unwrap().clone();
+                                crate::stdlib::dictionary::merge(&base, &other).map_err(RuntimeError::from)
                             }
-                            "values" => {
-                                let values: Vec<Value> = map.lock().unwrap().values()
-                                    .cloned()
-                                    .collect();
-                                Ok(Value::List(Arc::new(Mutex::new(values))))
+                            "clear" => {
+                                frozen::check_mutable(map)?;
+                                map.lock().unwrap().clear();
+                                Ok(Value::Void)
                             }
-                            _ => Err(format!("Method '{}' not found on Dictionary", method_call.method_name))
+                            _ => Err(RuntimeError::new(format!("Method '{}' not found on Dictionary", method_call.method_name)).with_note(suggest::hint(&method_call.method_name, DICTIONARY_METHODS.iter().copied()))),
                         }
                     }
-                    _ => Err(format!("method call requires object, got {}", obj_value.to_string()))
+                    _ => Err(format!("method call requires object, got {}", obj_value.to_string()).into())
                 }
             }
             
@@ -572,12 +717,14 @@ impl Evaluator {
                     Value::String(s) => s,
                     _ => error_value.to_string(),
                 };
-                Err(error_msg)
+                Err(error_msg.into())
             }
             
             Node::TryCatchStatement(try_catch) => {
                 match self.evaluate(&try_catch.try_block) {
                     Ok(value) => Ok(value),
+                    // "exit" unwinds as an error but must not be caught
+                    Err(error_msg) if self.exit_code().is_some() => Err(error_msg),
                     Err(error_msg) => {
                         // if catch block exists, execute it with error in scope
                         if let Some(catch_block) = &try_catch.catch_block {
@@ -586,7 +733,7 @@ impl Evaluator {
                             
                             if let Some(error_var) = &try_catch.error_variable {
                                 // set error variable in the new scope
-                                scope.define(error_var.clone(), Value::String(error_msg.clone()), true);
+                                scope.define(error_var.clone(), Value::String(error_msg.message.clone()), true);
                             }
                             
                             // Execute catch block in the new scope
@@ -639,7 +786,7 @@ impl Evaluator {
         }
     }
     
-    fn evaluate_operation(&mut self, op: &OperationExpression) -> Result<Value, String> {
+    fn evaluate_operation(&mut self, op: &OperationExpression) -> Result<Value, RuntimeError> {
         let left = self.evaluate(&op.left)?;
         
         if let Operator::Not = op.operator {
@@ -658,6 +805,10 @@ impl Evaluator {
         
         if let Some(right) = &op.right {
             let right_value = self.evaluate(right)?;
+
+            if let Some(result) = crate::stdlib::time::apply_operator(&op.operator, &left, &right_value) {
+                return result.map_err(RuntimeError::from);
+            }
             
             // helper coercions moved to methods
             
@@ -674,7 +825,7 @@ impl Evaluator {
                             if let (Some(a), Some(b)) = (Self::to_number(&la), Self::to_number(&rb)) {
                                 Ok(Value::Number(a + b))
                             } else {
-                                Err("Invalid operands for plus".to_string())
+                                Err("Invalid operands for plus".to_string().into())
                             }
                         }
                     }
@@ -683,27 +834,27 @@ impl Evaluator {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Number(a - b))
                     } else {
-                        Err("Invalid operands for minus".to_string())
+                        Err("Invalid operands for minus".to_string().into())
                     }
                 }
                 Operator::Times => {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Number(a * b))
                     } else {
-                        Err("Invalid operands for times".to_string())
+                        Err("Invalid operands for times".to_string().into())
                     }
                 }
                 Operator::DividedBy => {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
-                        if b == 0.0 { Err("Division by zero".to_string()) } else { Ok(Value::Number(a / b)) }
+                        if b == 0.0 { Err("Division by zero".to_string().into()) } else { Ok(Value::Number(a / b)) }
                     } else {
-                        Err("Invalid operands for divided by".to_string())
+                        Err("Invalid operands for divided by".to_string().into())
                     }
                 }
                 Operator::Modulo => {
                     match (left, right_value) {
                         (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a % b)),
-                        _ => Err("Invalid operands for modulo".to_string()),
+                        _ => Err("Invalid operands for modulo".to_string().into()),
                     }
                 }
                 Operator::Equals => {
@@ -716,28 +867,28 @@ impl Evaluator {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Bool(a > b))
                     } else {
-                        Err("Invalid operands for greater than".to_string())
+                        Err("Invalid operands for greater than".to_string().into())
                     }
                 }
                 Operator::LessThan => {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Bool(a < b))
                     } else {
-                        Err("Invalid operands for less than".to_string())
+                        Err("Invalid operands for less than".to_string().into())
                     }
                 }
                 Operator::GreaterThanOrEqual => {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Bool(a >= b))
                     } else {
-                        Err("Invalid operands for greater than or equal to".to_string())
+                        Err("Invalid operands for greater than or equal to".to_string().into())
                     }
                 }
                 Operator::LessThanOrEqual => {
                     if let (Some(a), Some(b)) = (Self::to_number(&left), Self::to_number(&right_value)) {
                         Ok(Value::Bool(a <= b))
                     } else {
-                        Err("Invalid operands for less than or equal to".to_string())
+                        Err("Invalid operands for less than or equal to".to_string().into())
                     }
                 }
                 Operator::And => {
@@ -746,14 +897,14 @@ impl Evaluator {
                 Operator::Or => {
                     Ok(Value::Bool(Self::is_truthy(&left) || Self::is_truthy(&right_value)))
                 }
-                _ => Err("Unsupported operator".to_string()),
+                _ => Err("Unsupported operator".to_string().into()),
             }
         } else {
-            Err("Binary operator missing right operand".to_string())
+            Err("Binary operator missing right operand".to_string().into())
         }
     }
     
-    fn call_function(&mut self, name: &str, args: &[Node]) -> Result<Value, String> {
+    fn call_function(&mut self, name: &str, args: &[Node]) -> Result<Value, RuntimeError> {
         // println!("DEBUG: calling function {}", name);
         if name.contains('.') {
 
@@ -786,50 +937,278 @@ impl Evaluator {
                 Ok(Value::String(result))
             }
             "convert" => {
-                // type conversion functions
-                if args.len() >= 2 {
-                    let value = self.evaluate(&args[0])?;
-                    let _target_type = self.evaluate(&args[1])?;
-                    // simplified: just convert to string for now
-                    Ok(Value::String(value.to_string()))
-                } else {
-                    Err("convert requires value and target type".to_string())
-                }
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::convert::convert(&vals).map_err(RuntimeError::from)
             }
 
             "__io_read_file" => {
                 let mut vals = Vec::new();
                 for arg in args { vals.push(self.evaluate(arg)?); }
-                crate::stdlib::io::read_file(&vals)
+                crate::stdlib::io::read_file(&vals).map_err(RuntimeError::from)
             },
             "__io_write_file" => {
                 let mut vals = Vec::new();
                 for arg in args { vals.push(self.evaluate(arg)?); }
-                crate::stdlib::io::write_file(&vals)
+                crate::stdlib::io::write_file(&vals).map_err(RuntimeError::from)
+            },
+            "__io_append_file" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::append_file(&vals).map_err(RuntimeError::from)
+            },
+            "__io_exists" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::exists(&vals).map_err(RuntimeError::from)
+            },
+            "__io_list_directory" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::list_directory(&vals).map_err(RuntimeError::from)
+            },
+            "__io_make_directory" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::make_directory(&vals).map_err(RuntimeError::from)
+            },
+            "__io_remove_directory" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::remove_directory(&vals).map_err(RuntimeError::from)
+            },
+            "__io_delete_file" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::delete_file(&vals).map_err(RuntimeError::from)
+            },
+            "__io_copy_file" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::copy_file(&vals).map_err(RuntimeError::from)
+            },
+            "__io_move_file" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::move_file(&vals).map_err(RuntimeError::from)
+            },
+            "__io_join_path" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::join_path(&vals).map_err(RuntimeError::from)
+            },
+            "__io_file_info" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::io::file_info(&vals).map_err(RuntimeError::from)
+            },
+            "__time_parse_date" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::time::parse_date(&vals).map_err(RuntimeError::from)
+            },
+            "__time_format_date" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::time::format_date(&vals).map_err(RuntimeError::from)
+            },
+            "__time_date_parts" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::time::date_parts(&vals).map_err(RuntimeError::from)
+            },
+            "__time_duration" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::time::duration(&vals).map_err(RuntimeError::from)
+            },
+            "__time_duration_in" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::time::duration_in(&vals).map_err(RuntimeError::from)
+            },
+            "__program_arguments" => {
+                let items = self.program_arguments.iter().cloned().map(Value::String).collect();
+                Ok(Value::List(Shared::new(items)))
+            },
+            "__io_ask" => {
+                let prompt = match args.first() {
+                    Some(arg) => self.evaluate(arg)?.to_string(),
+                    None => String::new(),
+                };
+                if let Some(callback) = &self.output_callback {
+                    callback(&prompt);
+                } else {
+                    print!("{}", prompt);
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                }
+                match self.input.read_line() {
+                    Some(answer) => Ok(Value::String(answer)),
+                    None => Err(format!("No input left to answer \"{}\"", prompt.trim_end()).into()),
+                }
+            },
+            "__io_read_all_input" => Ok(Value::String(self.input.read_all())),
+            "__io_input_lines" => Ok(crate::stdlib::io::input_lines(self.input.as_ref())),
+            "__env_get" => {
+                let name = match args.first().map(|arg| self.evaluate(arg)).transpose()? {
+                    Some(Value::String(name)) => name,
+                    _ => return Err("the environment variable name must be text".to_string().into()),
+                };
+                // unset (or non-unicode) variables are nothing
+                Ok(std::env::var(&name).map(Value::String).unwrap_or(Value::Nothing))
+            },
+            "exit" => {
+                let code = match args.first().map(|arg| self.evaluate(arg)).transpose()? {
+                    Some(Value::Number(n)) if n.fract() == 0.0 && (0.0..=255.0).contains(&n) => n as i32,
+                    Some(other) => return Err(format!("exit code must be a whole number from 0 to 255, got {}", other.to_string()).into()),
+                    None => 0,
+                };
+                *self.exit_code.lock().unwrap() = Some(code);
+                Err(format!("exit with code {}", code).into())
+            },
+            "__csv_parse_rows" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::csv::parse_rows(&vals).map_err(RuntimeError::from)
+            },
+            "__csv_parse_records" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::csv::parse_records(&vals).map_err(RuntimeError::from)
+            },
+            "__csv_format_rows" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::csv::format_rows(&vals).map_err(RuntimeError::from)
+            },
+            "__csv_format_records" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::csv::format_records(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_matches" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::matches(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_find_all" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::find_all(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_find_groups" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::find_groups(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_find_all_groups" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::find_all_groups(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_find_named_groups" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::find_named_groups(&vals).map_err(RuntimeError::from)
+            },
+            "__pattern_replace" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::pattern::replace(&vals).map_err(RuntimeError::from)
+            },
+            "__http_request" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                let request = crate::stdlib::http::request_from_args(&vals)?;
+                let response = self.transport.send(&request)?;
+                Ok(crate::stdlib::http::response_value(response))
+            },
+            "__json_parse" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::json::parse(&vals).map_err(RuntimeError::from)
+            },
+            "__json_format" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::json::format(&vals).map_err(RuntimeError::from)
+            },
+            "__collections_map" | "__collections_filter" | "__collections_reduce" | "__collections_find"
+            | "__collections_any" | "__collections_all" | "__collections_count_where"
+            | "__collections_group_by" | "__collections_sort_by" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                let operation = name.trim_start_matches("__collections_").replace('_', " ");
+                let items = match vals.first() {
+                    Some(Value::List(items)) => items.lock().unwrap().clone(),
+                    Some(other) => return Err(format!("{} expects a list, got {}", operation, other.to_string()).into()),
+                    None => return Err(format!("{} expects a list and a function", operation).into()),
+                };
+                let function = vals.get(1).cloned().ok_or_else(|| format!("{} expects a list and a function", operation))?;
+                let mut call = |arguments: Vec<Value>| self.call_function_value(&operation, &function, arguments);
+                use crate::stdlib::collections;
+                match name {
+                    "__collections_map" => collections::map(&items, &mut call),
+                    "__collections_filter" => collections::filter(&items, &mut call),
+                    "__collections_reduce" => {
+                        let initial = vals.get(2).cloned().unwrap_or(Value::Nothing);
+                        collections::reduce(&items, initial, &mut call)
+                    },
+                    "__collections_find" => collections::find(&items, &mut call),
+                    "__collections_any" => collections::any(&items, &mut call),
+                    "__collections_all" => collections::all(&items, &mut call),
+                    "__collections_count_where" => collections::count_where(&items, &mut call),
+                    "__collections_group_by" => collections::group_by(&items, &mut call),
+                    _ => collections::sort_by(&items, &mut call),
+                }
+            },
+            "__freeze" => {
+                if args.len() != 1 {
+                    return Err("frozen copy expects 1 value".to_string().into());
+                }
+                let value = frozen::deep_copy(&self.evaluate(&args[0])?);
+                frozen::freeze(&value, frozen::UNNAMED);
+                Ok(value)
+            },
+            "__maybe_index" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::maybe::index(&vals).map_err(RuntimeError::from)
+            },
+            "__maybe_property" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::maybe::property(&vals).map_err(RuntimeError::from)
+            },
+            "__dict_from_pairs" => {
+                let mut vals = Vec::new();
+                for arg in args { vals.push(self.evaluate(arg)?); }
+                crate::stdlib::dictionary::from_pairs(&vals).map_err(RuntimeError::from)
             },
             "__str_split" => {
                 let mut vals = Vec::new();
                 for arg in args { vals.push(self.evaluate(arg)?); }
-                crate::stdlib::string::split(&vals)
+                crate::stdlib::string::split(&vals).map_err(RuntimeError::from)
             },
             "__str_trim" => {
                 let mut vals = Vec::new();
                 for arg in args { vals.push(self.evaluate(arg)?); }
-                crate::stdlib::string::trim(&vals)
+                crate::stdlib::string::trim(&vals).map_err(RuntimeError::from)
             },
             "append" => {
                 if args.len() != 2 {
-                    return Err("append expects 2 arguments (list, item)".to_string());
+                    return Err("append expects 2 arguments (list, item)".to_string().into());
                 }
                 let list_val = self.evaluate(&args[0])?;
                 let item = self.evaluate(&args[1])?;
                 
                 match list_val {
                     Value::List(items) => {
+                        frozen::check_mutable(&items)?;
                         items.lock().unwrap().push(item);
                         Ok(Value::Void)
                     }
-                    _ => Err("append expects a list as first argument".to_string())
+                    _ => Err("append expects a list as first argument".to_string().into())
                 }
             },
             "create_list" => {
@@ -838,7 +1217,7 @@ impl Evaluator {
                     let val = self.evaluate(arg)?;
                     items.push(val);
                 }
-                Ok(Value::List(Arc::new(Mutex::new(items))))
+                Ok(Value::List(Shared::new(items)))
             },
             "create_dictionary" => {
                 let mut map = HashMap::new();
@@ -848,7 +1227,7 @@ impl Evaluator {
                     let key = key_val.to_string(); // keys are strings
                     
                     if i + 1 >= args.len() {
-                        return Err("Dictionary missing value for key".to_string());
+                        return Err("Dictionary missing value for key".to_string().into());
                     }
                     let value = self.evaluate(&args[i+1])?;
                     
@@ -856,25 +1235,25 @@ impl Evaluator {
                     map.insert(key, value);
                     i += 2;
                 }
-                Ok(Value::Dictionary(Arc::new(Mutex::new(map))))
+                Ok(Value::Dictionary(Shared::new(map)))
             },
             "len" | "length" | "count" => {
                 if args.len() != 1 {
-                    return Err("len expects 1 argument".to_string());
+                    return Err("len expects 1 argument".to_string().into());
                 }
                 let value = self.evaluate(&args[0])?;
                 match value {
                     Value::List(items) => Ok(Value::Number(items.lock().unwrap().len() as f64)),
                     Value::Dictionary(map) => Ok(Value::Number(map.lock().unwrap().len() as f64)),
                     Value::String(s) => Ok(Value::Number(s.len() as f64)),
-                    _ => Err(format!("Cannot get length of {}", value.to_string())),
+                    _ => Err(format!("Cannot get length of {}", value.to_string()).into()),
                 }
             },
             "__make_variant" => {
                 // Internal helper for constructing variants
                 // Args: type_name, variant_name, field1, field2...
                 if args.len() < 2 {
-                    return Err("__make_variant requires at least type_name and variant_name".to_string());
+                    return Err("__make_variant requires at least type_name and variant_name".to_string().into());
                 }
                 
                 let type_name_val = self.evaluate(&args[0])?;
@@ -882,12 +1261,12 @@ impl Evaluator {
                 
                 let type_name = match type_name_val {
                     Value::String(s) => s,
-                    _ => return Err("type_name must be a string".to_string()),
+                    _ => return Err("type_name must be a string".to_string().into()),
                 };
                 
                 let variant_name = match variant_name_val {
                     Value::String(s) => s,
-                    _ => return Err("variant_name must be a string".to_string()),
+                    _ => return Err("variant_name must be a string".to_string().into()),
                 };
                 
                 let mut fields = HashMap::new();
@@ -910,11 +1289,11 @@ impl Evaluator {
                     let field_name_val = self.evaluate(&args[i])?;
                     let field_name = match field_name_val {
                         Value::String(s) => s,
-                        _ => return Err("field name must be a string".to_string()),
+                        _ => return Err("field name must be a string".to_string().into()),
                     };
                     
                     if i + 1 >= args.len() {
-                        return Err("Missing value for field".to_string());
+                        return Err("Missing value for field".to_string().into());
                     }
                     
                     let field_value = self.evaluate(&args[i+1])?;
@@ -932,11 +1311,11 @@ impl Evaluator {
                     let actual = self.evaluate(&args[0])?;
                     let expected = self.evaluate(&args[1])?;
                     if actual != expected {
-                        return Err(format!("expected {} but got {}", expected.to_string(), actual.to_string()));
+                        return Err(format!("expected {} but got {}", expected.to_string(), actual.to_string()).into());
                     }
                     Ok(Value::Void)
                 } else {
-                    Err("expect requires actual and expected values".to_string())
+                    Err("expect requires actual and expected values".to_string().into())
                 }
             },
             "expect error" => {
@@ -950,18 +1329,18 @@ impl Evaluator {
                     };
                     match self.evaluate(code_block) {
                         Ok(_) => {
-                            return Err("expected error but code executed successfully".to_string());
+                            return Err("expected error but code executed successfully".to_string().into());
                         }
                         Err(e) => {
-                            if e == expected_str || e.contains(&expected_str) {
+                            if e.message.contains(&expected_str) {
                                 Ok(Value::Void) // test passed
                             } else {
-                                return Err(format!("expected error message '{}' but got '{}'", expected_str, e));
+                                return Err(format!("expected error message '{}' but got '{}'", expected_str, e).into());
                             }
                         }
                     }
                 } else {
-                    Err("expect error requires code block and expected message".to_string())
+                    Err("expect error requires code block and expected message".to_string().into())
                 }
             },
             "test" => {
@@ -980,7 +1359,7 @@ impl Evaluator {
                         }
                     }
                 } else {
-                    Err("test requires name and body".to_string())
+                    Err("test requires name and body".to_string().into())
                 }
             },
             "describe" => {
@@ -996,14 +1375,14 @@ impl Evaluator {
                         Err(e) => Err(e),
                     }
                 } else {
-                    Err("describe requires name and body".to_string())
+                    Err("describe requires name and body".to_string().into())
                 }
             },
             // expect a compilation error from a file path (string)
             // usage: call expect_compilation_error with 'path' and 'expected substring'
             "expect_compilation_error" => {
                 if args.is_empty() {
-                    return Err("expect_compilation_error requires a file path".to_string());
+                    return Err("expect_compilation_error requires a file path".to_string().into());
                 }
                 let path_val = self.evaluate(&args[0])?;
                 let path_str = match path_val { Value::String(s) => s, _ => path_val.to_string() };
@@ -1018,7 +1397,7 @@ impl Evaluator {
                 let tokens = match lx.tokenize() {
                     Ok(t) => t,
                     Err(e) => {
-                        if let Some(sub) = expected_sub { if e.contains(&sub) { return Ok(Value::Void); } }
+                        if let Some(sub) = expected_sub { if e.message.contains(&sub) { return Ok(Value::Void); } }
                         return Ok(Value::Void); // any compile failure satisfies expectation
                     }
                 };
@@ -1026,7 +1405,7 @@ impl Evaluator {
                 let mut ast = match ps.parse() {
                     Ok(a) => a,
                     Err(e) => {
-                        if let Some(sub) = expected_sub { if e.contains(&sub) { return Ok(Value::Void); } }
+                        if let Some(sub) = expected_sub { if e.message.contains(&sub) { return Ok(Value::Void); } }
                         return Ok(Value::Void);
                     }
                 };
@@ -1037,7 +1416,7 @@ impl Evaluator {
                 ast = match r.resolve_and_bundle(&ast) {
                     Ok(a) => a,
                     Err(e) => {
-                        if let Some(sub) = expected_sub { if e.contains(&sub) { return Ok(Value::Void); } }
+                        if let Some(sub) = expected_sub { if e.message.contains(&sub) { return Ok(Value::Void); } }
                         return Ok(Value::Void);
                     }
                 };
@@ -1051,12 +1430,12 @@ impl Evaluator {
                     }
                 }
                 // reached here without failing any compile stage
-                Err("expected compilation error but compilation succeeded".to_string())
+                Err("expected compilation error but compilation succeeded".to_string().into())
             },
             // expect runtime error when running a file
             // usage: call expect_runtime_error_from with 'path' and 'expected substring'
             "expect_runtime_error_from" => {
-                if args.is_empty() { return Err("expect_runtime_error_from requires a file path".to_string()); }
+                if args.is_empty() { return Err("expect_runtime_error_from requires a file path".to_string().into()); }
                 let path_val = self.evaluate(&args[0])?;
                 let path_str = match path_val { Value::String(s) => s, _ => path_val.to_string() };
                 let expected_sub = if args.len() >= 2 {
@@ -1073,19 +1452,19 @@ impl Evaluator {
                 let base_dir = std::path::Path::new(&path_str).parent()
                     .map(|p| p.to_path_buf()).unwrap_or_else(|| std::path::PathBuf::from("."));
                 let mut r = resolver::ImportResolver::new(base_dir);
-                ast = r.resolve_and_bundle(&ast).map_err(|e| e)?;
+                ast = r.resolve_and_bundle(&ast).map_err(|e| RuntimeError::new(e.message))?;
                 if let Node::Program(ref prog) = ast {
                     let mut tc = typechecker::TypeChecker::new();
                     if let Err(errors) = tc.check_program(prog) {
                         let msg = errors.into_iter().map(|er| er.message).collect::<Vec<_>>().join("; ");
-                        return Err(format!("expected runtime error, but got compilation/type error: {}", msg));
+                        return Err(format!("expected runtime error, but got compilation/type error: {}", msg).into());
                     }
                 }
                 let mut ev = Evaluator::new();
                 match ev.evaluate(&ast) {
-                    Ok(_) => Err("expected runtime error but code executed successfully".to_string()),
+                    Ok(_) => Err("expected runtime error but code executed successfully".to_string().into()),
                     Err(e) => {
-                        if let Some(sub) = expected_sub { if e.contains(&sub) { Ok(Value::Void) } else { Err(format!("expected runtime error containing '{}' but got '{}'", sub, e)) } } else { Ok(Value::Void) }
+                        if let Some(sub) = expected_sub { if e.message.contains(&sub) { Ok(Value::Void) } else { Err(format!("expected runtime error containing '{}' but got '{}'", sub, e).into()) } } else { Ok(Value::Void) }
                     }
                 }
             },
@@ -1094,7 +1473,7 @@ impl Evaluator {
                 let func_value = if name.contains('.') {
                     let parts: Vec<&str> = name.split('.').collect();
                     if parts.len() != 2 {
-                        return Err(format!("Invalid function name '{}'", name));
+                        return Err(format!("Invalid function name '{}'", name).into());
                     }
                     let module_name = parts[0];
                     let func_name = parts[1];
@@ -1117,42 +1496,73 @@ impl Evaluator {
                 };
 
                 if let Some(func_value) = func_value {
-                    if let Value::Function { parameters, body, env, .. } = func_value {
-                        // create new scope with captured environment as parent
-                        let parent_env = env.unwrap_or_else(|| self.environment.clone());
-                        let func_env = Environment::with_parent(parent_env);
-                        
+                    if let Value::Function { parameters, .. } = &func_value {
                         if args.len() != parameters.len() {
                             return Err(format!("Function '{}' expects {} arguments but got {}", 
-                                name, parameters.len(), args.len()));
-                        }
-                        
-                        for (param, arg_node) in parameters.iter().zip(args.iter()) {
-                            let arg_value = self.evaluate(arg_node)?;
-                            func_env.define(param.clone(), arg_value, true);
+                                name, parameters.len(), args.len()).into());
                         }
-                        
-                        let mut func_eval = Evaluator {
-                            environment: func_env,
-                            loaded_modules: self.loaded_modules.clone(),
-                            output_callback: self.output_callback.clone(),
-                        };
-                        
-                        let result = func_eval.evaluate(&body)?;
-                        if let Value::Return(val) = result {
-                            Ok(*val)
-                        } else {
-                            Ok(result)
-                        }
-                    } else {
-                        Err(format!("'{}' is not a function", name))
                     }
+                    let mut arg_values = Vec::new();
+                    for arg_node in args {
+                        arg_values.push(self.evaluate(arg_node)?);
+                    }
+                    self.call_function_value(name, &func_value, arg_values)
                 } else {
-                    Err(format!("Unknown function: {}", name))
+                    Err(RuntimeError::new(format!("Unknown function: {}", name)).with_note(self.name_hint(name)))
                 }
             }
         }
     }
+
+    /// run a function or method body, telling the hook it was entered (with the values of its
+    /// `parameters`, already bound in this scope) and what it returned
+    fn evaluate_body<'a>(&mut self, name: &str, parameters: impl IntoIterator<Item = &'a str>, body: &Node) -> Result<Value, RuntimeError> {
+        let Some(hook) = self.hook.clone() else {
+            return self.evaluate(body);
+        };
+        let arguments: Vec<(String, Value)> = parameters
+            .into_iter()
+            .filter_map(|parameter| Some((parameter.to_string(), self.environment.get(parameter)?)))
+            .collect();
+        hook.enter_function(name, &arguments);
+        let result = self.evaluate(body);
+        let returned = match &result {
+            Ok(Value::Return(value)) => Some(value.as_ref()),
+            Ok(value) => Some(value),
+            Err(_) => None,
+        };
+        hook.exit_function(name, returned);
+        result
+    }
+
+    /// call a function value (a named function or a lambda) with already evaluated arguments
+    fn call_function_value(&mut self, name: &str, function: &Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        if let Value::Function { parameters, body, env, .. } = function {
+            // create new scope with captured environment as parent
+            let parent_env = env.clone().unwrap_or_else(|| self.environment.clone());
+            let func_env = Environment::with_parent(parent_env);
+
+            if args.len() != parameters.len() {
+                return Err(format!("Function '{}' expects {} arguments but got {}",
+                    name, parameters.len(), args.len()).into());
+            }
+
+            for (param, arg_value) in parameters.iter().zip(args) {
+                func_env.define(param.clone(), arg_value, true);
+            }
+
+            let mut func_eval = self.child(func_env);
+
+            let result = func_eval.evaluate_body(name, parameters.iter().map(String::as_str), body)?;
+            if let Value::Return(val) = result {
+                Ok(*val)
+            } else {
+                Ok(result)
+            }
+        } else {
+            Err(format!("'{}' is not a function", name).into())
+        }
+    }
 }
 
 impl Evaluator {
@@ -1170,6 +1580,7 @@ impl Evaluator {
             Value::Return(val) => Self::value_to_text(val),
             Value::Future(_) => "<future>".to_string(),
             Value::Variant(_, variant_name, _) => variant_name.clone(),
+            Value::Date(_) | Value::Duration(_) => v.to_string(),
             Value::Nothing => "nothing".to_string(),
         }
     }
@@ -1203,12 +1614,13 @@ impl Evaluator {
             Value::Return(val) => Self::is_truthy(val),
             Value::Future(_) => true,
             Value::Variant { .. } => true,
+            Value::Date(_) | Value::Duration(_) => true,
             Value::Nothing => false,
         }
     }
 
 
-    fn evaluate_import(&mut self, stmt: &ImportStatement) -> Result<Value, String> {
+    fn evaluate_import(&mut self, stmt: &ImportStatement) -> Result<Value, RuntimeError> {
         // Resolve path relative to current file
         let current_file_path = Path::new(&stmt.location.file);
         let parent_dir = current_file_path.parent().unwrap_or(Path::new("."));
@@ -1233,10 +1645,10 @@ impl Evaluator {
         
         // Read file
         if !path.exists() {
-            return Err(format!("Module not found: {}", module_key));
+            return Err(format!("Module not found: {}", module_key).into());
         }
         if !path.exists() {
-            return Err(format!("Module not found: {}", module_key));
+            return Err(format!("Module not found: {}", module_key).into());
         }
         
         let source = fs::read_to_string(path)
@@ -1254,11 +1666,7 @@ impl Evaluator {
         // Evaluate in new environment
         let module_env = Environment::new();
         // Share loaded_modules cache with the new evaluator
-        let mut module_evaluator = Evaluator {
-            environment: module_env.clone(),
-            loaded_modules: self.loaded_modules.clone(),
-            output_callback: self.output_callback.clone(),
-        };
+        let mut module_evaluator = self.child(module_env.clone());
         // Evaluate module
         module_evaluator.evaluate(&ast)?;
         
@@ -1291,7 +1699,7 @@ impl Evaluator {
             for (key, (val, _)) in module_env.variables.lock().unwrap().iter() {
                 exports.insert(key.clone(), val.clone());
             }
-            let module_obj = Value::Dictionary(Arc::new(Mutex::new(exports)));
+            let module_obj = Value::Dictionary(Shared::new(exports));
             self.environment.define(alias.clone(), module_obj, true);
         } else {
             // import ... (no alias, no specific imports)
@@ -1323,11 +1731,7 @@ impl Evaluator {
             if parameters.is_empty() {
                 // Create new environment for function scope
                 let func_env = Environment::with_parent(self.environment.clone());
-                let mut func_evaluator = Evaluator {
-                    environment: func_env.clone(),
-                    loaded_modules: self.loaded_modules.clone(),
-                    output_callback: self.output_callback.clone(),
-                };
+                let mut func_evaluator = self.child(func_env.clone());
                 
                 // Execute function body
                 // We ignore the result/error of close()
@@ -1336,7 +1740,7 @@ impl Evaluator {
         }
     }
 
-    fn evaluate_run_concurrently_statement(&mut self, stmt: &RunConcurrentlyStatement) -> Result<Value, String> {
+    fn evaluate_run_concurrently_statement(&mut self, stmt: &RunConcurrentlyStatement) -> Result<Value, RuntimeError> {
         let mut handles = Vec::new();
         
         for s in &stmt.statements {
@@ -1347,11 +1751,7 @@ impl Evaluator {
             // We want to share the SAME environment instance, so changes are visible.
             // But Evaluator takes ownership of environment? No, it holds Arc.
             
-            let mut thread_evaluator = Evaluator {
-                environment: self.environment.clone(), // Shared environment
-                loaded_modules: self.loaded_modules.clone(),
-                output_callback: self.output_callback.clone(),
-            };
+            let mut thread_evaluator = self.child(self.environment.clone()); // Shared environment
             
             let stmt_clone = s.clone();
             
@@ -1367,27 +1767,23 @@ impl Evaluator {
             match handle.join() {
                 Ok(result) => {
                     if let Err(e) = result {
-                        return Err(format!("Thread error: {}", e));
+                        return Err(format!("Thread error: {}", e).into());
                     }
                 }
-                Err(_) => return Err("Thread panicked".to_string()),
+                Err(_) => return Err("Thread panicked".to_string().into()),
             }
         }
         
         Ok(Value::Void)
     }
     
-    fn evaluate_start_expression(&mut self, expr: &StartExpression) -> Result<Value, String> {
+    fn evaluate_start_expression(&mut self, expr: &StartExpression) -> Result<Value, RuntimeError> {
         // Create a future value
         let future_state = Arc::new(Mutex::new(FutureState::Pending));
         let future_value = Value::Future(future_state.clone());
         
         // Create a new evaluator for the background task
-        let mut thread_evaluator = Evaluator {
-            environment: self.environment.clone(), // Shared environment
-            loaded_modules: self.loaded_modules.clone(),
-            output_callback: self.output_callback.clone(),
-        };
+        let mut thread_evaluator = self.child(self.environment.clone()); // Shared environment
         
         let expr_clone = expr.expression.clone();
         
@@ -1403,7 +1799,7 @@ impl Evaluator {
         Ok(future_value)
     }
     
-    fn evaluate_wait_expression(&mut self, expr: &WaitExpression) -> Result<Value, String> {
+    fn evaluate_wait_expression(&mut self, expr: &WaitExpression) -> Result<Value, RuntimeError> {
         let future_val = self.evaluate(&expr.expression)?;
         
         if let Value::Future(state_arc) = future_val {
@@ -1424,11 +1820,11 @@ impl Evaluator {
                 }
             }
         } else {
-            Err(format!("'wait for' expects a future, got {:?}", future_val.get_type()))
+            Err(format!("'wait for' expects a future, got {:?}", future_val.get_type()).into())
         }
     }
 
-    fn evaluate_type_declaration(&mut self, decl: &TypeDeclaration) -> Result<Value, String> {
+    fn evaluate_type_declaration(&mut self, decl: &TypeDeclaration) -> Result<Value, RuntimeError> {
         // Register constructor functions for each variant
         for variant in &decl.variants {
             let type_name = decl.name.clone();
@@ -1555,7 +1951,7 @@ impl Evaluator {
         Ok(Value::Void)
     }
 
-    fn evaluate_inspect_statement(&mut self, stmt: &InspectStatement) -> Result<Value, String> {
+    fn evaluate_inspect_statement(&mut self, stmt: &InspectStatement) -> Result<Value, RuntimeError> {
         let value = self.evaluate(&stmt.expression)?;
         
         if let Value::Variant(_, variant_name, fields) = value {
@@ -1591,7 +1987,7 @@ impl Evaluator {
             // No match?
             Ok(Value::Void)
         } else {
-            Err(format!("'inspect' expects a variant type, got {}", value.to_string()))
+            Err(format!("'inspect' expects a variant type, got {}", value.to_string()).into())
         }
     }
 }
@@ -0,0 +1,1128 @@
+// formatter - rewrites layman source in one canonical layout
+// the AST is printed back as text with the comments and blank lines put back around it;
+// every rewritten statement is parsed again and kept only if it means exactly the same thing
+
+use crate::ast::*;
+use crate::diagnostics::{codes, Diagnostic};
+use crate::lexer::{self, Comment, Lexer};
+use crate::parser::Parser;
+use crate::MessageFormat;
+use std::collections::HashMap;
+
+const INDENT: &str = "  ";
+
+// what may follow a printed expression on the same line, from loosest to tightest.
+// an expression's `tail` is the loosest follower that will not be swallowed by its last operand
+const END: i8 = -1; // nothing follows (end of line)
+const KEYWORD: i8 = 0; // then, do, to, with, ...
+const AND: i8 = 2; // also needs `and_ok`
+const COMPARISON: i8 = 3;
+const TERM: i8 = 4;
+const POSTFIX: i8 = 8; // .property, [index], a duration unit
+const COMMA: i8 = -2; // needs `comma`; any expression that is not open at the end takes one
+
+struct Printed {
+    text: String,
+    /// the loosest parse level that reads this text back as a single operand
+    level: u8,
+    /// the loosest follower that keeps the expression intact (see the constants above)
+    tail: i8,
+    /// whether a comma may follow
+    comma: bool,
+    /// whether "and" may follow (argument lists read it as a separator)
+    and_ok: bool,
+}
+
+impl Printed {
+    fn closed(text: String) -> Self {
+        Printed { text, level: 8, tail: 9, comma: true, and_ok: true }
+    }
+
+    /// a form that swallows "and" after it
+    fn open(text: String, level: u8, tail: i8, comma: bool) -> Self {
+        Printed { text, level, tail, comma, and_ok: false }
+    }
+
+    /// a form whose last operand was parsed at `level`, so only looser followers are safe
+    fn ending_with(text: String, level: u8, last: &Printed, parsed_at: i8) -> Self {
+        Printed { text, level, tail: last.tail.min(parsed_at - 1), comma: last.comma, and_ok: last.and_ok }
+    }
+
+    fn needs(&self, level: u8, follower: i8) -> Result<&str, String> {
+        let fits = match follower {
+            COMMA => self.comma && self.tail >= KEYWORD,
+            AND => self.and_ok && self.tail >= AND,
+            _ => self.tail >= follower,
+        };
+        if self.level < level || !fits {
+            return Err(format!("'{}' cannot be written here without changing its meaning", self.text));
+        }
+        Ok(&self.text)
+    }
+}
+
+struct Line {
+    depth: usize,
+    text: String,
+    /// the source line this output line stands for; comments above it are written first
+    source_line: Option<usize>,
+    /// a comment at the end of this source line stays at the end of this output line
+    trailing: Option<usize>,
+    /// the last source line this output line finishes
+    end: Option<usize>,
+    /// copied from the source as is, comments included
+    verbatim: bool,
+    /// `end if` and friends: comments before them belong to the block
+    closer: bool,
+    blank_before: bool,
+}
+
+impl Line {
+    fn new(depth: usize, text: String) -> Self {
+        Line { depth, text, source_line: None, trailing: None, end: None, verbatim: false, closer: false, blank_before: false }
+    }
+}
+
+struct Printer<'a> {
+    /// statement location -> (first line, last line); None where two statements share a location
+    spans: &'a HashMap<(usize, usize), Option<(usize, usize)>>,
+    source: &'a [&'a str],
+    lines: Vec<Line>,
+    query_depth: usize,
+}
+
+/// format one file; fails if the source does not parse or could not be rewritten faithfully
+pub fn format_source(source: &str, file: &str) -> Result<String, Box<Diagnostic>> {
+    let source = source.replace("\r\n", "\n");
+    let (program, spans, comments) = parse_with_layout(&source, file)?;
+    let failed = |message: String| Box::new(Diagnostic::error(codes::FORMAT, message));
+    let statements = match &program {
+        Node::Program(p) => &p.statements,
+        _ => return Err(failed("Expected a program".to_string())),
+    };
+
+    let mut span_map: HashMap<(usize, usize), Option<(usize, usize)>> = HashMap::new();
+    for span in &spans {
+        let key = (span.location.line, span.location.column);
+        let value = Some((span.first_line, span.last_line));
+        span_map.entry(key).and_modify(|v| *v = None).or_insert(value);
+    }
+
+    // the parser keeps prose lines as empty statements; line them up with the real ones
+    let top_level: Vec<_> = spans.iter().filter(|s| s.depth == 0).collect();
+    let kept: Vec<_> = top_level.iter().filter(|s| !s.skipped).collect();
+    if kept.len() != statements.len() {
+        return Err(failed(format!("Could not match the statements of {} to the source", file)));
+    }
+
+    let source_lines: Vec<&str> = source.lines().collect();
+    let mut printer = Printer { spans: &span_map, source: &source_lines, lines: Vec::new(), query_depth: 0 };
+    let mut nodes = statements.iter();
+    let mut previous_declaration = false;
+    for span in top_level {
+        let start = printer.lines.len();
+        let declaration = if span.skipped {
+            printer.verbatim(span.first_line, span.last_line);
+            false
+        } else {
+            let node = nodes.next().unwrap();
+            // statements that cannot be rewritten faithfully keep their original lines
+            if printer.top_level(node, file).is_err() {
+                printer.lines.truncate(start);
+                printer.verbatim(span.first_line, span.last_line);
+            }
+            is_declaration(node)
+        };
+        if start > 0 && start < printer.lines.len() && (declaration || previous_declaration) {
+            printer.lines[start].blank_before = true;
+        }
+        previous_declaration = declaration;
+    }
+
+    let formatted = assemble(printer.lines, &comments, &source_lines);
+    let (reparsed, _, _) = parse_with_layout(&formatted, file)
+        .map_err(|e| failed(format!("Formatting {} produced code that does not parse: {}", file, e.message)))?;
+    if strip_locations(&reparsed) != strip_locations(&program) {
+        return Err(failed(format!("Formatting {} would change its meaning; left unchanged", file)));
+    }
+    Ok(formatted)
+}
+
+type Layout = (Node, Vec<crate::parser::StatementSpan>, Vec<Comment>);
+
+fn parse_with_layout(source: &str, file: &str) -> Result<Layout, Box<Diagnostic>> {
+    let mut lexer = Lexer::new(source, file.to_string());
+    let tokens = lexer.tokenize().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::LEX, &e)))?;
+    let comments = lexer::comments(&tokens);
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::SYNTAX, &e)))?;
+    Ok((program, parser.statement_spans().to_vec(), comments))
+}
+
+fn is_declaration(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) | Node::StructDeclaration(_) | Node::TypeDeclaration(_)
+    )
+}
+
+/// the AST as JSON without locations, so two parses can be compared for meaning
+fn strip_locations(node: &Node) -> serde_json::Value {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.remove("location");
+                map.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(node).unwrap_or(serde_json::Value::Null);
+    strip(&mut value);
+    value
+}
+
+/// lay the printed lines out with the source's comments and (single) blank lines
+fn assemble(lines: Vec<Line>, comments: &[Comment], source: &[&str]) -> String {
+    let is_blank = |line: usize| source.get(line.wrapping_sub(1)).is_some_and(|l| l.trim().is_empty());
+    let blank_between = |from: usize, to: usize| (from + 1..to).any(is_blank);
+    let is_trailing = |c: &Comment| {
+        source.get(c.location.line - 1)
+            .is_some_and(|l| l.chars().take(c.location.column).any(|ch| !ch.is_whitespace()))
+    };
+
+    let mut out: Vec<String> = Vec::new();
+    let mut last_depth = 0;
+    let mut cursor = 0;
+    let mut pending = comments.iter().peekable();
+
+    // a blank line never opens a block, closes one, starts the file or doubles up
+    let push_blank = |out: &mut Vec<String>, last_depth: usize, depth: usize, closer: bool| {
+        if !out.is_empty() && !out.last().unwrap().is_empty() && depth <= last_depth && !closer {
+            out.push(String::new());
+        }
+    };
+
+    for line in lines {
+        let mut want_blank = line.blank_before;
+        if let Some(at) = line.source_line {
+            let comment_depth = if line.closer { line.depth + 1 } else { line.depth };
+            while let Some(c) = pending.peek() {
+                if c.location.line > at || (c.location.line == at && !line.verbatim) {
+                    break;
+                }
+                let c = pending.next().unwrap();
+                if c.location.line == at {
+                    continue; // part of the verbatim text
+                }
+                if want_blank || blank_between(cursor, c.location.line) {
+                    push_blank(&mut out, last_depth, comment_depth, false);
+                }
+                want_blank = false;
+                out.push(format!("{}{}", INDENT.repeat(comment_depth), c.text));
+                last_depth = comment_depth;
+                cursor = c.location.line;
+            }
+            if blank_between(cursor, at) {
+                want_blank = true;
+            }
+        }
+        if want_blank {
+            push_blank(&mut out, last_depth, line.depth, line.closer);
+        }
+
+        let mut text = if line.verbatim { line.text.clone() } else { format!("{}{}", INDENT.repeat(line.depth), line.text) };
+        if let Some(at) = line.trailing {
+            if let Some(c) = pending.peek() {
+                if c.location.line == at && is_trailing(c) {
+                    text.push(' ');
+                    text.push_str(&c.text);
+                    pending.next();
+                }
+            }
+        }
+        out.push(text);
+        last_depth = line.depth;
+        cursor = cursor.max(line.source_line.unwrap_or(0)).max(line.end.unwrap_or(0));
+    }
+
+    for c in pending {
+        if blank_between(cursor, c.location.line) {
+            push_blank(&mut out, 0, 0, false);
+        }
+        out.push(c.text.clone());
+        cursor = c.location.line;
+    }
+
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    if out.is_empty() {
+        return String::new();
+    }
+    out.join("\n") + "\n"
+}
+
+impl Printer<'_> {
+    /// print a top-level statement and check that it reads back the same
+    fn top_level(&mut self, node: &Node, file: &str) -> Result<(), String> {
+        let start = self.lines.len();
+        self.statement(node, 0)?;
+        let text: String = self.lines[start..]
+            .iter()
+            .map(|l| if l.verbatim { format!("{}\n", l.text) } else { format!("{}{}\n", INDENT.repeat(l.depth), l.text) })
+            .collect();
+        let (reparsed, _, _) = parse_with_layout(&text, file).map_err(|d| d.message)?;
+        match reparsed {
+            Node::Program(p) if p.statements.len() == 1 && strip_locations(&p.statements[0]) == strip_locations(node) => Ok(()),
+            _ => Err("statement does not read back the same".to_string()),
+        }
+    }
+
+    fn verbatim(&mut self, first: usize, last: usize) {
+        for number in first..=last {
+            let text = self.source.get(number - 1).map_or("", |l| l.trim_end());
+            let mut line = Line::new(0, text.to_string());
+            line.source_line = Some(number);
+            line.end = Some(number);
+            line.verbatim = true;
+            self.lines.push(line);
+        }
+    }
+
+    fn span_of(&self, node: &Node) -> Option<(usize, usize)> {
+        let location = node.location();
+        self.spans.get(&(location.line, location.column)).copied().flatten()
+    }
+
+    fn push(&mut self, depth: usize, text: String) {
+        self.lines.push(Line::new(depth, text));
+    }
+
+    fn closer(&mut self, depth: usize, text: &str, span: Option<(usize, usize)>) {
+        let mut line = Line::new(depth, text.to_string());
+        line.closer = true;
+        if let Some((_, last)) = span {
+            line.source_line = Some(last);
+            line.trailing = Some(last);
+        }
+        self.lines.push(line);
+    }
+
+    fn block(&mut self, node: &Node, depth: usize) -> Result<(), String> {
+        match node {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }) => Ok(()),
+            Node::Program(p) => p.statements.iter().try_for_each(|s| self.statement(s, depth)),
+            other => self.statement(other, depth),
+        }
+    }
+
+    fn statement(&mut self, node: &Node, depth: usize) -> Result<(), String> {
+        let span = self.span_of(node);
+        let start = self.lines.len();
+        self.statement_lines(node, depth, span)?;
+        if let Some((first, last)) = span {
+            if let Some(line) = self.lines.get_mut(start) {
+                line.source_line.get_or_insert(first);
+                line.trailing.get_or_insert(first);
+            }
+            if let Some(line) = self.lines.last_mut() {
+                line.end = Some(last);
+            }
+        }
+        Ok(())
+    }
+
+    fn statement_lines(&mut self, node: &Node, depth: usize, span: Option<(usize, usize)>) -> Result<(), String> {
+        match node {
+            Node::Program(p) => p.statements.iter().try_for_each(|s| self.statement(s, depth)),
+            Node::DeclareStatement(d) => {
+                let mut head = format!("the {} {}", if d.is_mutable { "variable" } else { "constant" }, d.name);
+                if let Some(t) = &d.type_annotation {
+                    head.push_str(&format!(" of type {}", type_text(t)?));
+                    if is_void(&d.value) {
+                        self.push(depth, head);
+                        return Ok(());
+                    }
+                }
+                self.value_line(depth, &format!("{} is", head), &d.value)
+            }
+            Node::AssignStatement(a) => {
+                if !a.is_mutable {
+                    return Err("immutable assignment".to_string());
+                }
+                self.value_line(depth, &format!("set {} to", a.identifier), &a.expression)
+            }
+            Node::SetStatement(s) => {
+                let object = self.expr(&s.object)?;
+                let head = format!("set {} in {} to", s.property, object.needs(1, KEYWORD)?);
+                self.value_line(depth, &head, &s.value)
+            }
+            Node::IndexSetStatement(s) => {
+                let object = self.expr(&s.object)?;
+                let index = self.expr(&s.index)?;
+                let value = self.expr(&s.value)?;
+                let text = format!("{}[{}] = {}", object.needs(8, POSTFIX)?, index.needs(1, KEYWORD)?, value.needs(4, END)?);
+                self.push(depth, text);
+                Ok(())
+            }
+            Node::ExpressionStatement(e) => self.expression_statement(&e.expression, depth, span),
+            Node::CallExpression(c) => self.call_statement(c, depth, span),
+            Node::ConditionalStatement(c) => self.conditional(c, depth, span, "if"),
+            Node::LoopStatement(l) => {
+                match l.loop_type {
+                    LoopType::ForEach => {
+                        let collection = self.expr(l.collection.as_deref().ok_or("loop without collection")?)?;
+                        let iterator = l.iterator.as_deref().ok_or("loop without iterator")?;
+                        self.push(depth, format!("for each {} in {} do", iterator, collection.needs(1, KEYWORD)?));
+                        self.block(&l.body, depth + 1)?;
+                        self.closer(depth, "end for", span);
+                    }
+                    LoopType::While => {
+                        let condition = self.expr(l.condition.as_deref().ok_or("loop without condition")?)?;
+                        self.push(depth, format!("while {} do", condition.needs(1, KEYWORD)?));
+                        self.block(&l.body, depth + 1)?;
+                        self.closer(depth, "end while", span);
+                    }
+                    LoopType::Repeat => return Err("repeat loops have no syntax".to_string()),
+                }
+                Ok(())
+            }
+            Node::ReturnStatement(r) => match &r.expression {
+                Some(value) => self.value_line(depth, "return", value),
+                None => {
+                    self.push(depth, "return".to_string());
+                    Ok(())
+                }
+            },
+            Node::ThrowStatement(t) => {
+                let value = self.expr(&t.expression)?;
+                self.push(depth, format!("throw {}", value.needs(1, END)?));
+                Ok(())
+            }
+            Node::ImportStatement(i) => {
+                let module = if i.is_file_path { format!("file {}", quote(&i.module_name)?) } else { i.module_name.clone() };
+                let text = if i.specific_imports.is_empty() {
+                    match &i.alias {
+                        Some(alias) => format!("import {} as {}", module, alias),
+                        None => format!("import {}", module),
+                    }
+                } else {
+                    format!("from {} import {}", module, i.specific_imports.join(", "))
+                };
+                self.push(depth, text);
+                Ok(())
+            }
+            Node::TryCatchStatement(t) => {
+                self.push(depth, "try".to_string());
+                self.block(&t.try_block, depth + 1)?;
+                if let Some(catch) = &t.catch_block {
+                    match &t.error_variable {
+                        Some(name) => self.push(depth, format!("catch {}", name)),
+                        None => self.push(depth, "catch".to_string()),
+                    }
+                    self.block(catch, depth + 1)?;
+                }
+                self.closer(depth, "end try", span);
+                Ok(())
+            }
+            Node::UsingStatement(u) => {
+                let resource = self.expr(&u.resource)?;
+                self.push(depth, format!("using {} as {} do", resource.needs(1, KEYWORD)?, u.identifier));
+                // the body ends where the indentation does
+                self.block(&u.body, depth + 1)
+            }
+            Node::RunConcurrentlyStatement(r) => {
+                self.push(depth, "run concurrently".to_string());
+                r.statements.iter().try_for_each(|s| self.statement(s, depth + 1))?;
+                self.closer(depth, "and wait for all", span);
+                Ok(())
+            }
+            Node::TestStatement(t) => {
+                self.push(depth, format!("test {}", quote(&t.name)?));
+                self.block(&t.body, depth + 1)?;
+                self.closer(depth, "end test", span);
+                Ok(())
+            }
+            Node::InspectStatement(i) => {
+                let value = self.expr(&i.expression)?;
+                self.push(depth, format!("inspect {}", value.needs(1, END)?));
+                for case in &i.cases {
+                    self.push(depth + 1, format!("case {} do", case.variant_name));
+                    self.block(&case.body, depth + 2)?;
+                }
+                Ok(())
+            }
+            Node::FunctionDeclaration(f) => self.function(f, depth),
+            Node::ClassDeclaration(c) => {
+                let head = match &c.extends {
+                    Some(parent) => format!("define class {} that extends {}", c.name, parent),
+                    None => format!("define class {} that has", c.name),
+                };
+                self.push(depth, head);
+                for property in &c.properties {
+                    let text = match &property.type_annotation {
+                        Type::GenericType(name) => format!("property {} which is {}", property.name, name),
+                        other => format!("property {} of type {}", property.name, type_text(other)?),
+                    };
+                    let mut line = Line::new(depth + 1, text);
+                    line.source_line = Some(property.location.line);
+                    line.trailing = Some(property.location.line);
+                    self.lines.push(line);
+                }
+                for method in &c.methods {
+                    if method.is_async {
+                        return Err("background methods have no syntax".to_string());
+                    }
+                    let start = self.lines.len();
+                    self.function(method, depth + 1)?;
+                    self.lines[start].source_line = Some(method.location.line);
+                    self.lines[start].trailing = Some(method.location.line);
+                }
+                Ok(())
+            }
+            Node::StructDeclaration(s) => {
+                let fields = s.properties
+                    .iter()
+                    .map(|p| Ok(format!("{} as {}", p.name, type_text(&p.type_annotation)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                let text = if fields.is_empty() {
+                    format!("define struct {}", s.name)
+                } else {
+                    format!("define struct {} with {}", s.name, fields.join(", "))
+                };
+                self.push(depth, text);
+                Ok(())
+            }
+            Node::TypeDeclaration(t) => {
+                let variants: Vec<String> = t.variants
+                    .iter()
+                    .map(|v| {
+                        if v.fields.is_empty() {
+                            v.name.clone()
+                        } else {
+                            let fields: Vec<String> = v.fields.iter().map(|(n, ty)| format!("{} of type {}", n, ty)).collect();
+                            format!("{} with {}", v.name, fields.join(" and "))
+                        }
+                    })
+                    .collect();
+                self.push(depth, format!("define type {} as either {}", t.name, variants.join(" or ")));
+                Ok(())
+            }
+            Node::StartExpression(_) | Node::WaitExpression(_) | Node::MethodCall(_) => {
+                let value = self.expr(node)?;
+                self.push(depth, value.text);
+                Ok(())
+            }
+            other => Err(format!("cannot format {}", other)),
+        }
+    }
+
+    fn expression_statement(&mut self, expression: &Node, depth: usize, span: Option<(usize, usize)>) -> Result<(), String> {
+        match expression {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }) => {
+                // prose the parser skipped: keep the original line, re-indented
+                let (first, last) = span.ok_or("skipped line without a span")?;
+                for number in first..=last {
+                    let text = self.source.get(number - 1).map_or("", |l| l.trim());
+                    let mut line = Line::new(depth, text.to_string());
+                    line.source_line = Some(number);
+                    line.end = Some(number);
+                    line.text = format!("{}{}", INDENT.repeat(depth), text);
+                    line.verbatim = true;
+                    self.lines.push(line);
+                }
+                Ok(())
+            }
+            Node::CallExpression(c) if c.function_name == "print" && c.arguments.len() == 1 => {
+                let value = self.expr(&c.arguments[0])?;
+                self.push(depth, format!("print {}", value.needs(1, END)?));
+                Ok(())
+            }
+            Node::CallExpression(c) if c.function_name == "exit" && c.arguments.len() == 1 => {
+                let text = match &c.arguments[0] {
+                    Node::LiteralExpression(LiteralExpression { value: LiteralValue::Number(n), .. }) if *n == 0.0 => "exit".to_string(),
+                    code => format!("exit with code {}", self.expr(code)?.needs(1, END)?),
+                };
+                self.push(depth, text);
+                Ok(())
+            }
+            other => {
+                let value = self.expr(other)?;
+                self.push(depth, value.needs(1, END)?.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    fn call_statement(&mut self, call: &CallExpression, depth: usize, span: Option<(usize, usize)>) -> Result<(), String> {
+        match (call.function_name.as_str(), call.arguments.as_slice()) {
+            ("expect", [actual, expected]) => {
+                let actual = self.expr(actual)?;
+                let expected = self.expr(expected)?;
+                let text = format!("expect {} is {}", actual.needs(3, COMPARISON)?, expected.needs(4, END)?);
+                self.push(depth, text);
+                Ok(())
+            }
+            ("describe", [Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(name), .. }), body]) => {
+                self.push(depth, format!("describe {}", quote(name)?));
+                self.block(body, depth + 1)?;
+                self.closer(depth, "end describe", span);
+                Ok(())
+            }
+            _ => Err(format!("cannot format a bare call to {}", call.function_name)),
+        }
+    }
+
+    fn conditional(&mut self, c: &ConditionalStatement, depth: usize, span: Option<(usize, usize)>, keyword: &str) -> Result<(), String> {
+        let condition = self.expr(&c.condition)?;
+        self.push(depth, format!("{} {} then", keyword, condition.needs(1, KEYWORD)?));
+        self.block(&c.then_branch, depth + 1)?;
+        match c.else_branch.as_deref() {
+            None => self.closer(depth, "end if", span),
+            Some(Node::ConditionalStatement(chained)) => return self.conditional(chained, depth, span, "else if"),
+            Some(other) => {
+                self.push(depth, "else".to_string());
+                self.block(other, depth + 1)?;
+                self.closer(depth, "end if", span);
+            }
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, f: &FunctionDeclaration, depth: usize) -> Result<(), String> {
+        let mut head = format!("define {}function {}", if f.is_async { "background " } else { "" }, f.name);
+        if !f.parameters.is_empty() {
+            let parameters = f.parameters
+                .iter()
+                .map(|p| match &p.type_annotation {
+                    Some(t) => Ok(format!("{} as {}", p.name, type_text(t)?)),
+                    None => Ok(p.name.clone()),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            head.push_str(&format!(" that takes {}", parameters.join(" and ")));
+        }
+        if let Some(t) = &f.return_type {
+            let joiner = if f.parameters.is_empty() { "" } else { " and" };
+            head.push_str(&format!("{} returns {}", joiner, type_text(t)?));
+        }
+        self.push(depth, head);
+        self.block(&f.body, depth + 1)
+    }
+
+    /// `head value`, spreading a new object with more than two properties over indented lines
+    fn value_line(&mut self, depth: usize, head: &str, value: &Node) -> Result<(), String> {
+        if let Node::ObjectCreation(o) = value {
+            if o.properties.len() > 2 {
+                self.push(depth, format!("{} a new {} with", head, o.class_name));
+                for property in &o.properties {
+                    let value = self.expr(&property.value)?;
+                    self.push(depth + 1, format!("{} is {}", property.name, value.needs(1, END)?));
+                }
+                return Ok(());
+            }
+        }
+        let value = self.expr(value)?;
+        self.push(depth, format!("{} {}", head, value.needs(1, END)?));
+        Ok(())
+    }
+
+    fn expr(&mut self, node: &Node) -> Result<Printed, String> {
+        match node {
+            Node::LiteralExpression(l) => Ok(Printed::closed(match &l.value {
+                LiteralValue::Number(n) => number_text(*n)?,
+                LiteralValue::String(s) => quote(s)?,
+                LiteralValue::Bool(b) => b.to_string(),
+                LiteralValue::Nothing => "nothing".to_string(),
+                LiteralValue::Void => return Err("an empty value has no syntax".to_string()),
+            })),
+            Node::VariableExpression(v) => Ok(Printed::closed(v.identifier.clone())),
+            Node::OperationExpression(o) => self.operation(o),
+            Node::CallExpression(c) => self.call(c),
+            Node::MethodCall(m) => self.method_call(m),
+            Node::AccessExpression(a) => self.access(&a.object, &a.property),
+            Node::IndexExpression(i) => self.index(&i.object, &i.index),
+            Node::ObjectCreation(o) => {
+                if o.properties.is_empty() {
+                    return Ok(Printed::closed(format!("a new {}", o.class_name)));
+                }
+                let mut parts = Vec::new();
+                let mut last = None;
+                for (i, property) in o.properties.iter().enumerate() {
+                    let value = self.expr(&property.value)?;
+                    let follower = if i + 1 < o.properties.len() { COMMA } else { END };
+                    parts.push(format!("{} is {}", property.name, value.needs(1, follower)?));
+                    last = Some(value);
+                }
+                let text = format!("a new {} with {}", o.class_name, parts.join(", "));
+                Ok(Printed::open(text, 8, last.map_or(9, |v| v.tail.min(KEYWORD)), false))
+            }
+            Node::FunctionDeclaration(f) => {
+                let body = match (&*f.body, f.name.as_str()) {
+                    (Node::ReturnStatement(ReturnStatement { expression: Some(body), .. }), "<lambda>") => body,
+                    _ => return Err("only lambdas can be written inline".to_string()),
+                };
+                if f.parameters.is_empty() || f.is_async || f.return_type.is_some() || f.parameters.iter().any(|p| p.type_annotation.is_some()) {
+                    return Err("lambda cannot be written".to_string());
+                }
+                let body = self.expr(body)?;
+                let names: Vec<&str> = f.parameters.iter().map(|p| p.name.as_str()).collect();
+                let text = format!("function of {} returning {}", names.join(", "), body.needs(1, END)?);
+                Ok(Printed::open(text, 8, body.tail.min(KEYWORD), body.comma))
+            }
+            Node::StartExpression(s) => {
+                let value = self.expr(&s.expression)?;
+                Ok(Printed::ending_with(format!("start {}", value.needs(6, END)?), 6, &value, 6))
+            }
+            Node::WaitExpression(w) => {
+                let value = self.expr(&w.expression)?;
+                Ok(Printed::ending_with(format!("wait for {}", value.needs(6, END)?), 6, &value, 6))
+            }
+            other => Err(format!("cannot format {}", other)),
+        }
+    }
+
+    fn operation(&mut self, o: &OperationExpression) -> Result<Printed, String> {
+        let zero = matches!(&*o.left, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Number(n), .. }) if *n == 0.0);
+        let right = match &o.right {
+            Some(right) => right,
+            None if o.operator == Operator::Exists => {
+                let left = self.expr(&o.left)?;
+                return Ok(Printed { level: 3, ..Printed::closed(format!("{} exists", left.needs(3, COMPARISON)?)) });
+            }
+            None => return Err("operation without a right side".to_string()),
+        };
+        if o.operator == Operator::Not && zero {
+            let value = self.expr(right)?;
+            return Ok(Printed::ending_with(format!("not {}", value.needs(6, END)?), 6, &value, 6));
+        }
+        let is_duration = matches!(&**right, Node::CallExpression(c) if c.function_name == "__time_duration");
+        let right = self.expr(right)?;
+        let left_is_duration = matches!(&*o.left, Node::CallExpression(c) if c.function_name == "__time_duration");
+        if is_duration && !left_is_duration && matches!(o.operator, Operator::Plus | Operator::Minus) {
+            // "3 days after launch"
+            let date = self.expr(&o.left)?;
+            let direction = if o.operator == Operator::Plus { "after" } else { "before" };
+            let text = format!("{} {} {}", right.text, direction, date.needs(6, END)?);
+            return Ok(Printed::ending_with(text, 7, &date, 6));
+        }
+        if o.operator == Operator::Minus && zero && right.level >= 6 {
+            return Ok(Printed::ending_with(format!("-{}", right.text), 6, &right, 6));
+        }
+
+        let (level, word) = match o.operator {
+            Operator::Or => (1, "or"),
+            Operator::And => (2, "and"),
+            Operator::Equals => (3, "is"),
+            Operator::NotEquals => (3, "is not"),
+            Operator::GreaterThan => (3, "is greater than"),
+            Operator::LessThan => (3, "is less than"),
+            Operator::GreaterThanOrEqual => (3, "is greater than or equal to"),
+            Operator::LessThanOrEqual => (3, "is less than or equal to"),
+            Operator::Plus => (4, "plus"),
+            Operator::Minus => (4, "minus"),
+            Operator::Times => (5, "times"),
+            Operator::DividedBy => (5, "divided by"),
+            Operator::Modulo => (5, "modulo"),
+            Operator::Not | Operator::Exists => return Err("malformed operation".to_string()),
+        };
+        // "x is not y" and friends read as a different comparison; "equals" keeps plain equality
+        let first_word = right.text.split(' ').next().unwrap_or("").to_lowercase();
+        let word = if o.operator == Operator::Equals && ["not", "greater", "less", "before", "after"].contains(&first_word.as_str()) {
+            "equals"
+        } else {
+            word
+        };
+        let left = self.expr(&o.left)?;
+        let text = format!("{} {} {}", left.needs(level, if level == 2 { AND } else { level as i8 })?, word, right.needs(level + 1, END)?);
+        Ok(Printed::ending_with(text, level, &right, level as i8 + 1))
+    }
+
+    fn call(&mut self, c: &CallExpression) -> Result<Printed, String> {
+        let args = c.arguments.as_slice();
+        let string_arg = |node: &Node| match node {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(s), .. }) => Some(s.clone()),
+            _ => None,
+        };
+        match (c.function_name.as_str(), args) {
+            ("create_list", []) => Ok(Printed::closed("an empty list".to_string())),
+            ("create_list", items) => {
+                let items = self.separated(items)?;
+                Ok(Printed::open(format!("a list containing {}", items), 8, END, false))
+            }
+            ("create_dictionary", []) => Ok(Printed::closed("an empty dictionary".to_string())),
+            ("create_dictionary", entries) if entries.len() % 2 == 0 => {
+                let mut parts = Vec::new();
+                for (i, pair) in entries.chunks(2).enumerate() {
+                    let key = self.expr(&pair[0])?;
+                    let value = self.expr(&pair[1])?;
+                    let follower = if (i + 1) * 2 < entries.len() { COMMA } else { END };
+                    parts.push(format!("{} is {}", key.needs(4, COMPARISON)?, value.needs(4, follower)?));
+                }
+                Ok(Printed::open(format!("a dictionary containing {}", parts.join(", ")), 8, END, false))
+            }
+            ("__dict_from_pairs", [pairs]) => self.prefixed("a dictionary from pairs", pairs, TERM, 8),
+            ("__pattern_matches", [text, pattern]) => {
+                let text = self.expr(text)?;
+                let pattern = self.expr(pattern)?;
+                let out = format!("{} matches pattern {}", text.needs(3, COMPARISON)?, pattern.needs(4, END)?);
+                Ok(Printed::ending_with(out, 3, &pattern, TERM))
+            }
+            ("__maybe_index", [object, index]) => {
+                let inner = self.index(object, index)?;
+                Ok(Printed::ending_with(format!("maybe {}", inner.text), 6, &inner, 6))
+            }
+            ("__maybe_property", [object, property]) => {
+                let property = string_arg(property).ok_or("maybe without a property name")?;
+                let inner = self.access(object, &property)?;
+                Ok(Printed::ending_with(format!("maybe {}", inner.text), 6, &inner, 6))
+            }
+            ("__freeze", [value]) => self.prefixed("frozen copy of", value, 6, 6),
+            ("__time_duration", [amount, unit]) => {
+                let unit = string_arg(unit).ok_or("duration without a unit")?;
+                if string_arg(amount).is_some() {
+                    return Err("duration of a text".to_string());
+                }
+                let amount = self.expr(amount)?;
+                Ok(Printed { level: 7, ..Printed::closed(format!("{} {}", amount.needs(8, POSTFIX)?, unit)) })
+            }
+            ("__program_arguments", []) => Ok(Printed::closed("the arguments of the program".to_string())),
+            ("__io_input_lines", []) => Ok(Printed::closed("the lines of input".to_string())),
+            ("__io_read_all_input", []) => Ok(Printed::closed("read all input".to_string())),
+            ("__env_get", [name]) => self.prefixed("the environment variable", name, TERM, 8),
+            ("__io_ask", [prompt]) => self.prefixed("ask", prompt, TERM, 8),
+            ("__pattern_find_all", [text, pattern]) => self.find("all matches", text, pattern),
+            ("__pattern_find_all_groups", [text, pattern]) => self.find("all groups", text, pattern),
+            ("__pattern_find_groups", [text, pattern]) => self.find("groups", text, pattern),
+            ("__pattern_find_named_groups", [text, pattern]) => self.find("named groups", text, pattern),
+            ("__pattern_replace", [text, pattern, replacement]) => {
+                let pattern = self.expr(pattern)?;
+                let text = self.expr(text)?;
+                let replacement = self.expr(replacement)?;
+                let out = format!(
+                    "replace pattern {} in {} with {}",
+                    pattern.needs(4, KEYWORD)?,
+                    text.needs(4, KEYWORD)?,
+                    replacement.needs(4, END)?
+                );
+                Ok(Printed::ending_with(out, 8, &replacement, TERM))
+            }
+            ("__http_request", [method, url, body, headers]) => {
+                let method = string_arg(method).ok_or("request without a method")?;
+                let url = self.expr(url)?;
+                let mut text = if method == "GET" {
+                    format!("fetch {}", url.needs(4, KEYWORD)?)
+                } else {
+                    format!("send a {} to {}", method, url.needs(4, KEYWORD)?)
+                };
+                let mut last = url;
+                let mut joiner = "with";
+                for (word, part) in [("body", body), ("headers", headers)] {
+                    if matches!(part, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Nothing, .. })) {
+                        continue;
+                    }
+                    let value = self.expr(part)?;
+                    text.push_str(&format!(" {} {} {}", joiner, word, value.needs(4, KEYWORD)?));
+                    joiner = "and";
+                    last = value;
+                }
+                Ok(Printed::open(text, 8, last.tail.min(COMPARISON), true))
+            }
+            ("convert", [value, target]) => {
+                let target = string_arg(target).ok_or("convert without a target")?;
+                let value = self.expr(value)?;
+                Ok(Printed::closed(format!("convert {} to {}", value.needs(4, KEYWORD)?, target)))
+            }
+            ("__collections_filter" | "__collections_sort_by" | "__collections_group_by", [_, _]) => {
+                match self.query(c)? {
+                    Some(printed) => Ok(printed),
+                    None => self.generic_call(c),
+                }
+            }
+            ("__collections_count_where" | "__collections_find", [Node::VariableExpression(v), lambda]) => {
+                let Some(condition) = element_body(lambda) else {
+                    return self.generic_call(c);
+                };
+                let condition = self.query_part(condition)?;
+                let summary = if c.function_name == "__collections_count_where" { "number" } else { "first" };
+                let text = format!("the {} of {} where {}", summary, v.identifier, condition.needs(1, END)?);
+                Ok(Printed::open(text, 8, condition.tail.min(KEYWORD), condition.comma))
+            }
+            _ => self.generic_call(c),
+        }
+    }
+
+    /// `the xs where ... sorted by ...`, or None when the call is not shaped like a query
+    fn query(&mut self, c: &CallExpression) -> Result<Option<Printed>, String> {
+        let mut clauses = Vec::new();
+        let mut current = c;
+        let base = loop {
+            let (word, body) = match (current.function_name.as_str(), current.arguments.as_slice()) {
+                ("__collections_filter", [_, lambda]) => ("where", element_body(lambda)),
+                ("__collections_sort_by", [_, lambda]) => ("sorted by", element_body(lambda)),
+                ("__collections_group_by", [_, lambda]) => ("grouped by", element_body(lambda)),
+                _ => return Ok(None),
+            };
+            let Some(body) = body else { return Ok(None) };
+            clauses.push((word, body));
+            match &current.arguments[0] {
+                Node::VariableExpression(v) => break v.identifier.clone(),
+                Node::CallExpression(inner) => current = inner,
+                _ => return Ok(None),
+            }
+        };
+
+        let mut text = format!("the {}", base);
+        let mut tail = 9;
+        let mut comma = true;
+        let mut and_ok = true;
+        for (word, body) in clauses.into_iter().rev() {
+            let part = self.query_part(body)?;
+            let (level, parsed_at) = if word == "where" { (1, 1) } else { (4, TERM) };
+            text.push_str(&format!(" {} {}", word, part.needs(level, KEYWORD)?));
+            tail = part.tail.min(parsed_at - 1);
+            comma = part.comma;
+            and_ok = part.and_ok && word != "where";
+        }
+        Ok(Some(Printed { text, level: 8, tail, comma, and_ok }))
+    }
+
+    fn query_part(&mut self, body: &Node) -> Result<Printed, String> {
+        self.query_depth += 1;
+        let part = self.expr(body);
+        self.query_depth -= 1;
+        part
+    }
+
+    fn generic_call(&mut self, c: &CallExpression) -> Result<Printed, String> {
+        if c.arguments.is_empty() {
+            return Ok(Printed { level: 7, ..Printed::closed(format!("call function {}", c.function_name)) });
+        }
+        let args = self.arguments(&c.arguments, &["argument"])?;
+        let last = self.expr(c.arguments.last().unwrap())?;
+        let text = format!("call function {} with {}", c.function_name, args);
+        Ok(Printed::open(text, 7, last.tail.min(COMPARISON), false))
+    }
+
+    fn method_call(&mut self, m: &MethodCall) -> Result<Printed, String> {
+        let object = self.expr(&m.object)?;
+        if m.method_name == "contains_key" && m.arguments.len() == 1 {
+            let key = self.expr(&m.arguments[0])?;
+            let text = format!("{} contains key {}", object.needs(3, COMPARISON)?, key.needs(4, END)?);
+            return Ok(Printed::ending_with(text, 3, &key, TERM));
+        }
+        let head = format!("call {} on", m.method_name);
+        if m.arguments.is_empty() {
+            let text = format!("{} {}", head, object.needs(1, END)?);
+            return Ok(Printed::open(text, 7, object.tail.min(KEYWORD), object.comma));
+        }
+        let args = self.arguments(&m.arguments, &["argument", "value", "amount"])?;
+        let last = self.expr(m.arguments.last().unwrap())?;
+        let text = format!("{} {} with {}", head, object.needs(1, KEYWORD)?, args);
+        Ok(Printed::open(text, 7, last.tail.min(COMPARISON), false))
+    }
+
+    /// call arguments joined by "and", or by commas when an argument would swallow the "and";
+    /// arguments that start with a word the parser skips are marked with "argument"
+    fn arguments(&mut self, args: &[Node], skipped: &[&str]) -> Result<String, String> {
+        let values = args.iter().map(|a| self.expr(a)).collect::<Result<Vec<_>, String>>()?;
+        let joins_with_and = values[..values.len() - 1].iter().all(|v| v.needs(4, AND).is_ok());
+        let mut parts = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            let follower = match (i + 1 < values.len(), joins_with_and) {
+                (false, _) => END,
+                (true, true) => AND,
+                (true, false) => COMMA,
+            };
+            let text = value.needs(4, follower)?;
+            let first: String = text.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            if skipped.contains(&first.to_lowercase().as_str()) {
+                parts.push(format!("argument {}", text));
+            } else {
+                parts.push(text.to_string());
+            }
+        }
+        Ok(parts.join(if joins_with_and { " and " } else { ", " }))
+    }
+
+    fn separated(&mut self, items: &[Node]) -> Result<String, String> {
+        let mut parts = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let value = self.expr(item)?;
+            let follower = if i + 1 < items.len() { COMMA } else { END };
+            parts.push(value.needs(4, follower)?.to_string());
+        }
+        Ok(parts.join(", "))
+    }
+
+    /// `words operand`, where the operand is parsed at `operand_level`
+    fn prefixed(&mut self, words: &str, operand: &Node, operand_level: i8, level: u8) -> Result<Printed, String> {
+        let value = self.expr(operand)?;
+        let text = format!("{} {}", words, value.needs(operand_level as u8, END)?);
+        Ok(Printed::ending_with(text, level, &value, operand_level))
+    }
+
+    fn find(&mut self, what: &str, text: &Node, pattern: &Node) -> Result<Printed, String> {
+        let pattern = self.expr(pattern)?;
+        let text = self.expr(text)?;
+        let out = format!("find {} of {} in {}", what, pattern.needs(4, KEYWORD)?, text.needs(4, END)?);
+        Ok(Printed::ending_with(out, 8, &text, TERM))
+    }
+
+    fn access(&mut self, object: &Node, property: &str) -> Result<Printed, String> {
+        if self.query_depth > 0 {
+            if let Node::VariableExpression(v) = object {
+                if v.identifier == "each" {
+                    return Ok(Printed::closed(format!("each {}", property)));
+                }
+            }
+        }
+        let object = self.expr(object)?;
+        if object.needs(8, POSTFIX).is_ok() && !object.text.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(Printed::closed(format!("{}.{}", object.text, property)));
+        }
+        let text = format!("get {} from {}", property, object.needs(4, END)?);
+        Ok(Printed::ending_with(text, 8, &object, TERM))
+    }
+
+    fn index(&mut self, object: &Node, index: &Node) -> Result<Printed, String> {
+        let object = self.expr(object)?;
+        let index = self.expr(index)?;
+        if object.needs(8, POSTFIX).is_ok() {
+            return Ok(Printed::closed(format!("{}[{}]", object.text, index.needs(1, KEYWORD)?)));
+        }
+        let text = format!("item {} of {}", index.needs(4, KEYWORD)?, object.needs(4, END)?);
+        Ok(Printed::ending_with(text, 8, &object, TERM))
+    }
+}
+
+/// the condition or key of a query's `each` function
+fn element_body(node: &Node) -> Option<&Node> {
+    match node {
+        Node::FunctionDeclaration(f)
+            if f.name == "<lambda>"
+                && f.parameters.len() == 1
+                && f.parameters[0].name == "each"
+                && f.parameters[0].type_annotation.is_none()
+                && f.return_type.is_none()
+                && !f.is_async =>
+        {
+            match &*f.body {
+                Node::ReturnStatement(ReturnStatement { expression: Some(body), .. }) => Some(body),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_void(node: &Node) -> bool {
+    matches!(node, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }))
+}
+
+fn number_text(n: f64) -> Result<String, String> {
+    if !n.is_finite() || n < 0.0 {
+        return Err(format!("{} has no literal syntax", n));
+    }
+    if n.fract() == 0.0 && n < 1e15 {
+        Ok(format!("{}", n as i64))
+    } else {
+        Ok(format!("{}", n))
+    }
+}
+
+/// text in double quotes, or single quotes when it contains a double quote
+fn quote(text: &str) -> Result<String, String> {
+    if text.contains('\n') {
+        return Err("text spans lines".to_string());
+    }
+    if !text.contains(['"', '\u{201d}']) {
+        Ok(format!("\"{}\"", text))
+    } else if !text.contains(['\'', '\u{2019}']) {
+        Ok(format!("'{}'", text))
+    } else {
+        Err("text contains both kinds of quote".to_string())
+    }
+}
+
+fn type_text(t: &Type) -> Result<String, String> {
+    Ok(match t {
+        Type::BasicType(b) => match b {
+            BasicType::Number => "Number",
+            BasicType::String => "String",
+            BasicType::Bool => "Bool",
+            BasicType::Void => "Void",
+            BasicType::Any => "Any",
+            BasicType::Date => "Date",
+            BasicType::Duration => "Duration",
+        }
+        .to_string(),
+        Type::CompositeType(CompositeType::List(inner)) => format!("List of {}", type_text(inner)?),
+        Type::CompositeType(CompositeType::DictionaryType(k, v)) => format!("Dictionary of {} to {}", type_text(k)?, type_text(v)?),
+        Type::CompositeType(CompositeType::Maybe(inner)) => format!("Maybe {}", type_text(inner)?),
+        Type::ClassType(name) | Type::GenericType(name) => name.clone(),
+        other => return Err(format!("{:?} has no syntax", other)),
+    })
+}
+
+/// format files in place, or with `check` only report the ones that would change
+pub fn format_paths(paths: &[String], check: bool, format: MessageFormat) -> Result<(), String> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_files(std::path::Path::new(path), &mut files)?;
+    }
+    files.sort();
+
+    let mut unformatted = Vec::new();
+    let mut failures = Vec::new();
+    for file in &files {
+        let name = file.to_string_lossy().to_string();
+        let source = std::fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", name, e))?;
+        let formatted = match format_source(&source, &name) {
+            Ok(formatted) => formatted,
+            Err(diagnostic) => {
+                crate::report(&diagnostic, format);
+                failures.push(name);
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", name);
+        } else {
+            std::fs::write(file, formatted).map_err(|e| format!("Could not write {}: {}", name, e))?;
+            println!("formatted {}", name);
+        }
+        unformatted.push(name);
+    }
+
+    if !failures.is_empty() {
+        return Err(format!("{} file(s) could not be formatted", failures.len()));
+    }
+    if check && !unformatted.is_empty() {
+        return Err(format!("{} file(s) are not formatted", unformatted.len()));
+    }
+    Ok(())
+}
+
+pub fn collect_files(path: &std::path::Path, files: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    if path.is_dir() {
+        let entries = std::fs::read_dir(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        for entry in entries.flatten() {
+            let child = entry.path();
+            let hidden = child.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if !hidden && (child.is_dir() || child.extension().is_some_and(|e| e == "lay")) {
+                collect_files(&child, files)?;
+            }
+        }
+        Ok(())
+    } else if path.exists() {
+        files.push(path.to_path_buf());
+        Ok(())
+    } else {
+        Err(format!("{} does not exist", path.display()))
+    }
+}
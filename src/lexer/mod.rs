@@ -2,12 +2,133 @@
 // tokenizes natural english text into structured tokens
 
 use crate::ast::Location;
+use std::fmt;
+
+/// the words layman gives a meaning to, for completion and for "did you mean" hints
+pub const KEYWORDS: &[&str] = &[
+    "a", "all", "an", "and", "any", "as", "background", "by", "call", "case", "catch", "class", "concurrently",
+    "constant", "containing", "define", "describe", "dictionary", "divided", "do", "each", "either", "else", "end",
+    "equal", "equals", "exists", "expect", "false", "for", "from", "function", "get", "greater", "if", "import", "in",
+    "inspect", "is", "less", "list", "maybe", "minus", "modulo", "new", "not", "nothing", "of", "or", "otherwise",
+    "plus", "print", "property", "repeat", "return", "returns", "set", "takes", "test", "than", "that", "the", "then",
+    "throw", "times", "to", "true", "try", "type", "until", "variable", "wait", "while", "with",
+];
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
     pub location: Location,
     pub text: String,
+    /// whitespace, comments and line breaks between the previous line's end and this token
+    pub leading_trivia: Vec<Trivia>,
+    /// whitespace and a comment after this token on the same line
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl Token {
+    /// the token as it appeared in the source, trivia included
+    #[allow(dead_code)]
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        for trivia in &self.leading_trivia {
+            text.push_str(&trivia.text);
+        }
+        text.push_str(&self.text);
+        for trivia in &self.trailing_trivia {
+            text.push_str(&trivia.text);
+        }
+        text
+    }
+}
+
+/// source text the parser ignores, kept on the tokens so tools can reproduce the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    /// spaces, tabs and carriage returns
+    Whitespace,
+    /// a line break that is not a token (the one ending a comment line)
+    LineBreak,
+    /// a `#` comment, without its line break
+    Comment,
+    /// characters the lexer does not recognise
+    Skipped,
+}
+
+/// a `#` comment, read back out of the tokens' trivia by `comments`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub location: Location,
+    /// the comment including its leading `#`, without the line break
+    pub text: String,
+}
+
+/// the comments in a token stream, in source order; trivia carries no positions, so they are
+/// counted off from the start of the file
+pub fn comments(tokens: &[Token]) -> Vec<Comment> {
+    fn step(text: &str, line: &mut usize, column: &mut usize) {
+        for c in text.chars() {
+            if c == '\n' {
+                *line += 1;
+                *column = 0;
+            } else {
+                *column += 1;
+            }
+        }
+    }
+    let mut comments = Vec::new();
+    let (mut line, mut column) = (1, 0);
+    for token in tokens {
+        // None stands for the token itself, between its leading and trailing trivia
+        let pieces = token.leading_trivia.iter().map(Some).chain([None]).chain(token.trailing_trivia.iter().map(Some));
+        for piece in pieces {
+            match piece {
+                Some(trivia) => {
+                    if trivia.kind == TriviaKind::Comment {
+                        let location = Location { file: token.location.file.clone(), line, column, source: None };
+                        comments.push(Comment { location, text: trivia.text.trim_end().to_string() });
+                    }
+                    step(&trivia.text, &mut line, &mut column);
+                }
+                None => step(&token.text, &mut line, &mut column),
+            }
+        }
+    }
+    comments
+}
+
+/// a lexer or parser error, at the characters it is about
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub location: Location,
+    /// how many characters it covers, usually the offending token
+    pub length: usize,
+}
+
+impl SyntaxError {
+    pub fn new(message: impl Into<String>, location: Location, length: usize) -> Self {
+        SyntaxError { message: message.into(), location, length }
+    }
+}
+
+/// "file:line:column: message", with the column counted from 0 like other located messages
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.location.file, self.location.line, self.location.column, self.message)
+    }
+}
+
+/// for callers that pass errors on as text
+impl From<SyntaxError> for String {
+    fn from(error: SyntaxError) -> String {
+        error.to_string()
+    }
 }
 
 #[allow(dead_code)]
@@ -133,9 +254,12 @@ impl Lexer {
             current_file: file,
         }
     }
-    
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, SyntaxError> {
         let mut tokens = Vec::new();
+        // the source between one token and the next, which becomes trivia
+        let mut gaps = Vec::new();
+        let mut gap_start = 0;
         
         while !self.is_at_end() {
             self.skip_whitespace();
@@ -153,7 +277,10 @@ impl Lexer {
             let start_line = self.line;
             let start_col = self.column;
             
-            let token = self.next_token()?;
+            let token = self.next_token().map_err(|message| {
+                let location = Location { file: self.current_file.clone(), line: start_line, column: start_col, source: None };
+                SyntaxError::new(message, location, self.position - start_pos)
+            })?;
             
             // check if we got EOF from unknown char (position should have advanced due to advance() in next_token)
             // if token is EOF but we're not actually at end, it means unknown char was skipped
@@ -183,7 +310,11 @@ impl Lexer {
                         source: Some(text.clone()),
                     },
                     text,
+                    leading_trivia: Vec::new(),
+                    trailing_trivia: Vec::new(),
                 });
+                gaps.push(gap_start..start_pos);
+                gap_start = self.position;
             } else {
                 // reached EOF naturally
                 break;
@@ -199,10 +330,53 @@ impl Lexer {
                 source: None,
             },
             text: String::new(),
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
         });
-        
+        gaps.push(gap_start..self.input.len());
+
+        self.attach_trivia(&mut tokens, &gaps);
         Ok(tokens)
     }
+
+    /// split the text between tokens into trivia: what follows a token on its own line trails it,
+    /// everything else leads the next token
+    fn attach_trivia(&self, tokens: &mut [Token], gaps: &[std::ops::Range<usize>]) {
+        for (index, gap) in gaps.iter().enumerate() {
+            let mut pieces = self.trivia_in(gap.clone());
+            if index > 0 && !matches!(tokens[index - 1].kind, TokenKind::Newline) {
+                let same_line = pieces.iter().position(|t| t.kind == TriviaKind::LineBreak).unwrap_or(pieces.len());
+                let rest = pieces.split_off(same_line);
+                tokens[index - 1].trailing_trivia = std::mem::replace(&mut pieces, rest);
+            }
+            tokens[index].leading_trivia = pieces;
+        }
+    }
+
+    fn trivia_in(&self, range: std::ops::Range<usize>) -> Vec<Trivia> {
+        let mut pieces: Vec<Trivia> = Vec::new();
+        let mut position = range.start;
+        while position < range.end {
+            let c = self.input[position];
+            let (kind, end) = match c {
+                '\n' => (TriviaKind::LineBreak, position + 1),
+                '#' => {
+                    let end = self.input[position..range.end].iter().position(|&c| c == '\n').map_or(range.end, |offset| position + offset);
+                    (TriviaKind::Comment, end)
+                }
+                ' ' | '\t' | '\r' => (TriviaKind::Whitespace, position + 1),
+                _ => (TriviaKind::Skipped, position + 1),
+            };
+            let text: String = self.input[position..end].iter().collect();
+            // runs of whitespace or skipped characters form one piece
+            match pieces.last_mut() {
+                Some(last) if last.kind == kind && matches!(kind, TriviaKind::Whitespace | TriviaKind::Skipped) => last.text.push_str(&text),
+                _ => pieces.push(Trivia { kind, text }),
+            }
+            position = end;
+        }
+        pieces
+    }
     
     fn next_token(&mut self) -> Result<TokenKind, String> {
         let c = self.advance();
@@ -4,6 +4,9 @@
 pub mod ast;
 pub mod lexer;
 pub mod parser;
+pub mod cst;
+pub mod diagnostics;
+pub mod suggest;
 pub mod evaluator;
 pub mod types;
 pub mod typechecker;
@@ -0,0 +1,678 @@
+// linter - checks for code that runs but is probably not what was meant
+// the rules walk the AST with the scoping the evaluator uses; each rule's severity can be set
+// in the [lint] table of layman.toml, and a `# lint: allow <rule>` comment silences it on one line
+
+use crate::ast::*;
+use crate::diagnostics::{codes, Diagnostic, Severity, Span};
+use crate::lexer::{self, Comment, Lexer};
+use crate::lpm::manifest::Manifest;
+use crate::parser::Parser;
+use crate::MessageFormat;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// every rule, with what it looks for
+pub const RULES: &[(&str, &str)] = &[
+    ("unused-variable", "a variable inside a function or block is never read"),
+    ("unused-parameter", "a function parameter is never read"),
+    ("unused-import", "a name imported with `from` or `as` is never used"),
+    ("shadowing", "a variable is declared again, or hides one from an enclosing scope"),
+    ("unreachable-code", "a statement comes after a return or throw"),
+    ("constant-condition", "an if or while condition can never change"),
+    ("empty-block", "a block has no statements"),
+    ("missing-return-type", "a function returns a value but declares no return type"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "off" => Some(Level::Off),
+            "warning" => Some(Level::Warning),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub line: usize,
+    /// 1-based
+    pub column: usize,
+    pub message: String,
+    /// another place the finding is about, e.g. the declaration a variable hides
+    pub related: Option<(usize, usize, String)>,
+    pub note: Option<String>,
+}
+
+fn rule_id(name: &str) -> Option<&'static str> {
+    RULES.iter().map(|(id, _)| *id).find(|id| *id == name)
+}
+
+/// run every rule over a file, leaving out findings silenced by `# lint: allow` comments
+pub fn lint_source(source: &str, file: &str) -> Result<Vec<Finding>, Box<Diagnostic>> {
+    let mut lexer = Lexer::new(source, file.to_string());
+    let tokens = lexer.tokenize().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::LEX, &e)))?;
+    let comments = lexer::comments(&tokens);
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::SYNTAX, &e)))?;
+
+    let mut spans = HashMap::new();
+    for span in parser.statement_spans() {
+        spans.entry((span.location.line, span.location.column)).or_insert((span.first_line, span.last_line));
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    let comment_lines = comments.iter().map(|c| c.location.line).collect();
+    let mut linter = Linter { spans, lines: &lines, comment_lines, scopes: Vec::new(), findings: Vec::new() };
+    linter.push_scope(true);
+    linter.block(&program);
+    linter.pop_scope();
+
+    let allowed = suppressions(&comments, &lines)?;
+    let mut findings: Vec<Finding> = linter
+        .findings
+        .into_iter()
+        .filter(|f| match allowed.get(&f.line) {
+            Some(None) => false,
+            Some(Some(rules)) => !rules.contains(f.rule),
+            None => true,
+        })
+        .collect();
+    findings.sort_by_key(|f| (f.line, f.column));
+    findings.dedup();
+    Ok(findings)
+}
+
+/// the lines silenced by `# lint: allow rule-a, rule-b` (None silences every rule).
+/// a comment after code covers its own line; a comment on a line by itself covers the next one
+fn suppressions(comments: &[Comment], lines: &[&str]) -> Result<HashMap<usize, Option<HashSet<&'static str>>>, Box<Diagnostic>> {
+    let mut allowed = HashMap::new();
+    for comment in comments {
+        let text = comment.text.trim_start_matches('#').trim();
+        let Some(rest) = text.strip_prefix("lint:") else {
+            continue;
+        };
+        let error = |message: String| {
+            let span = Span::new(&comment.location.file, comment.location.line, comment.location.column + 1, comment.text.chars().count());
+            Box::new(Diagnostic::error(codes::SYNTAX, message).with_span(span))
+        };
+        let Some(rest) = rest.trim().strip_prefix("allow") else {
+            return Err(error("expected `# lint: allow <rule>, ...`".to_string()));
+        };
+        let mut rules = HashSet::new();
+        for name in rest.split([',', ' ']).filter(|n| !n.is_empty()) {
+            let id = rule_id(name).ok_or_else(|| error(format!("unknown lint rule '{}'", name)))?;
+            rules.insert(id);
+        }
+
+        let own_line = lines.get(comment.location.line - 1).is_some_and(|l| l.trim_start().starts_with('#'));
+        let line = if own_line { comment.location.line + 1 } else { comment.location.line };
+        let entry = allowed.entry(line).or_insert_with(|| Some(HashSet::new()));
+        match (entry.as_mut(), rules.is_empty()) {
+            (Some(_), true) => *entry = None,
+            (Some(existing), false) => existing.extend(rules),
+            (None, _) => {}
+        }
+    }
+    Ok(allowed)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SymbolKind {
+    Variable,
+    Parameter,
+    Import,
+}
+
+struct Symbol {
+    name: String,
+    kind: SymbolKind,
+    line: usize,
+    column: usize,
+    used: bool,
+}
+
+struct Scope<'a> {
+    symbols: Vec<Symbol>,
+    /// function bodies, walked when the scope closes so they can see names declared after them
+    deferred: Vec<(&'a FunctionDeclaration, (usize, usize))>,
+    /// top-level variables are what other files import, so they are never unused
+    top_level: bool,
+}
+
+struct Linter<'a> {
+    /// statement location -> the first and last line of the statement
+    spans: HashMap<(usize, usize), (usize, usize)>,
+    lines: &'a [&'a str],
+    comment_lines: HashSet<usize>,
+    scopes: Vec<Scope<'a>>,
+    findings: Vec<Finding>,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, rule: &'static str, (line, column): (usize, usize), message: String) {
+        self.findings.push(Finding { rule, line, column, message, related: None, note: None });
+    }
+
+    /// point the last finding at a second place too
+    fn relate(&mut self, (line, column): (usize, usize), label: &str, note: String) {
+        if let Some(finding) = self.findings.last_mut() {
+            finding.related = Some((line, column, label.to_string()));
+            finding.note = Some(note);
+        }
+    }
+
+    /// where a finding about a statement goes: the start of its first line
+    fn position(&self, node: &Node) -> (usize, usize) {
+        self.position_at(&node.location())
+    }
+
+    fn position_at(&self, location: &Location) -> (usize, usize) {
+        match self.spans.get(&(location.line, location.column)) {
+            Some(&(line, _)) => {
+                let indent = self.lines.get(line - 1).map(|l| l.len() - l.trim_start().len()).unwrap_or(0);
+                (line, indent + 1)
+            }
+            None => (location.line, location.column + 1),
+        }
+    }
+
+    fn push_scope(&mut self, top_level: bool) {
+        self.scopes.push(Scope { symbols: Vec::new(), deferred: Vec::new(), top_level });
+    }
+
+    fn pop_scope(&mut self) {
+        loop {
+            let deferred = std::mem::take(&mut self.scopes.last_mut().unwrap().deferred);
+            if deferred.is_empty() {
+                break;
+            }
+            for (function, position) in deferred {
+                self.function(function, position);
+            }
+        }
+
+        let scope = self.scopes.pop().unwrap();
+        for symbol in scope.symbols {
+            if symbol.used || symbol.name.starts_with('_') {
+                continue;
+            }
+            let position = (symbol.line, symbol.column);
+            match symbol.kind {
+                SymbolKind::Variable if !scope.top_level => {
+                    self.report("unused-variable", position, format!("variable '{}' is never used", symbol.name))
+                }
+                SymbolKind::Parameter => {
+                    self.report("unused-parameter", position, format!("parameter '{}' is never used", symbol.name))
+                }
+                SymbolKind::Import => {
+                    self.report("unused-import", position, format!("'{}' is imported but never used", symbol.name))
+                }
+                SymbolKind::Variable => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: SymbolKind, position: (usize, usize), used: bool) {
+        let depth = self.scopes.len() - 1;
+        for (index, scope) in self.scopes.iter_mut().enumerate().rev() {
+            let Some(earlier) = scope.symbols.iter_mut().rev().find(|s| s.name == name) else {
+                continue;
+            };
+            let (line, column) = (earlier.line, earlier.column);
+            if index != depth && line >= position.0 {
+                // a function body is walked after its scope, so names declared below it are in view
+                break;
+            }
+            if index == depth {
+                // only the redeclaration is worth reporting, not the value it replaced
+                earlier.used = true;
+                self.report("shadowing", position, format!("'{}' is declared again; it was already declared on line {}", name, line));
+                self.relate((line, column), "first declared here", format!("to change its value, write `set {} to ...`", name));
+            } else {
+                let what = match earlier.kind {
+                    SymbolKind::Variable => "variable",
+                    SymbolKind::Parameter => "parameter",
+                    SymbolKind::Import => "import",
+                };
+                self.report("shadowing", position, format!("'{}' hides the {} declared on line {}", name, what, line));
+                let note = format!("this is a new variable; to change the outer one, write `set {} to ...`", name);
+                self.relate((line, column), &format!("the hidden {}", what), note);
+            }
+            break;
+        }
+        let symbol = Symbol { name: name.to_string(), kind, line: position.0, column: position.1, used };
+        self.scopes.last_mut().unwrap().symbols.push(symbol);
+    }
+
+    fn read(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(symbol) = scope.symbols.iter_mut().rev().find(|s| s.name == name) {
+                symbol.used = true;
+                return;
+            }
+        }
+    }
+
+    fn read_type(&mut self, annotation: &Type) {
+        match annotation {
+            Type::ClassType(name) => self.read(name),
+            Type::CompositeType(CompositeType::List(t) | CompositeType::Maybe(t) | CompositeType::Set(t)) => self.read_type(t),
+            Type::CompositeType(CompositeType::DictionaryType(k, v)) => {
+                self.read_type(k);
+                self.read_type(v);
+            }
+            Type::CompositeType(CompositeType::Tuple(types)) => types.iter().for_each(|t| self.read_type(t)),
+            Type::FunctionType(f) => {
+                f.parameters.iter().for_each(|t| self.read_type(t));
+                self.read_type(&f.return_type);
+            }
+            Type::BasicType(_) | Type::GenericType(_) => {}
+        }
+    }
+
+    /// a block's statements, noting the first one that can never run
+    fn block(&mut self, node: &'a Node) {
+        let statements: Vec<&'a Node> = match node {
+            Node::Program(program) => program.statements.iter().collect(),
+            other => vec![other],
+        };
+        let mut ended = false;
+        let mut reported = false;
+        for statement in statements.into_iter().filter(|s| !is_void(s)) {
+            if ended && !reported {
+                let position = self.position(statement);
+                self.report("unreachable-code", position, "this code can never run".to_string());
+                reported = true;
+            }
+            self.statement(statement);
+            ended |= terminates(statement);
+        }
+    }
+
+    /// whether a statement has a comment inside it, which is taken to explain an empty block
+    fn commented(&self, location: &Location) -> bool {
+        self.spans
+            .get(&(location.line, location.column))
+            .is_some_and(|&(first, last)| (first + 1..=last).any(|line| self.comment_lines.contains(&line)))
+    }
+
+    fn empty_block(&mut self, owner: &Node, block: &Node, what: &str) {
+        if is_empty(block) && !self.commented(&owner.location()) {
+            let position = self.position(owner);
+            self.report("empty-block", position, format!("empty {}", what));
+        }
+    }
+
+    fn constant_condition(&mut self, owner: &Node, condition: &Node) {
+        if !is_constant(condition) {
+            return;
+        }
+        let message = match condition {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::Bool(value), .. }) => {
+                format!("condition is always {}", value)
+            }
+            _ => "condition is made only of constants".to_string(),
+        };
+        let position = self.position(owner);
+        self.report("constant-condition", position, message);
+    }
+
+    fn statement(&mut self, node: &'a Node) {
+        match node {
+            Node::DeclareStatement(s) => {
+                self.expression(&s.value);
+                if let Some(annotation) = &s.type_annotation {
+                    self.read_type(annotation);
+                }
+                let position = self.position(node);
+                self.declare(&s.name, SymbolKind::Variable, position, false);
+            }
+            Node::AssignStatement(s) => self.expression(&s.expression),
+            Node::ConditionalStatement(s) => {
+                self.constant_condition(node, &s.condition);
+                self.expression(&s.condition);
+                self.empty_block(node, &s.then_branch, "'then' block");
+                self.block(&s.then_branch);
+                if let Some(else_branch) = &s.else_branch {
+                    self.empty_block(node, else_branch, "'else' block");
+                    self.block(else_branch);
+                }
+            }
+            Node::LoopStatement(s) => {
+                if let Some(condition) = &s.condition {
+                    // `while true` is how a loop that ends with return is written
+                    let forever = matches!(s.loop_type, LoopType::While)
+                        && matches!(&**condition, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Bool(true), .. }));
+                    if matches!(s.loop_type, LoopType::While) && !forever {
+                        self.constant_condition(node, condition);
+                    }
+                    self.expression(condition);
+                }
+                if let Some(collection) = &s.collection {
+                    self.expression(collection);
+                }
+                self.empty_block(node, &s.body, "loop body");
+                match &s.iterator {
+                    Some(iterator) => {
+                        self.push_scope(false);
+                        let position = self.position(node);
+                        self.declare(iterator, SymbolKind::Variable, position, false);
+                        self.block(&s.body);
+                        self.pop_scope();
+                    }
+                    None => self.block(&s.body),
+                }
+            }
+            Node::ReturnStatement(s) => {
+                if let Some(expression) = &s.expression {
+                    self.expression(expression);
+                }
+            }
+            Node::ImportStatement(s) => {
+                let position = self.position(node);
+                if !s.specific_imports.is_empty() {
+                    for name in &s.specific_imports {
+                        self.declare(name, SymbolKind::Import, position, false);
+                    }
+                } else if let Some(alias) = &s.alias {
+                    self.declare(alias, SymbolKind::Import, position, false);
+                }
+                // a plain `import io` names nothing here, so its use can't be checked without the module
+            }
+            Node::ExpressionStatement(s) => self.expression(&s.expression),
+            Node::ThrowStatement(s) => self.expression(&s.expression),
+            Node::TryCatchStatement(s) => {
+                self.empty_block(node, &s.try_block, "'try' block");
+                self.block(&s.try_block);
+                if let Some(catch_block) = &s.catch_block {
+                    self.empty_block(node, catch_block, "'catch' block");
+                    self.push_scope(false);
+                    if let Some(error) = &s.error_variable {
+                        let position = self.position(node);
+                        self.declare(error, SymbolKind::Variable, position, true);
+                    }
+                    self.block(catch_block);
+                    self.pop_scope();
+                }
+            }
+            Node::UsingStatement(s) => {
+                self.expression(&s.resource);
+                self.push_scope(false);
+                let position = self.position(node);
+                // the resource is closed at the end even if nothing reads it
+                self.declare(&s.identifier, SymbolKind::Variable, position, true);
+                self.block(&s.body);
+                self.pop_scope();
+            }
+            Node::SetStatement(s) => {
+                self.expression(&s.object);
+                self.expression(&s.value);
+            }
+            Node::IndexSetStatement(s) => {
+                self.expression(&s.object);
+                self.expression(&s.index);
+                self.expression(&s.value);
+            }
+            Node::RunConcurrentlyStatement(s) => s.statements.iter().for_each(|statement| self.statement(statement)),
+            Node::TestStatement(s) => {
+                self.push_scope(false);
+                self.block(&s.body);
+                self.pop_scope();
+            }
+            Node::InspectStatement(s) => {
+                self.expression(&s.expression);
+                for case in &s.cases {
+                    self.push_scope(false);
+                    self.block(&case.body);
+                    self.pop_scope();
+                }
+            }
+            Node::FunctionDeclaration(f) => {
+                let position = self.position(node);
+                self.function_signature(f, position);
+                self.scopes.last_mut().unwrap().deferred.push((f, position));
+            }
+            Node::ClassDeclaration(c) => {
+                if let Some(parent) = &c.extends {
+                    self.read(parent);
+                }
+                c.properties.iter().for_each(|p| self.read_type(&p.type_annotation));
+                for method in &c.methods {
+                    let position = self.position_at(&method.location);
+                    self.function_signature(method, position);
+                    self.scopes.last_mut().unwrap().deferred.push((method, position));
+                }
+            }
+            Node::StructDeclaration(s) => s.properties.iter().for_each(|p| self.read_type(&p.type_annotation)),
+            Node::TypeDeclaration(_) | Node::ModuleDeclaration(_) => {}
+            Node::Program(_) => self.block(node),
+            _ => self.expression(node),
+        }
+    }
+
+    /// checks on a named function that do not need its body walked
+    fn function_signature(&mut self, function: &FunctionDeclaration, position: (usize, usize)) {
+        if function.return_type.is_none() && returns_value(&function.body) {
+            let message = format!("function '{}' returns a value but does not declare its return type", function.name);
+            self.report("missing-return-type", position, message);
+        }
+        if is_empty(&function.body) && !self.commented(&function.location) {
+            self.report("empty-block", position, format!("function '{}' has an empty body", function.name));
+        }
+    }
+
+    fn function(&mut self, function: &'a FunctionDeclaration, position: (usize, usize)) {
+        self.push_scope(false);
+        for parameter in &function.parameters {
+            if let Some(annotation) = &parameter.type_annotation {
+                self.read_type(annotation);
+            }
+            // `self` is bound for every method, `each` for every query
+            let implicit = parameter.name == "self" || parameter.name == "each";
+            self.declare(&parameter.name, SymbolKind::Parameter, position, implicit);
+        }
+        if let Some(annotation) = &function.return_type {
+            self.read_type(annotation);
+        }
+        self.block(&function.body);
+        self.pop_scope();
+    }
+
+    fn expression(&mut self, node: &'a Node) {
+        match node {
+            Node::VariableExpression(e) => self.read(&e.identifier),
+            Node::CallExpression(e) => {
+                // `math.sqrt` reads the module imported as `math`
+                let name = e.function_name.split('.').next().unwrap_or_default().to_string();
+                self.read(&name);
+                e.arguments.iter().for_each(|argument| self.expression(argument));
+            }
+            Node::OperationExpression(e) => {
+                self.expression(&e.left);
+                if let Some(right) = &e.right {
+                    self.expression(right);
+                }
+            }
+            Node::ConditionalExpression(e) => {
+                self.expression(&e.condition);
+                self.expression(&e.then_expr);
+                self.expression(&e.else_expr);
+            }
+            Node::AccessExpression(e) => self.expression(&e.object),
+            Node::IndexExpression(e) => {
+                self.expression(&e.object);
+                self.expression(&e.index);
+            }
+            Node::ObjectCreation(e) => {
+                self.read(&e.class_name);
+                e.properties.iter().for_each(|property| self.expression(&property.value));
+            }
+            Node::MethodCall(e) => {
+                self.expression(&e.object);
+                e.arguments.iter().for_each(|argument| self.expression(argument));
+            }
+            Node::StartExpression(e) => self.expression(&e.expression),
+            Node::WaitExpression(e) => self.expression(&e.expression),
+            Node::FunctionDeclaration(f) => {
+                let position = (f.location.line, f.location.column + 1);
+                self.scopes.last_mut().unwrap().deferred.push((f, position));
+            }
+            Node::LiteralExpression(_) => {}
+            _ => self.statement(node),
+        }
+    }
+}
+
+fn is_void(node: &Node) -> bool {
+    match node {
+        Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }) => true,
+        Node::ExpressionStatement(s) => is_void(&s.expression),
+        _ => false,
+    }
+}
+
+fn is_empty(block: &Node) -> bool {
+    match block {
+        Node::Program(program) => program.statements.iter().all(is_void),
+        other => is_void(other),
+    }
+}
+
+fn is_constant(node: &Node) -> bool {
+    match node {
+        Node::LiteralExpression(e) => !matches!(e.value, LiteralValue::Void),
+        Node::OperationExpression(e) => is_constant(&e.left) && e.right.as_deref().is_none_or(is_constant),
+        _ => false,
+    }
+}
+
+/// whether nothing after this statement in the same block can run
+fn terminates(node: &Node) -> bool {
+    match node {
+        Node::ReturnStatement(_) | Node::ThrowStatement(_) => true,
+        Node::Program(program) => program.statements.iter().any(terminates),
+        Node::ConditionalStatement(s) => {
+            terminates(&s.then_branch) && s.else_branch.as_deref().is_some_and(terminates)
+        }
+        _ => false,
+    }
+}
+
+/// whether a function body has a `return` with a value, outside any nested function
+fn returns_value(node: &Node) -> bool {
+    match node {
+        Node::ReturnStatement(s) => s.expression.is_some(),
+        Node::Program(program) => program.statements.iter().any(returns_value),
+        Node::ConditionalStatement(s) => {
+            returns_value(&s.then_branch) || s.else_branch.as_deref().is_some_and(returns_value)
+        }
+        Node::LoopStatement(s) => returns_value(&s.body),
+        Node::TryCatchStatement(s) => {
+            returns_value(&s.try_block) || s.catch_block.as_deref().is_some_and(returns_value)
+        }
+        Node::UsingStatement(s) => returns_value(&s.body),
+        Node::InspectStatement(s) => s.cases.iter().any(|case| returns_value(&case.body)),
+        _ => false,
+    }
+}
+
+/// the rule severities for a file: warnings, unless the nearest layman.toml says otherwise
+fn severities(file: &Path, cache: &mut HashMap<PathBuf, HashMap<&'static str, Level>>) -> Result<HashMap<&'static str, Level>, String> {
+    let absolute = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let manifest_path = absolute.ancestors().skip(1).map(|dir| dir.join("layman.toml")).find(|path| path.exists());
+    let Some(manifest_path) = manifest_path else {
+        return Ok(RULES.iter().map(|(id, _)| (*id, Level::Warning)).collect());
+    };
+    if let Some(severities) = cache.get(&manifest_path) {
+        return Ok(severities.clone());
+    }
+
+    let manifest = Manifest::load(&manifest_path).map_err(|e| format!("{:#}", e))?;
+    let mut severities: HashMap<&'static str, Level> = RULES.iter().map(|(id, _)| (*id, Level::Warning)).collect();
+    for (name, level) in &manifest.lint {
+        let id = rule_id(name).ok_or_else(|| format!("{}: unknown lint rule '{}'", manifest_path.display(), name))?;
+        let severity = Level::parse(level).ok_or_else(|| {
+            format!("{}: lint rule '{}' must be \"off\", \"warning\" or \"error\", not \"{}\"", manifest_path.display(), name, level)
+        })?;
+        severities.insert(id, severity);
+    }
+    cache.insert(manifest_path, severities.clone());
+    Ok(severities)
+}
+
+/// lint every .lay file under the given paths, failing if any finding is an error
+pub fn lint_paths(paths: &[String], format: MessageFormat) -> Result<(), String> {
+    let mut files = Vec::new();
+    for path in paths {
+        crate::formatter::collect_files(Path::new(path), &mut files)?;
+    }
+    files.sort();
+
+    let mut cache = HashMap::new();
+    let (mut errors, mut warnings, mut failures) = (0, 0, 0);
+    for file in &files {
+        let name = file.to_string_lossy().to_string();
+        let source = std::fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", name, e))?;
+        let findings = match lint_source(&source, &name) {
+            Ok(findings) => findings,
+            Err(diagnostic) => {
+                crate::report(&diagnostic, format);
+                failures += 1;
+                continue;
+            }
+        };
+        let severities = severities(file, &mut cache)?;
+        // underline a statement from where it starts to the end of its line
+        let lines: Vec<&str> = source.lines().collect();
+        let span = |line: usize, column: usize| {
+            let length = lines.get(line - 1).map_or(1, |text| text.trim_end().chars().count().saturating_sub(column - 1));
+            Span::new(&name, line, column, length)
+        };
+        for finding in findings {
+            let severity = match severities[finding.rule] {
+                Level::Off => continue,
+                Level::Warning => {
+                    warnings += 1;
+                    Severity::Warning
+                }
+                Level::Error => {
+                    errors += 1;
+                    Severity::Error
+                }
+            };
+            let mut diagnostic =
+                Diagnostic::new(severity, finding.rule, finding.message).with_span(span(finding.line, finding.column));
+            if let Some((line, column, label)) = finding.related {
+                diagnostic = diagnostic.with_label(span(line, column), label);
+            }
+            if let Some(note) = finding.note {
+                diagnostic = diagnostic.with_note(note);
+            }
+            match format {
+                MessageFormat::Human => println!("{}", diagnostic.render(&|_| Some(source.clone()))),
+                MessageFormat::Json => println!("{}", diagnostic.to_json()),
+            }
+        }
+    }
+
+    if errors + warnings > 0 && format == MessageFormat::Human {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if failures > 0 {
+        return Err(format!("{} file(s) could not be linted", failures));
+    }
+    if errors > 0 {
+        return Err(format!("{} lint error(s)", errors));
+    }
+    Ok(())
+}
@@ -44,6 +44,7 @@ pub fn init(path: Option<String>, name: Option<String>) -> Result<()> {
             authors: vec![],
         },
         dependencies: BTreeMap::new(),
+        lint: BTreeMap::new(),
     };
 
     manifest.save(&manifest_path)?;
@@ -0,0 +1,454 @@
+// language server for editors, speaking LSP over stdin and stdout
+//
+// every open document is lexed, parsed, resolved and type checked on each change. the last
+// analysis that got past the parser is kept, so hover, go-to-definition and completion keep
+// working while the text is briefly broken.
+
+pub mod protocol;
+
+use crate::ast::{Location, Node};
+use crate::diagnostics::{codes, Diagnostic, Severity, Span};
+use crate::typechecker::{Symbol, SymbolKind, TypeChecker};
+use crate::types::RuntimeType;
+use crate::{lexer, parser, resolver};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// LSP completion item kinds
+const KIND_METHOD: u8 = 2;
+const KIND_FUNCTION: u8 = 3;
+const KIND_VARIABLE: u8 = 6;
+const KIND_CLASS: u8 = 7;
+const KIND_PROPERTY: u8 = 10;
+const KIND_KEYWORD: u8 = 14;
+const KIND_CONSTANT: u8 = 21;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+struct Analysis {
+    checker: TypeChecker,
+    /// top-level imports as (line, module name), lines counting from 1
+    imports: Vec<(usize, String)>,
+    /// the line each top-level statement and method starts on, with where it starts if it is a
+    /// function or method, in line order
+    scopes: Vec<(usize, Option<Location>)>,
+}
+
+impl Analysis {
+    /// where the function or method a line (counting from 1) is inside starts
+    fn function_at(&self, line: usize) -> Option<&Location> {
+        self.scopes.iter().rfind(|(start, _)| *start <= line).and_then(|(_, function)| function.as_ref())
+    }
+}
+
+struct Document {
+    path: PathBuf,
+    text: String,
+    analysis: Option<Analysis>,
+}
+
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+/// serve one client on stdin and stdout; returns the exit code the client asked for
+pub fn run() -> Result<i32, String> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut input = stdin.lock();
+    let mut output = stdout.lock();
+    let mut server = Server::new();
+    while let Some(message) = protocol::read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            protocol::write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code {
+            return Ok(code);
+        }
+    }
+    // the client went away without asking the server to exit
+    Ok(if server.shutdown { 0 } else { 1 })
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            shutdown: false,
+            exit_code: None,
+        }
+    }
+
+    /// handle one request or notification; returns the messages to send back
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // a response to a request we never send
+            return Vec::new();
+        };
+        let params = &message["params"];
+        let id = message.get("id");
+
+        if self.shutdown && method != "exit" {
+            return id.map(|id| protocol::error_response(id, INVALID_REQUEST, "The server is shutting down")).into_iter().collect();
+        }
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                },
+                "serverInfo": { "name": "layman" },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let uri = document["uri"].as_str().unwrap_or_default();
+                let text = document["text"].as_str().unwrap_or_default();
+                return self.update(uri, text.to_string());
+            }
+            "textDocument/didChange" => {
+                // full synchronisation: the last change holds the whole text
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last()).and_then(|c| c["text"].as_str()) else {
+                    return Vec::new();
+                };
+                return self.update(uri, text.to_string());
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                // unknown notifications are ignored, unknown requests are refused
+                return id
+                    .map(|id| protocol::error_response(id, METHOD_NOT_FOUND, &format!("Unsupported method '{}'", method)))
+                    .into_iter()
+                    .collect();
+            }
+        };
+        match id {
+            Some(id) => vec![protocol::response(id, result)],
+            None => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> Vec<Value> {
+        let path = protocol::uri_to_path(uri).unwrap_or_else(|| PathBuf::from(uri));
+        let (diagnostics, analysis) = analyze(&path, &text);
+        let document = self.documents.entry(uri.to_string()).or_insert_with(|| Document {
+            path,
+            text: String::new(),
+            analysis: None,
+        });
+        document.text = text;
+        if analysis.is_some() {
+            document.analysis = analysis;
+        }
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    /// the document, its analysis and the 0-based line and character a request points at
+    fn locate(&self, params: &Value) -> Option<(&Document, &Analysis, usize, usize)> {
+        let document = self.documents.get(params["textDocument"]["uri"].as_str()?)?;
+        let analysis = document.analysis.as_ref()?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some((document, analysis, line, character))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((document, analysis, line, character)) = self.locate(params) else {
+            return Value::Null;
+        };
+        let Some((word, start, end)) = word_at(&document.text, line, character) else {
+            return Value::Null;
+        };
+        let file = document.path.display().to_string();
+        let Some(symbol) = find_symbol(analysis, &word, &file, line + 1) else {
+            return Value::Null;
+        };
+        json!({
+            "contents": { "kind": "markdown", "value": format!("```layman\n{}\n```", describe(&analysis.checker, symbol)) },
+            "range": protocol::range(line, start, end),
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((document, analysis, line, character)) = self.locate(params) else {
+            return Value::Null;
+        };
+        // on an import line, jump to the imported file
+        if let Some((_, module)) = analysis.imports.iter().find(|(import_line, _)| *import_line == line + 1) {
+            let base_dir = document.path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+            return match resolver::ImportResolver::new(base_dir).resolve_file_path(module) {
+                Ok(path) => json!({ "uri": protocol::path_to_uri(&path), "range": protocol::range(0, 0, 0) }),
+                Err(_) => Value::Null,
+            };
+        }
+        let Some((word, _, _)) = word_at(&document.text, line, character) else {
+            return Value::Null;
+        };
+        let file = document.path.display().to_string();
+        let Some(symbol) = find_symbol(analysis, &word, &file, line + 1) else {
+            return Value::Null;
+        };
+        let location = &symbol.location;
+        let start = location.column;
+        let end = start + location.source.as_deref().map_or(0, |s| s.chars().count());
+        let uri = if location.file == file {
+            params["textDocument"]["uri"].clone()
+        } else {
+            Value::String(protocol::path_to_uri(Path::new(&location.file)))
+        };
+        json!({ "uri": uri, "range": protocol::range(location.line.saturating_sub(1), start, end) })
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let Some((document, analysis, line, character)) = self.locate(params) else {
+            return json!([]);
+        };
+        let checker = &analysis.checker;
+        let file = document.path.display().to_string();
+        let before: String = document.text.lines().nth(line).unwrap_or("").chars().take(character).collect();
+
+        // after "object." offer the members of the object's class
+        let member_access = Regex::new(r"([A-Za-z_][A-Za-z0-9_]*)\.[A-Za-z0-9_]*$").unwrap();
+        if let Some(captures) = member_access.captures(&before) {
+            let class_name = match find_symbol(analysis, &captures[1], &file, line + 1).map(|s| &s.ty) {
+                Some(RuntimeType::Object(name)) | Some(RuntimeType::Class(name)) => name.clone(),
+                _ => return json!([]),
+            };
+            let Some(class) = checker.class_members(&class_name) else {
+                return json!([]);
+            };
+            let mut items: Vec<Value> = class
+                .properties
+                .iter()
+                .map(|(name, ty)| completion_item(name, KIND_PROPERTY, &checker.type_to_string(ty)))
+                .chain(class.methods.iter().map(|(name, sig)| {
+                    let function = RuntimeType::Function {
+                        parameters: sig.parameters.iter().map(|(_, ty)| ty.clone()).collect(),
+                        return_type: Box::new(sig.return_type.clone()),
+                    };
+                    completion_item(name, KIND_METHOD, &checker.type_to_string(&function))
+                }))
+                .collect();
+            items.sort_by(|a, b| a["label"].as_str().cmp(&b["label"].as_str()));
+            return Value::Array(items);
+        }
+
+        let function = analysis.function_at(line + 1);
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+        for symbol in checker.symbols().iter().rev() {
+            let in_scope = match symbol.kind {
+                // functions and classes can be used before they are declared, and come from imports
+                SymbolKind::Function | SymbolKind::Class => true,
+                _ => {
+                    symbol.location.file == file
+                        && symbol.location.line <= line + 1
+                        && (symbol.function.is_none() || symbol.function.as_ref() == function)
+                }
+            };
+            if !in_scope || symbol.name.starts_with("__") || !seen.insert(symbol.name.clone()) {
+                continue;
+            }
+            let kind = match symbol.kind {
+                SymbolKind::Variable | SymbolKind::Parameter => KIND_VARIABLE,
+                SymbolKind::Constant => KIND_CONSTANT,
+                SymbolKind::Function => KIND_FUNCTION,
+                SymbolKind::Class => KIND_CLASS,
+            };
+            items.push(completion_item(&symbol.name, kind, &checker.type_to_string(&symbol.ty)));
+        }
+        items.sort_by(|a, b| a["label"].as_str().cmp(&b["label"].as_str()));
+        items.extend(
+            lexer::KEYWORDS
+                .iter()
+                .filter(|keyword| !seen.contains(**keyword))
+                .map(|keyword| json!({ "label": keyword, "kind": KIND_KEYWORD })),
+        );
+        Value::Array(items)
+    }
+}
+
+/// lex, parse, resolve and type check a document; the analysis is None when it does not lex
+fn analyze(path: &Path, text: &str) -> (Vec<Value>, Option<Analysis>) {
+    let file = path.display().to_string();
+    let tokens = match lexer::Lexer::new(text, file.clone()).tokenize() {
+        Ok(tokens) => tokens,
+        Err(e) => return (vec![lsp_diagnostic(&Diagnostic::from_syntax_error(codes::LEX, &e))], None),
+    };
+    // the statements that did parse still give hover, completion and definitions
+    let (ast, syntax_errors) = parser::Parser::new(tokens).parse_recovering();
+    let syntax_diagnostics: Vec<Value> = syntax_errors
+        .iter()
+        .map(|e| lsp_diagnostic(&Diagnostic::from_syntax_error(codes::SYNTAX, e)))
+        .collect();
+    let imports: Vec<(usize, String)> = match &ast {
+        Node::Program(program) => program
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Node::ImportStatement(import) => Some((import.location.line, import.module_name.clone())),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut scopes = Vec::new();
+    if let Node::Program(program) = &ast {
+        for stmt in &program.statements {
+            match stmt {
+                Node::FunctionDeclaration(func) => scopes.push((func.location.line, Some(func.location.clone()))),
+                Node::ClassDeclaration(class) => {
+                    scopes.push((class.location.line, None));
+                    scopes.extend(class.methods.iter().map(|method| (method.location.line, Some(method.location.clone()))));
+                }
+                other => scopes.push((other.location().line, None)),
+            }
+        }
+    }
+
+    let base_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+    let mut checker = TypeChecker::new();
+    let bundled = match resolver::ImportResolver::new(base_dir).resolve_and_bundle(&ast) {
+        Ok(bundled) => bundled,
+        Err(mut diagnostic) => {
+            // a problem inside an imported file is shown on the import of that file
+            if diagnostic.span.as_ref().is_none_or(|span| span.file != file) {
+                let line = imports
+                    .iter()
+                    .find(|(_, module)| diagnostic.span.as_ref().is_some_and(|span| span.file.contains(module.as_str())))
+                    .map_or(1, |(line, _)| *line);
+                let length = word_at(text, line - 1, 0).map_or(1, |(word, _, _)| word.chars().count());
+                diagnostic.span = Some(Span::new(&file, line, 1, length));
+            }
+            let mut diagnostics = syntax_diagnostics;
+            diagnostics.push(lsp_diagnostic(&diagnostic));
+            return (diagnostics, Some(Analysis { checker, imports, scopes }));
+        }
+    };
+    let mut diagnostics = Vec::new();
+    if let Node::Program(program) = &bundled {
+        if let Err(errors) = checker.check_program(program) {
+            // errors inside imported files belong to those files
+            for error in errors.iter().filter(|error| error.location.file == file) {
+                diagnostics.push(lsp_diagnostic(&Diagnostic::from_type_error(error)));
+            }
+        }
+    }
+    // type errors in a file with broken lines are mostly about what those lines would have declared
+    if !syntax_diagnostics.is_empty() {
+        diagnostics = syntax_diagnostics;
+    }
+    (diagnostics, Some(Analysis { checker, imports, scopes }))
+}
+
+/// a diagnostic as LSP wants it: 0-based positions, on the line it starts on
+fn lsp_diagnostic(diagnostic: &Diagnostic) -> Value {
+    let (line, start, end) = diagnostic.span.as_ref().map_or((0, 0, 0), |span| {
+        let end = if span.end.line == span.start.line { span.end.column } else { span.start.column + 1 };
+        (span.start.line - 1, span.start.column - 1, end - 1)
+    });
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    json!({
+        "range": protocol::range(line, start, end),
+        "severity": severity,
+        "code": diagnostic.code,
+        "source": "layman",
+        // editors show no separate notes, so they go under the message
+        "message": std::iter::once(&diagnostic.message).chain(&diagnostic.notes).cloned().collect::<Vec<_>>().join("\n"),
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    protocol::notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": diagnostics }))
+}
+
+fn completion_item(label: &str, kind: u8, detail: &str) -> Value {
+    json!({ "label": label, "kind": kind, "detail": detail })
+}
+
+/// the identifier at a 0-based position, with the characters where it starts and ends
+fn word_at(text: &str, line: usize, character: usize) -> Option<(String, usize, usize)> {
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = character.min(chars.len());
+    // a cursor just after a word still points at it
+    if start == chars.len() || !is_word(chars[start]) {
+        if start > 0 && is_word(chars[start - 1]) {
+            start -= 1;
+        } else {
+            return None;
+        }
+    }
+    let mut end = start;
+    while start > 0 && is_word(chars[start - 1]) {
+        start -= 1;
+    }
+    while end < chars.len() && is_word(chars[end]) {
+        end += 1;
+    }
+    Some((chars[start..end].iter().collect(), start, end))
+}
+
+/// the declaration a name at `line` (counting from 1) refers to: the nearest one above it in
+/// the same file that is top-level or in the same function, else a later one, else a function
+/// or class from an imported file
+fn find_symbol<'a>(analysis: &'a Analysis, name: &str, file: &str, line: usize) -> Option<&'a Symbol> {
+    let symbols = analysis.checker.symbols();
+    let function = analysis.function_at(line);
+    let in_file = move || {
+        symbols.iter().filter(move |s| {
+            s.name == name && s.location.file == file && (s.function.is_none() || s.function.as_ref() == function)
+        })
+    };
+    in_file()
+        .rfind(|s| s.location.line <= line)
+        .or_else(|| in_file().next())
+        .or_else(|| symbols.iter().find(|s| s.name == name && matches!(s.kind, SymbolKind::Function | SymbolKind::Class)))
+}
+
+fn describe(checker: &TypeChecker, symbol: &Symbol) -> String {
+    let ty = checker.type_to_string(&symbol.ty);
+    match symbol.kind {
+        SymbolKind::Variable => format!("the variable {}: {}", symbol.name, ty),
+        SymbolKind::Constant => format!("the constant {}: {}", symbol.name, ty),
+        SymbolKind::Parameter => format!("parameter {}: {}", symbol.name, ty),
+        SymbolKind::Function => format!("function {}{}", symbol.name, ty.trim_start_matches("function")),
+        SymbolKind::Class => {
+            let mut text = format!("class {}", symbol.name);
+            if let Some(class) = checker.class_members(&symbol.name) {
+                let mut properties: Vec<_> = class.properties.iter().collect();
+                properties.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, ty) in properties {
+                    text.push_str(&format!("\n  property {}: {}", name, checker.type_to_string(ty)));
+                }
+            }
+            text
+        }
+    }
+}
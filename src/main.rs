@@ -3,6 +3,11 @@
 mod ast;
 mod lexer;
 mod parser;
+// the binary only reads doc comments from the syntax tree; rendering it back is for library users
+#[allow(dead_code)]
+mod cst;
+mod diagnostics;
+mod suggest;
 mod evaluator;
 mod types;
 mod typechecker;
@@ -11,8 +16,18 @@ mod stdlib;
 mod utils;
 mod verify;
 mod lpm;
+mod repl;
+mod lsp;
+mod formatter;
+mod lint;
+mod debugger;
+mod trace;
+mod profile;
+mod coverage;
+mod doc;
 
 use clap::{Parser, Subcommand};
+use diagnostics::{codes, Diagnostic};
 use std::fs;
 use std::path::Path;
 
@@ -22,11 +37,21 @@ use std::path::Path;
 struct Cli {
     /// The .lay file to compile and run (if no subcommand provided)
     file: Option<String>,
+
+    /// How errors and warnings are printed: `human` (under the source line) or `json` (one object per line)
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
     
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Compile a layman file (validate and create executable artifact)
@@ -41,6 +66,26 @@ enum Commands {
     Run {
         /// The .lay file to run (defaults to src/main.lay if in project)
         file: Option<String>,
+        /// Freeze the clock at this UTC time (e.g. 2026-01-01T00:00:00Z)
+        #[arg(long)]
+        fixed_time: Option<String>,
+        /// Narrate each statement, variable change, call and branch on stderr, as `english` or `json` lines
+        #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "english")]
+        trace: Option<trace::TraceFormat>,
+        /// Arguments passed to the program (after --)
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Run a layman file and report the calls, statements and time of each function
+    Profile {
+        /// The .lay file to profile (defaults to src/main.lay if in project)
+        file: Option<String>,
+        /// Also write folded stacks for flamegraph tools to this file
+        #[arg(long)]
+        folded: Option<String>,
+        /// Rank by statements executed instead of time, so the report is the same on every run
+        #[arg(long)]
+        steps: bool,
     },
     /// Run a compiled .layc file
     RunCompiled {
@@ -57,12 +102,55 @@ enum Commands {
         /// Directory containing test cases
         #[arg(default_value = "test-cases")]
         dir: String,
+        #[command(flatten)]
+        coverage: coverage::CoverageArgs,
     },
     /// Verify all tests (compiles, runs, compares to expected output)
     Verify {
         /// Directory containing test cases
         #[arg(default_value = "test-cases")]
         dir: String,
+        #[command(flatten)]
+        coverage: coverage::CoverageArgs,
+    },
+    /// Start an interactive session
+    Repl,
+    /// Start a language server for editors on stdin and stdout
+    Lsp,
+    /// Run a layman file under the step debugger
+    Debug {
+        /// The .lay file to debug (defaults to src/main.lay if in project)
+        file: Option<String>,
+        /// Speak the Debug Adapter Protocol on stdin and stdout instead of reading commands
+        #[arg(long)]
+        dap: bool,
+    },
+    /// Rewrite .lay files in the canonical layout
+    Fmt {
+        /// Files or directories to format (defaults to the current directory)
+        paths: Vec<String>,
+        /// Only list the files that would change, and fail if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Report likely mistakes in layman code, as configured in the [lint] table of layman.toml
+    Lint {
+        /// Files or directories to lint (defaults to the current directory)
+        paths: Vec<String>,
+    },
+    /// Generate API docs from the comments above functions, classes and types
+    Doc {
+        /// Files or directories to document (defaults to src, or the current directory)
+        paths: Vec<String>,
+        /// Write Markdown or HTML pages
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: doc::DocFormat,
+        /// The directory to write the pages to
+        #[arg(long, default_value = "docs")]
+        output: String,
+        /// Run the examples in doc comments instead, checking their `=> ` output lines
+        #[arg(long)]
+        test: bool,
     },
     /// Check a layman file for errors without running it
     Check {
@@ -109,9 +197,8 @@ fn main() {
     if let Some(file) = cli.file {
         if cli.command.is_none() {
             // layman hello.lay -> compile and run
-            if let Err(e) = run_file(&file) {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
+            if let Err(diagnostics) = run_file(&file, &RunOptions::default()) {
+                fail(&diagnostics, cli.message_format);
             }
             return;
         }
@@ -121,30 +208,33 @@ fn main() {
         Some(Commands::Compile { file, output }) => {
             match resolve_entry_file(file) {
                 Ok(f) => {
-                    if let Err(e) = compile_file(&f, output.as_deref()) {
-                        eprintln!("Error: {}", e);
-                        std::process::exit(1);
+                    if let Err(diagnostics) = compile_file(&f, output.as_deref()) {
+                        fail(&diagnostics, cli.message_format);
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
-                }
+                Err(e) => fail(&[Diagnostic::error(codes::IO, e)], cli.message_format),
             }
         }
-        Some(Commands::Run { file }) => {
+        Some(Commands::Run { file, fixed_time, trace, args }) => {
             // run directly (compiles on-the-fly)
             match resolve_entry_file(file) {
                 Ok(f) => {
-                    if let Err(e) = run_file(&f) {
-                        eprintln!("Error: {}", e);
-                        std::process::exit(1);
+                    let options = RunOptions { fixed_time, trace, arguments: args };
+                    if let Err(diagnostics) = run_file(&f, &options) {
+                        fail(&diagnostics, cli.message_format);
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
+                Err(e) => fail(&[Diagnostic::error(codes::IO, e)], cli.message_format),
+            }
+        }
+        Some(Commands::Profile { file, folded, steps }) => {
+            match resolve_entry_file(file) {
+                Ok(f) => {
+                    if let Err(diagnostics) = profile::profile_file(&f, folded.as_deref(), steps) {
+                        fail(&diagnostics, cli.message_format);
+                    }
                 }
+                Err(e) => fail(&[Diagnostic::error(codes::IO, e)], cli.message_format),
             }
         }
         Some(Commands::RunCompiled { file }) => {
@@ -159,22 +249,81 @@ fn main() {
                 std::process::exit(1);
             }
         }
-        Some(Commands::Test { dir }) => {
-            if let Err(e) = run_tests(&dir) {
+        Some(Commands::Test { dir, coverage }) => {
+            if let Err(e) = coverage::collect(&coverage, || run_tests(&dir)) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
-        Some(Commands::Verify { dir }) => {
-            if let Err(e) = verify::verify_all_tests(&dir) {
+        Some(Commands::Verify { dir, coverage }) => {
+            if let Err(e) = coverage::collect(&coverage, || verify::verify_all_tests(&dir)) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
-        Some(Commands::Check { file }) => {
-            if let Err(e) = check_file(&file) {
+        Some(Commands::Repl) => {
+            if let Err(e) = repl::run() {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
+            }
+        }
+        Some(Commands::Lsp) => {
+            match lsp::run() {
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Debug { file, dap }) => {
+            let result = if dap {
+                debugger::dap::run()
+            } else {
+                match resolve_entry_file(file) {
+                    Ok(f) => match fs::read_to_string(&f) {
+                        Ok(source) => match check_source(&f, &source) {
+                            Ok(ast) => debugger::run_terminal(&f, ast, evaluator::Evaluator::new()),
+                            Err(diagnostics) => fail(&diagnostics, cli.message_format),
+                        },
+                        Err(e) => Err(format!("Failed to read file {}: {}", f, e)),
+                    },
+                    Err(e) => fail(&[Diagnostic::error(codes::IO, e)], cli.message_format),
+                }
+            };
+            match result {
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Fmt { paths, check }) => {
+            let paths = if paths.is_empty() { vec![".".to_string()] } else { paths };
+            if let Err(e) = formatter::format_paths(&paths, check, cli.message_format) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Doc { paths, format, output, test }) => {
+            let default = if Path::new("src").is_dir() { "src" } else { "." };
+            let paths = if paths.is_empty() { vec![default.to_string()] } else { paths };
+            if let Err(e) = doc::document(&paths, format, &output, test, cli.message_format) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Lint { paths }) => {
+            let paths = if paths.is_empty() { vec![".".to_string()] } else { paths };
+            if let Err(e) = lint::lint_paths(&paths, cli.message_format) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Check { file }) => {
+            if let Err(diagnostics) = check_file(&file) {
+                fail(&diagnostics, cli.message_format);
             } else {
                 println!("Check successful: No errors found.");
             }
@@ -218,6 +367,53 @@ fn main() {
     }
 }
 
+/// print diagnostics to stderr and exit with status 1
+fn fail(diagnostics: &[Diagnostic], format: MessageFormat) -> ! {
+    for diagnostic in diagnostics {
+        report(diagnostic, format);
+    }
+    std::process::exit(1);
+}
+
+/// print a diagnostic to stderr, under its source line or as one line of JSON
+fn report(diagnostic: &Diagnostic, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => eprintln!("{}", diagnostic.render(&read_source)),
+        MessageFormat::Json => eprintln!("{}", diagnostic.to_json()),
+    }
+}
+
+/// the text of a file a diagnostic points into, for its source snippet
+fn read_source(file: &str) -> Option<String> {
+    fs::read_to_string(file).ok()
+}
+
+/// lex, parse, resolve imports and type check a file, stopping at the first stage that fails
+fn check_source(filename: &str, source: &str) -> Result<ast::Node, Vec<Diagnostic>> {
+    let tokens = lexer::Lexer::new(source, filename.to_string())
+        .tokenize()
+        .map_err(|e| vec![Diagnostic::from_syntax_error(codes::LEX, &e)])?;
+    // report every syntax error at once rather than one per run
+    let (ast, errors) = parser::Parser::new(tokens).parse_recovering();
+    if !errors.is_empty() {
+        return Err(errors.iter().map(|e| Diagnostic::from_syntax_error(codes::SYNTAX, e)).collect());
+    }
+
+    // bundle the imported files into a single AST
+    let base_dir = Path::new(filename).parent().unwrap_or(Path::new(".")).to_path_buf();
+    let ast = resolver::ImportResolver::new(base_dir)
+        .resolve_and_bundle(&ast)
+        .map_err(|e| vec![*e])?;
+
+    let ast::Node::Program(program) = &ast else {
+        return Err(vec![Diagnostic::error(codes::SYNTAX, "Expected program node")]);
+    };
+    typechecker::TypeChecker::new()
+        .check_program(program)
+        .map_err(|errors| errors.iter().map(Diagnostic::from_type_error).collect::<Vec<_>>())?;
+    Ok(ast)
+}
+
 // Helper to resolve entry file from optional path
 // If path is None -> check current dir for src/main.lay
 // If path is Directory -> check dir/src/main.lay
@@ -250,97 +446,37 @@ fn resolve_entry_file(path: Option<String>) -> Result<String, String> {
     Ok(target.to_string_lossy().to_string())
 }
 
-fn check_file(filename: &str) -> Result<(), String> {
+fn check_file(filename: &str) -> Result<(), Vec<Diagnostic>> {
     let path = Path::new(filename);
     if !path.exists() {
-        return Err(format!("File not found: {}", filename));
+        return Err(vec![Diagnostic::error(codes::IO, format!("File not found: {}", filename))]);
     }
     
     let source = fs::read_to_string(path)
-        .map_err(|e| format!("Could not read file: {}", e))?;
-        
-    // 1. Lex
-    let mut lexer = lexer::Lexer::new(&source, filename.to_string());
-    let tokens = lexer.tokenize()
-        .map_err(|e| format!("Lexer error: {}", e))?;
-        
-    // 2. Parse
-    let mut parser = parser::Parser::new(tokens);
-    let ast = parser.parse()
-        .map_err(|e| format!("Parser error: {}", e))?;
-        
-    // 3. Resolve Imports
-    let mut resolver = resolver::ImportResolver::new(path.parent().unwrap_or(Path::new(".")).to_path_buf());
-    let resolved_ast = resolver.resolve_and_bundle(&ast)
-        .map_err(|e| format!("Import resolution error: {}", e))?;
-        
-    // 4. Type Check
-    let mut type_checker = typechecker::TypeChecker::new();
-    
-    let program_node = match resolved_ast {
-        ast::Node::Program(prog) => prog,
-        _ => return Err("Expected program node".to_string()),
-    };
-    
-    type_checker.check_program(&program_node)
-        .map_err(|errors| {
-            let mut error_msg = String::from("Type errors:\n");
-            for err in errors {
-                error_msg.push_str(&format!("  {}:{}:{}: {}\n", err.location.file, err.location.line, err.location.column, err.message));
-            }
-            error_msg
-        })?;
-        
+        .map_err(|e| vec![Diagnostic::error(codes::IO, format!("Could not read file: {}", e))])?;
+    check_source(filename, &source)?;
     Ok(())
 }
 
 // compile a .lay file: validate and create a standalone native executable (no external runtimes)
-fn compile_file(filename: &str, output: Option<&str>) -> Result<(), String> {
+fn compile_file(filename: &str, output: Option<&str>) -> Result<(), Vec<Diagnostic>> {
     let content = fs::read_to_string(filename)
-        .map_err(|e| format!("Failed to read file {}: {}", filename, e))?;
-    
-    let file_name = filename.to_string();
-    
-    // step 1: lex
-    let mut lexer = lexer::Lexer::new(&content, file_name.clone());
-    let tokens = lexer.tokenize()?;
-    
-    // step 2: parse
-    let mut parser = parser::Parser::new(tokens);
-    let mut ast = parser.parse()?;
-
-    // step 2.5: resolve imports and bundle into single AST
-    let base_dir = std::path::Path::new(filename).parent()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| std::path::PathBuf::from("."));
-    let mut import_resolver = resolver::ImportResolver::new(base_dir);
-    ast = import_resolver.resolve_and_bundle(&ast)?;
+        .map_err(|e| vec![Diagnostic::error(codes::IO, format!("Failed to read file {}: {}", filename, e))])?;
+    let ast = check_source(filename, &content)?;
 
     // validate non-empty program
     if let ast::Node::Program(ref prog) = ast {
         if prog.statements.is_empty() {
-            return Err("no executable statements parsed; check syntax and grammar".to_string());
+            let message = "no executable statements parsed; check syntax and grammar";
+            return Err(vec![Diagnostic::error(codes::SYNTAX, message)]);
         }
     }
-    
-    // step 3: type check
-    let mut typechecker = typechecker::TypeChecker::new();
-    if let Err(type_errors) = typechecker.check_program(match &ast {
-        ast::Node::Program(prog) => prog,
-        _ => return Err("Expected program node".to_string()),
-    }) {
-        let mut error_msg = String::new();
-        error_msg.push_str("Type errors:\n");
-        for err in type_errors {
-            error_msg.push_str(&format!(
-                "  {}:{}:{}: {}\n",
-                err.location.file, err.location.line, err.location.column, err.message
-            ));
-        }
-        return Err(error_msg);
-    }
-    
-    // step 4: produce a native standalone executable by embedding the AST into a copy of this binary
+
+    write_executable(filename, output, &ast).map_err(|e| vec![Diagnostic::error(codes::IO, e)])
+}
+
+// produce a native standalone executable by embedding the AST into a copy of this binary
+fn write_executable(filename: &str, output: Option<&str>, ast: &ast::Node) -> Result<(), String> {
     let output_name = output.map(|s| s.to_string()).unwrap_or_else(|| {
         filename.strip_suffix(".lay")
             .map(|s| s.to_string())
@@ -348,7 +484,7 @@ fn compile_file(filename: &str, output: Option<&str>) -> Result<(), String> {
     });
     
     // serialize AST to JSON
-    let ast_json = serde_json::to_string(&ast)
+    let ast_json = serde_json::to_string(ast)
         .map_err(|e| format!("Failed to serialize AST: {}", e))?;
 
     // copy current executable to output
@@ -399,7 +535,13 @@ fn run_compiled_file(filename: &str) -> Result<(), String> {
     
     // execute the compiled AST
     let mut evaluator = evaluator::Evaluator::new();
-    evaluator.evaluate(&ast)?;
+    let recorder = coverage::record(&mut evaluator);
+    let result = evaluator.evaluate(&ast);
+    if let Some(recorder) = recorder {
+        recorder.save(&ast);
+    }
+    exit_if_requested(&evaluator);
+    result?;
     
     Ok(())
 }
@@ -444,7 +586,15 @@ fn try_run_embedded() -> Result<bool, String> {
             let ast: ast::Node = serde_json::from_str(json)
                 .map_err(|e| format!("failed to parse embedded program: {}", e))?;
             let mut evaluator = evaluator::Evaluator::new();
-            evaluator.evaluate(&ast)?;
+            // every argument of a compiled program belongs to the program
+            evaluator.set_program_arguments(std::env::args().skip(1).collect());
+            let recorder = coverage::record(&mut evaluator);
+            let result = evaluator.evaluate(&ast);
+            if let Some(recorder) = recorder {
+                recorder.save(&ast);
+            }
+            exit_if_requested(&evaluator);
+            result?;
             return Ok(true);
         } else {
              // eprintln!("DEBUG: payload end marker not found");
@@ -455,48 +605,51 @@ fn try_run_embedded() -> Result<bool, String> {
     Ok(false)
 }
 
+// settings for a single `layman run`
+#[derive(Default)]
+struct RunOptions {
+    fixed_time: Option<String>,
+    trace: Option<trace::TraceFormat>,
+    arguments: Vec<String>,
+}
+
+// a program that ran "exit with code N" ends the process with that status
+fn exit_if_requested(evaluator: &evaluator::Evaluator) {
+    if let Some(code) = evaluator.exit_code() {
+        std::process::exit(code);
+    }
+}
+
 // run a .lay file directly (compiles on-the-fly then executes)
-fn run_file(filename: &str) -> Result<(), String> {
+fn run_file(filename: &str, options: &RunOptions) -> Result<(), Vec<Diagnostic>> {
+    // validate the clock override before doing any work
+    let fixed_clock = match options.fixed_time.as_deref() {
+        Some(text) => Some(stdlib::time::parse_iso(text).ok_or_else(|| {
+            let message = format!("Invalid --fixed-time '{}': expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ", text);
+            vec![Diagnostic::error(codes::INVALID_OPTION, message)]
+        })?),
+        None => None,
+    };
+
     let content = fs::read_to_string(filename)
-        .map_err(|e| format!("Failed to read file {}: {}", filename, e))?;
+        .map_err(|e| vec![Diagnostic::error(codes::IO, format!("Failed to read file {}: {}", filename, e))])?;
+    let ast = check_source(filename, &content)?;
     
-    let file_name = filename.to_string();
-    
-    // step 1: lex
-    let mut lexer = lexer::Lexer::new(&content, file_name.clone());
-    let tokens = lexer.tokenize()?;
-    
-    // step 2: parse
-    let mut parser = parser::Parser::new(tokens);
-    let mut ast = parser.parse()?;
-    
-    // step 2.5: resolve imports and bundle into single AST
-    let base_dir = std::path::Path::new(filename).parent()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| std::path::PathBuf::from("."));
-    let mut import_resolver = resolver::ImportResolver::new(base_dir);
-    ast = import_resolver.resolve_and_bundle(&ast)?;
-    
-    // step 3: type check
-    let mut typechecker = typechecker::TypeChecker::new();
-    if let Err(type_errors) = typechecker.check_program(match &ast {
-        ast::Node::Program(prog) => prog,
-        _ => return Err("Expected program node".to_string()),
-    }) {
-        let mut error_msg = String::new();
-        error_msg.push_str("Type errors:\n");
-        for err in type_errors {
-            error_msg.push_str(&format!(
-                "  {}:{}:{}: {}\n",
-                err.location.file, err.location.line, err.location.column, err.message
-            ));
-        }
-        return Err(error_msg);
-    }
-    
-    // step 4: execute
     let mut evaluator = evaluator::Evaluator::new();
-    evaluator.evaluate(&ast)?;
+    if let Some(seconds) = fixed_clock {
+        evaluator.set_clock(std::sync::Arc::new(stdlib::time::FixedClock(seconds)));
+    }
+    evaluator.set_program_arguments(options.arguments.clone());
+    if let Some(format) = options.trace {
+        evaluator.set_hook(std::sync::Arc::new(trace::Tracer::new(format)));
+    }
+    let recorder = coverage::record(&mut evaluator);
+    let result = evaluator.evaluate(&ast);
+    if let Some(recorder) = recorder {
+        recorder.save(&ast);
+    }
+    exit_if_requested(&evaluator);
+    result.map_err(|e| vec![Diagnostic::from_runtime_error(&e)])?;
     
     Ok(())
 }
@@ -736,7 +889,8 @@ fn run_single_test(filename: &str) -> TestResult {
             if is_negative {
                 return TestResult::Passed; // any compile failure on negative is an expected pass
             }
-            return TestResult::Failed(format!("Compilation failed: {}", e));
+            let rendered: String = e.iter().map(|d| d.render(&read_source)).collect();
+            return TestResult::Failed(format!("Compilation failed: {}", rendered));
         }
         Ok(_) => {
             // compilation succeeded - files exist, will clean up after execution
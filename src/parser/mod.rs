@@ -2,27 +2,72 @@
 // parses pure english syntax into structured AST
 
 use crate::ast::*;
-use crate::lexer::{Token, TokenKind};
+use crate::lexer::{SyntaxError, Token, TokenKind};
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// inside a query condition or key, where "each" is the current element
+    query_depth: usize,
+    /// how many statements enclose the one being parsed
+    statement_depth: usize,
+    spans: Vec<StatementSpan>,
+    /// syntax errors found so far; parsing resumes after each one
+    errors: Vec<SyntaxError>,
+}
+
+/// the source lines a parsed statement covered, for tools that need the original layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementSpan {
+    /// the location of the statement node (not always its first token)
+    pub location: Location,
+    pub first_line: usize,
+    pub last_line: usize,
+    /// the statement's tokens, as indices into the token list (trailing line breaks excluded)
+    pub tokens: std::ops::Range<usize>,
+    /// the kind of AST node, e.g. "ConditionalStatement"
+    pub kind: &'static str,
+    /// 0 for top-level statements
+    pub depth: usize,
+    /// the line was skipped as prose, so the statement is an empty placeholder
+    pub skipped: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self { tokens, current: 0, query_depth: 0, statement_depth: 0, spans: Vec::new(), errors: Vec::new() }
+    }
+
+    /// spans of every statement parsed so far, in the order they finished parsing
+    pub fn statement_spans(&self) -> &[StatementSpan] {
+        &self.spans
     }
     
-    pub fn parse(&mut self) -> Result<Node, String> {
+    /// parse the whole program, failing with the first syntax error
+    pub fn parse(&mut self) -> Result<Node, SyntaxError> {
+        let (program, mut errors) = self.parse_recovering();
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    /// parse the whole program, skipping past each syntax error to the next statement.
+    /// returns the statements that did parse along with every error, in source order
+    pub fn parse_recovering(&mut self) -> (Node, Vec<SyntaxError>) {
         let mut statements = Vec::new();
         let mut iterations = 0;
         const MAX_ITERATIONS: usize = 100000; // prevent infinite loops
+        // after an error, the broken block's own `end ...` line is expected and not reported again
+        let mut recovering = false;
         
         while !self.is_at_end() {
             iterations += 1;
             if iterations > MAX_ITERATIONS {
-                return Err("Parser exceeded maximum iterations - possible infinite loop".to_string());
+                let error = self.error("Parser exceeded maximum iterations - possible infinite loop");
+                self.errors.push(error);
+                break;
             }
             
             if self.check(&TokenKind::Eof) {
@@ -36,10 +81,20 @@ impl Parser {
                 break;
             }
             
+            if recovering && self.at_block_end() {
+                self.skip_line();
+                recovering = false;
+                continue;
+            }
+
             let position_before = self.current;
+            let column = self.current_token().location.column;
             
             match self.parse_statement() {
                 Ok(stmt) => {
+                    if column == 0 {
+                        recovering = false;
+                    }
                     // only add non-empty statements
                     if !matches!(&stmt, Node::ExpressionStatement(es) if 
                         matches!(*es.expression, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. }))) {
@@ -57,21 +112,15 @@ impl Parser {
                     }
                 }
                 Err(e) => {
-                    // for now, continue on error to allow partial parsing
-                    // ensure we advance to prevent infinite loop
-                    if self.current == position_before && !self.is_at_end() {
-                        if self.current < self.tokens.len() {
-                            self.advance();
-                        }
-                    }
-                    // break on parse error to avoid hanging
-                    return Err(e);
+                    self.record_error(e);
+                    recovering = true;
+                    self.synchronize(position_before);
                 }
             }
         }
         
         // wrap in Program
-        Ok(Node::Program(Program {
+        let program = Node::Program(Program {
             location: if statements.is_empty() {
                 self.current_token().location.clone()
             } else {
@@ -97,17 +146,110 @@ impl Parser {
                 }
             },
             statements,
-        }))
+        });
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    /// keep a syntax error; parsing goes on from the next statement
+    fn record_error(&mut self, error: SyntaxError) {
+        self.errors.push(error);
+    }
+
+    /// a syntax error at the current token
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        let token = self.current_token();
+        SyntaxError::new(message, token.location.clone(), token.text.chars().count())
+    }
+
+    /// a syntax error about a statement or expression that started at `location`
+    fn error_at(location: &Location, message: impl Into<String>) -> SyntaxError {
+        let length = location.source.as_deref().map_or(1, |source| source.trim_end().chars().count());
+        SyntaxError::new(message, location.clone(), length)
+    }
+
+    /// after a syntax error, move on to where the next statement can start: the rest of the
+    /// line the error is on is skipped, unless the failed statement already ended at a line break
+    fn synchronize(&mut self, position_before: usize) {
+        if self.current == position_before && !self.is_at_end() {
+            self.advance();
+        } else if self.current > position_before && matches!(self.previous().kind, TokenKind::Newline) {
+            return;
+        }
+        self.skip_line();
+    }
+
+    /// skip to just past the next line break
+    fn skip_line(&mut self) {
+        while !self.is_at_end() && !self.check(&TokenKind::Newline) {
+            self.advance();
+        }
+        self.skip_newlines();
+    }
+
+    /// an `end ...` terminator starts the current line
+    fn at_block_end(&self) -> bool {
+        matches!(&self.current_token().kind, TokenKind::Identifier(s) if s.eq_ignore_ascii_case("end"))
     }
     
-    fn parse_statement(&mut self) -> Result<Node, String> {
+    fn parse_statement(&mut self) -> Result<Node, SyntaxError> {
+        self.skip_newlines();
+        let start = self.current;
+        let first_line = self.current_token().location.line;
+
+        self.statement_depth += 1;
+        let result = self.parse_statement_inner();
+        self.statement_depth -= 1;
+
+        // inside a block, a statement that got past its first token and then failed is a mistake
+        // in that line, not the end of the block: keep the error and carry on with the next line.
+        // a failure on the first token (`end`, `otherwise`, ...) is left for the block to handle
+        if self.statement_depth > 0 && self.current > start {
+            if let Err(e) = result {
+                self.record_error(e);
+                self.synchronize(start);
+                let location = self.tokens[start].location.clone();
+                return Ok(Node::ExpressionStatement(ExpressionStatement {
+                    location: location.clone(),
+                    expression: Box::new(Node::LiteralExpression(LiteralExpression { location, value: LiteralValue::Void })),
+                }));
+            }
+        }
+
+        if let Ok(node) = &result {
+            self.record_span(start, first_line, node);
+        }
+        result
+    }
+
+    /// note the tokens and lines a statement (or a method) covered, from token `start` to here
+    fn record_span(&mut self, start: usize, first_line: usize, node: &Node) {
+        // the last token the statement consumed, ignoring trailing line breaks
+        let mut end = self.current;
+        while end > start && matches!(self.tokens[end - 1].kind, TokenKind::Newline) {
+            end -= 1;
+        }
+        let last_line = if end > start { self.tokens[end - 1].location.line } else { first_line };
+        let skipped = matches!(node, Node::ExpressionStatement(es) if
+            matches!(*es.expression, Node::LiteralExpression(LiteralExpression { value: LiteralValue::Void, .. })));
+        self.spans.push(StatementSpan {
+            location: node.location(),
+            first_line,
+            last_line,
+            tokens: start..end,
+            kind: node.kind_name(),
+            depth: self.statement_depth,
+            skipped,
+        });
+    }
+
+    fn parse_statement_inner(&mut self) -> Result<Node, SyntaxError> {
         // skip newlines
         self.skip_newlines();
         
 
         
         if self.is_at_end() || self.check(&TokenKind::Eof) {
-            return Err("Unexpected end of input".to_string());
+            return Err(self.error("Unexpected end of input"));
         }
         
         // CRITICAL: check for "define" and "import" FIRST, before checking for "the variable"
@@ -154,6 +296,14 @@ impl Parser {
             }
         }
         
+        // exit statement: "exit" or "exit with code N"
+        if let TokenKind::Identifier(ref s) = &self.current_token().kind {
+            if s.to_lowercase() == "exit" && matches!(self.peek_kind(1), None | Some(TokenKind::With | TokenKind::Newline | TokenKind::Eof)) {
+                self.advance();
+                return self.parse_exit_statement();
+            }
+        }
+
         // ignore common pseudocode markers as no-ops
         if let TokenKind::Identifier(ref s) = &self.current_token().kind {
             let low = s.to_lowercase();
@@ -177,7 +327,7 @@ impl Parser {
         if let TokenKind::Identifier(ref s) = &self.current_token().kind {
             let low = s.to_lowercase();
             if low == "end" || low == "endif" || low == "endfor" || low == "endfunction" || low == "done" {
-                return Err(format!("Unexpected '{}'. Unmatched closing block or redundant end token.", s));
+                return Err(self.error(format!("Unexpected '{}'. Unmatched closing block or redundant end token.", s)));
             }
         }
 
@@ -342,7 +492,7 @@ impl Parser {
                             value: Box::new(value),
                         }))
                     } else {
-                        Err("Invalid assignment target".to_string())
+                        Err(self.error("Invalid assignment target"))
                     }
                 } else {
                     // Check if the expression was parsed as an equality check (e.g. "a[i] = b")
@@ -381,15 +531,15 @@ impl Parser {
         result
     }
     
-    fn parse_assignment(&mut self) -> Result<Node, String> {
-        let location = self.previous().location.clone();
+    fn parse_assignment(&mut self) -> Result<Node, SyntaxError> {
+        // the short form "x is 3" starts here, with nothing consumed yet
+        let location = self.current_token().location.clone();
         
         // CRITICAL: Check for "define", "import", or "return" BEFORE doing anything else
         // This should never happen, but if it does, we need to fail immediately
         // to prevent trying to parse these as identifiers or assignments
         if matches!(self.current_token().kind, TokenKind::Define | TokenKind::Import | TokenKind::Return) {
-            return Err(format!("Expected 'is' or 'equals' for assignment, got: {:?} at {:?}", 
-                self.current_token().kind, self.current_token().location));
+            return Err(self.error(format!("Expected 'is' or 'equals' for assignment, got {}", describe(self.current_token()))));
         }
         
         // consume "variable" or "constant" if present
@@ -403,8 +553,7 @@ impl Parser {
         }        
         // Additional check: if current token is Define, Import, or Return (regardless of column), this is wrong
         if matches!(self.current_token().kind, TokenKind::Define | TokenKind::Import | TokenKind::Return) {
-            return Err(format!("Expected 'is' or 'equals' for assignment, got: {:?} at {:?}", 
-                self.current_token().kind, self.current_token().location));
+            return Err(self.error(format!("Expected 'is' or 'equals' for assignment, got {}", describe(self.current_token()))));
         }
         
         let identifier = self.parse_identifier()?;
@@ -414,8 +563,7 @@ impl Parser {
         // This prevents trying to parse "the variable X return" or "the variable X define" as assignments
         self.skip_newlines();
         if matches!(self.current_token().kind, TokenKind::Return | TokenKind::Define | TokenKind::Import) {
-            return Err(format!("Expected 'is' or 'equals' for assignment, got: {:?} at {:?}", 
-                self.current_token().kind, self.current_token().location));
+            return Err(self.error(format!("Expected 'is' or 'equals' for assignment, got {}", describe(self.current_token()))));
         }
         
         // handle optional "of type <Type>"
@@ -448,11 +596,10 @@ impl Parser {
             // if we don't see "is" or "equals", this is an error
             // but first check if we're at end of input
             if self.is_at_end() || self.check(&TokenKind::Eof) {
-                return Err("Expected 'is' or 'equals' for assignment, but reached end of input".to_string());
+                return Err(self.error("Expected 'is' or 'equals' for assignment, but reached end of input"));
             }
             // return error with current token info for debugging
-            return Err(format!("Expected 'is' or 'equals' for assignment, got: {:?} at {:?}", 
-                self.current_token().kind, self.current_token().location));
+            return Err(self.error(format!("Expected 'is' or 'equals' for assignment, got {}", describe(self.current_token()))));
         }
         
         if self.check(&TokenKind::Is) {
@@ -577,7 +724,7 @@ impl Parser {
         } else if self.match_token(&[TokenKind::Equals]) {
             // backward compatibility: "equals" also works
         } else {
-            return Err("Expected 'is' or 'equals' for assignment".to_string());
+            return Err(self.error("Expected 'is' or 'equals' for assignment"));
         }
         
         let expression = self.parse_expression()?;
@@ -600,7 +747,7 @@ impl Parser {
         }
     }
     
-    fn parse_set_statement(&mut self) -> Result<Node, String> {
+    fn parse_set_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.current_token().location.clone();
         self.advance(); // consume "set"
         
@@ -635,7 +782,7 @@ impl Parser {
              
              self.skip_newlines();
              if !self.match_token(&[TokenKind::To, TokenKind::Is, TokenKind::Equals]) {
-                 return Err(format!("Expected 'to' or 'is' after object, got: {:?}", self.current_token()));
+                 return Err(self.error(format!("Expected 'to' or 'is' after object, got {}", describe(self.current_token()))));
              }
              
              let value = self.parse_expression()?;
@@ -657,13 +804,13 @@ impl Parser {
                  is_mutable: true,
              }));
         } else {
-             return Err(format!("Expected 'in' (for property) or 'to' (for variable) after '{}', got: {:?}", property, self.current_token()));
+             return Err(self.error(format!("Expected 'in' (for property) or 'to' (for variable) after '{}', got: {}", property, describe(self.current_token()))));
         }
         
 
     }
     
-    fn parse_function_declaration(&mut self, location: Location, is_async: bool) -> Result<Node, String> {
+    fn parse_function_declaration(&mut self, location: Location, is_async: bool) -> Result<Node, SyntaxError> {
         let name = self.parse_identifier()?;
         
         let mut parameters = Vec::new();
@@ -877,7 +1024,7 @@ impl Parser {
         }))
     }
 
-    fn parse_declaration_or_function(&mut self) -> Result<Node, String> {
+    fn parse_declaration_or_function(&mut self) -> Result<Node, SyntaxError> {
         // NOTE: "define" has already been consumed by the caller
         let location = self.previous().location.clone();
         
@@ -915,13 +1062,13 @@ impl Parser {
             return self.parse_function_declaration(location, is_async);
         } else {
             if is_async {
-                return Err("Expected 'function' after 'background'".to_string());
+                return Err(self.error("Expected 'function' after 'background'"));
             }
             self.parse_variable_declaration()
         }
     }
     
-    fn parse_type_declaration(&mut self, location: Location) -> Result<Node, String> {
+    fn parse_type_declaration(&mut self, location: Location) -> Result<Node, SyntaxError> {
         // define type <Name> as either <Variant1> or <Variant2> ...
         let name = self.parse_identifier()?;
         
@@ -945,7 +1092,7 @@ impl Parser {
         }))
     }
     
-    fn parse_variant(&mut self) -> Result<Variant, String> {
+    fn parse_variant(&mut self) -> Result<Variant, SyntaxError> {
         let name = self.parse_identifier()?;
         let mut fields = Vec::new();
         
@@ -970,7 +1117,7 @@ impl Parser {
                         self.advance();
                         t
                     } else {
-                        return Err("Expected type identifier".to_string());
+                        return Err(self.error("Expected type identifier"));
                     }
                 } else {
                     // Try to parse type
@@ -982,7 +1129,7 @@ impl Parser {
                     } else {
                         // Fallback to "Any" or error?
                         // For now, require explicit type
-                        return Err(format!("Expected type for field '{}', got {:?}", field_name, self.current_token().kind));
+                        return Err(self.error(format!("Expected type for field '{}', got {}", field_name, describe(self.current_token()))));
                     }
                 };
                 
@@ -997,7 +1144,7 @@ impl Parser {
         Ok(Variant { name, fields })
     }
 
-    fn parse_struct_declaration(&mut self, location: Location) -> Result<Node, String> {
+    fn parse_struct_declaration(&mut self, location: Location) -> Result<Node, SyntaxError> {
         let name = self.parse_identifier()?;
         
         let mut properties = Vec::new();
@@ -1035,7 +1182,7 @@ impl Parser {
         }))
     }
     
-    fn parse_variable_declaration(&mut self) -> Result<Node, String> {
+    fn parse_variable_declaration(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         let is_mutable = if self.match_token(&[TokenKind::Variable]) {
@@ -1043,11 +1190,31 @@ impl Parser {
         } else if self.match_token(&[TokenKind::Constant]) {
             false
         } else {
-            return Err("Expected 'variable' or 'constant'".to_string());
+            // `define x is 5` leaves out what kind of name x is
+            if let TokenKind::Identifier(name) = &self.current_token().kind {
+                if matches!(self.peek_kind(1), Some(TokenKind::Is | TokenKind::As)) {
+                    return Err(self.error(format!("Expected 'variable' or 'constant'; did you mean `define variable {} ...`?", name)));
+                }
+            }
+            return Err(self.error("Expected 'variable' or 'constant'"));
         };
         let identifier = self.parse_identifier()?;
-        
-        let type_annotation = if self.match_token(&[TokenKind::As]) {
+
+        // `define variable x as 5` gives the value; `as Number` followed by `is`, `equals` or the
+        // end of the line names a type
+        let annotated = self.match_token(&[TokenKind::As]);
+        if annotated {
+            let start = self.current;
+            let is_type = self.parse_type().is_ok()
+                && matches!(self.current_token().kind, TokenKind::Is | TokenKind::Equals | TokenKind::Newline | TokenKind::Eof);
+            self.current = start;
+            if !is_type {
+                let value = Box::new(self.parse_expression()?);
+                return Ok(Node::DeclareStatement(DeclareStatement { location, name: identifier, type_annotation: None, value, is_mutable }));
+            }
+        }
+
+        let type_annotation = if annotated {
             Some(self.parse_type()?)
         } else {
             if matches!(self.current_token().kind, TokenKind::Of) {
@@ -1115,7 +1282,7 @@ impl Parser {
                      value: LiteralValue::Void,
                  }))
             } else {
-                 return Err(format!("Variable '{}' must be initialized", identifier));
+                 return Err(self.error(format!("Variable '{}' must be initialized", identifier)));
             }
         };
         
@@ -1128,7 +1295,7 @@ impl Parser {
         }))
     }
     
-    fn _parse_function_declaration(&mut self) -> Result<Node, String> {
+    fn _parse_function_declaration(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         self.consume(&TokenKind::Function, "Expected 'function'")?;
@@ -1229,7 +1396,7 @@ impl Parser {
         }))
     }
     
-    fn parse_import(&mut self) -> Result<Node, String> {
+    fn parse_import(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         let start_token = self.previous().kind.clone();
         
@@ -1257,7 +1424,7 @@ impl Parser {
                     module_name = path.clone();
                     self.advance();
                 } else {
-                    return Err("Expected file path string after 'import file'".to_string());
+                    return Err(self.error("Expected file path string after 'import file'"));
                 }
             } else if let TokenKind::Text(path) = &self.current_token().kind {
                 // Allow import "package/module" directly
@@ -1285,7 +1452,7 @@ impl Parser {
                     module_name = path.clone();
                     self.advance();
                 } else {
-                    return Err("Expected file path string after 'from file'".to_string());
+                    return Err(self.error("Expected file path string after 'from file'"));
                 }
             } else {
                 // from <module>
@@ -1322,8 +1489,8 @@ impl Parser {
         }))
     }
     
-    fn parse_conditional(&mut self) -> Result<Node, String> {
-        let location = self.previous().location.clone();
+    fn parse_conditional(&mut self) -> Result<Node, SyntaxError> {
+        let location = self.current_token().location.clone();
         self.advance(); // consume "if"
         
         let condition = Box::new(self.parse_expression()?);
@@ -1455,11 +1622,14 @@ impl Parser {
         Ok(Node::ConditionalStatement(ConditionalStatement { location, condition, then_branch: Box::new(then_node), else_branch }))
     }
     
-    fn parse_for_loop(&mut self) -> Result<Node, String> {
+    fn parse_for_loop(&mut self) -> Result<Node, SyntaxError> {
     let location = self.previous().location.clone();
     // already consumed "for each"
     
     let iterator = self.parse_identifier()?;
+    if self.check(&TokenKind::Of) {
+        return Err(self.error(format!("Expected 'in'; write `for each {} in <list>`", iterator)));
+    }
     // tolerate newlines before 'in'
     self.consume(&TokenKind::In, "Expected 'in'")?;
     let collection = Box::new(self.parse_expression()?);
@@ -1534,7 +1704,7 @@ impl Parser {
     }))
     }
     
-    fn parse_while_loop(&mut self) -> Result<Node, String> {
+    fn parse_while_loop(&mut self) -> Result<Node, SyntaxError> {
         let location = self.current_token().location.clone();
         let _loop_indent = location.column;
         self.advance(); // consume "while"
@@ -1657,7 +1827,7 @@ impl Parser {
         }))
     }
     
-    fn parse_return(&mut self) -> Result<Node, String> {
+    fn parse_return(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         let expression = if !self.check(&TokenKind::Eof) && !self.check(&TokenKind::Newline) {
@@ -1672,8 +1842,9 @@ impl Parser {
         }))
     }
     
-    fn parse_call_statement(&mut self) -> Result<Node, String> {
-        let location = self.previous().location.clone();
+    fn parse_call_statement(&mut self) -> Result<Node, SyntaxError> {
+        // "call" has not been consumed yet
+        let location = self.current_token().location.clone();
         let call_expr = self.parse_call_expression()?;
         Ok(Node::ExpressionStatement(ExpressionStatement {
             location,
@@ -1684,7 +1855,7 @@ impl Parser {
 
 
     
-    fn _parse_expression_statement(&mut self) -> Result<Node, String> {
+    fn _parse_expression_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.current_token().location.clone();
         
         // if we can't parse anything, just return nothing for now
@@ -1716,11 +1887,11 @@ impl Parser {
         }
     }
     
-    fn parse_expression(&mut self) -> Result<Node, String> {
+    fn parse_expression(&mut self) -> Result<Node, SyntaxError> {
         self.parse_or()
     }
     
-    fn parse_or(&mut self) -> Result<Node, String> {
+    fn parse_or(&mut self) -> Result<Node, SyntaxError> {
         let mut expr = self.parse_and()?;
         
         while self.match_token(&[TokenKind::Or]) {
@@ -1737,7 +1908,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn parse_and(&mut self) -> Result<Node, String> {
+    fn parse_and(&mut self) -> Result<Node, SyntaxError> {
         let mut expr = self.parse_comparison()?;
         
         while self.match_token(&[TokenKind::And]) {
@@ -1754,7 +1925,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn parse_comparison(&mut self) -> Result<Node, String> {
+    fn parse_comparison(&mut self) -> Result<Node, SyntaxError> {
         let mut expr = self.parse_term()?;
         
         // handle "is less than", "is greater than", "equals", etc.
@@ -1849,6 +2020,22 @@ impl Parser {
                          right: Some(right),
                      });
                      continue;
+                } else if let Some(operator) = match peek_greater {
+                    Some(TokenKind::Identifier(w)) if w.to_lowercase() == "before" => Some(Operator::LessThan),
+                    Some(TokenKind::Identifier(w)) if w.to_lowercase() == "after" => Some(Operator::GreaterThan),
+                    _ => None,
+                } {
+                    // "is before" / "is after" compare dates and durations
+                    self.advance(); // consume "is"
+                    self.advance(); // consume "before" / "after"
+                    let right = Box::new(self.parse_term()?);
+                    expr = Node::OperationExpression(OperationExpression {
+                        location: self.previous().location.clone(),
+                        operator,
+                        left: Box::new(expr),
+                        right: Some(right),
+                    });
+                    continue;
                 } else {
                     // "is" alone -> treat as equality check
                     self.advance(); // consume "is"
@@ -1957,6 +2144,39 @@ impl Parser {
                 }
             }
 
+            // "<text> matches pattern <pattern>"
+            if matches!(&self.current_token().kind, TokenKind::Identifier(w) if w.to_lowercase() == "matches")
+                && matches!(self.peek_kind(1), Some(TokenKind::Identifier(w)) if w.to_lowercase() == "pattern")
+            {
+                let location = self.current_token().location.clone();
+                self.advance(); // consume "matches"
+                self.advance(); // consume "pattern"
+                let pattern = self.parse_term()?;
+                expr = Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__pattern_matches".to_string(),
+                    arguments: vec![expr, pattern],
+                });
+                continue;
+            }
+
+            // "<dictionary> contains key <key>"
+            if matches!(&self.current_token().kind, TokenKind::Identifier(w) if w.to_lowercase() == "contains")
+                && matches!(self.peek_kind(1), Some(TokenKind::Identifier(w)) if w.to_lowercase() == "key")
+            {
+                let location = self.current_token().location.clone();
+                self.advance(); // consume "contains"
+                self.advance(); // consume "key"
+                let key = self.parse_term()?;
+                expr = Node::MethodCall(MethodCall {
+                    location,
+                    object: Box::new(expr),
+                    method_name: "contains_key".to_string(),
+                    arguments: vec![key],
+                });
+                continue;
+            }
+
             break;
         }
 
@@ -1964,7 +2184,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn parse_term(&mut self) -> Result<Node, String> {
+    fn parse_term(&mut self) -> Result<Node, SyntaxError> {
         let mut expr = self.parse_factor()?;
         // println!("DEBUG: parse_term after factor: {:?}, next: {:?}", expr, self.current_token());
         
@@ -1987,7 +2207,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn parse_factor(&mut self) -> Result<Node, String> {
+    fn parse_factor(&mut self) -> Result<Node, SyntaxError> {
         let mut expr = self.parse_unary()?;
         
         // handle "times", "divided by", "modulo"
@@ -2032,7 +2252,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn parse_unary(&mut self) -> Result<Node, String> {
+    fn parse_unary(&mut self) -> Result<Node, SyntaxError> {
         if self.match_token(&[TokenKind::Not, TokenKind::Minus]) {
             let op = match self.previous().kind {
                 TokenKind::Not => Operator::Not,
@@ -2051,6 +2271,45 @@ impl Parser {
             }));
         }
         
+        // "maybe <lookup>": a list item, dictionary entry or property that may be missing
+        if self.check(&TokenKind::Maybe) {
+            let location = self.current_token().location.clone();
+            self.advance(); // consume "maybe"
+            let (function_name, arguments) = match self.parse_unary()? {
+                Node::IndexExpression(index) => ("__maybe_index", vec![*index.object, *index.index]),
+                Node::AccessExpression(access) => {
+                    let property = Node::LiteralExpression(LiteralExpression {
+                        location: access.location.clone(),
+                        value: LiteralValue::String(access.property),
+                    });
+                    ("__maybe_property", vec![*access.object, property])
+                }
+                _ => return Err(self.error("Expected a lookup after 'maybe', such as 'maybe item 2 of xs', 'maybe d[\"key\"]' or 'maybe get name from person'")),
+            };
+            return Ok(Node::CallExpression(CallExpression {
+                location,
+                function_name: function_name.to_string(),
+                arguments,
+            }));
+        }
+        
+        // "frozen copy of <value>": a deeply immutable copy
+        if matches!(&self.current_token().kind, TokenKind::Identifier(w) if w.to_lowercase() == "frozen")
+            && matches!(self.peek_kind(1), Some(TokenKind::Identifier(w)) if w.to_lowercase() == "copy")
+            && matches!(self.peek_kind(2), Some(TokenKind::Of))
+        {
+            let location = self.current_token().location.clone();
+            for _ in 0..3 {
+                self.advance(); // consume "frozen copy of"
+            }
+            let value = self.parse_unary()?;
+            return Ok(Node::CallExpression(CallExpression {
+                location,
+                function_name: "__freeze".to_string(),
+                arguments: vec![value],
+            }));
+        }
+        
         if self.match_token(&[TokenKind::Start]) {
             let location = self.previous().location.clone();
             let expr = self.parse_unary()?;
@@ -2069,14 +2328,14 @@ impl Parser {
                     expression: Box::new(expr),
                 }));
             } else {
-                return Err("Expected 'for' after 'wait'".to_string());
+                return Err(self.error("Expected 'for' after 'wait'"));
             }
         }
         
         self.parse_call()
     }
     
-    fn parse_call(&mut self) -> Result<Node, String> {
+    fn parse_call(&mut self) -> Result<Node, SyntaxError> {
         // check for standalone function call: "call function name with args"
         if self.check(&TokenKind::Call) {
             return self.parse_call_expression();
@@ -2092,11 +2351,9 @@ impl Parser {
                 
                 // check for method call - STRICTLY FORBIDDEN
                 if self.match_token(&[TokenKind::LeftParen]) {
-                    return Err(format!(
-                        "{}:{}:{}: C-style function calls like 'obj.method()' are not allowed in Layman. Please use 'call function method on obj' or 'call method using obj'. Layman is designed to be pure English.",
-                        expr.location().file,
-                        expr.location().line,
-                        expr.location().column
+                    return Err(Self::error_at(
+                        &expr.location(),
+                        "C-style function calls like 'obj.method()' are not allowed in Layman. Please use 'call function method on obj' or 'call method using obj'. Layman is designed to be pure English.",
                     ));
                 } else {
                     // property access
@@ -2120,16 +2377,55 @@ impl Parser {
                 break;
             }
         }
+
+        // durations: "3 days", optionally "3 days after <date>" / "2 hours before <date>"
+        // (the unit must be on the same line, so no newline skipping here)
+        if let TokenKind::Identifier(ref word) = self.current_token().kind {
+            let is_text = matches!(&expr, Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(_), .. }));
+            if !is_text && crate::stdlib::time::unit_seconds(word).is_some() {
+                let unit_location = self.current_token().location.clone();
+                let unit = word.to_lowercase();
+                self.advance(); // consume the unit
+                let location = expr.location();
+                let duration = Node::CallExpression(CallExpression {
+                    location: location.clone(),
+                    function_name: "__time_duration".to_string(),
+                    arguments: vec![
+                        expr,
+                        Node::LiteralExpression(LiteralExpression {
+                            location: unit_location,
+                            value: LiteralValue::String(unit),
+                        }),
+                    ],
+                });
+                let direction = match &self.current_token().kind {
+                    TokenKind::Identifier(w) if w.to_lowercase() == "after" => Some(Operator::Plus),
+                    TokenKind::Identifier(w) if w.to_lowercase() == "before" => Some(Operator::Minus),
+                    _ => None,
+                };
+                if let Some(operator) = direction {
+                    self.advance(); // consume "after" / "before"
+                    let date = self.parse_unary()?;
+                    return Ok(Node::OperationExpression(OperationExpression {
+                        location,
+                        operator,
+                        left: Box::new(date),
+                        right: Some(Box::new(duration)),
+                    }));
+                }
+                return Ok(duration);
+            }
+        }
         
         Ok(expr)
     }
     
-    fn parse_throw_statement(&mut self) -> Result<Node, String> {
+    fn parse_throw_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         // check if we have an expression to throw
         self.skip_newlines();
         if self.is_at_end() || self.check(&TokenKind::Eof) || self.check(&TokenKind::Newline) {
-            return Err(format!("{}:{}:{}: throw statement requires an expression", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "throw statement requires an expression"));
         }
         let expression = self.parse_expression()?;
         Ok(Node::ThrowStatement(ThrowStatement {
@@ -2138,7 +2434,7 @@ impl Parser {
         }))
     }
     
-    fn parse_try_catch_statement(&mut self) -> Result<Node, String> {
+    fn parse_try_catch_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // parse try block statements until "catch" or "end try"
@@ -2258,18 +2554,18 @@ impl Parser {
         }))
     }
     
-    fn parse_describe_statement(&mut self) -> Result<Node, String> {
+    fn parse_describe_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // parse suite name (string or identifier)
         self.skip_newlines();
         if self.is_at_end() || self.check(&TokenKind::Eof) {
-            return Err(format!("{}:{}:{}: describe statement requires a suite name", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "describe statement requires a suite name"));
         }
         
         // check if next token is a keyword which means missing name
         if matches!(self.current_token().kind, TokenKind::Test | TokenKind::Describe | TokenKind::Expect) {
-            return Err(format!("{}:{}:{}: describe statement requires a suite name", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "describe statement requires a suite name"));
         }
         
         let suite_name = if matches!(self.current_token().kind, TokenKind::Text(_)) {
@@ -2302,7 +2598,7 @@ impl Parser {
                     
                     // Check for mismatch (e.g. "end test")
                     if let Some(TokenKind::Test) = self.peek_kind_skip_newlines(1) {
-                        return Err("Mismatched closing block. Found 'end test' inside 'describe' block. Expected 'end describe'.".to_string());
+                        return Err(self.error("Mismatched closing block. Found 'end test' inside 'describe' block. Expected 'end describe'."));
                     }
                     
                     // Allow generic "end" for now, but strictly it should be paired
@@ -2340,18 +2636,18 @@ impl Parser {
         }))
     }
     
-    fn parse_test_statement(&mut self) -> Result<Node, String> {
+    fn parse_test_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // parse test name (string or identifier)
         self.skip_newlines();
         if self.is_at_end() || self.check(&TokenKind::Eof) {
-            return Err(format!("{}:{}:{}: test statement requires a test name", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "test statement requires a test name"));
         }
         
         // check if next token is a keyword (test/describe) which means missing name
         if matches!(self.current_token().kind, TokenKind::Test | TokenKind::Describe | TokenKind::Expect) {
-            return Err(format!("{}:{}:{}: test statement requires a test name", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "test statement requires a test name"));
         }
         
         let test_name = if matches!(self.current_token().kind, TokenKind::Text(_)) {
@@ -2384,7 +2680,7 @@ impl Parser {
                     
                     // Check for mismatch (e.g. "end describe")
                     if let Some(TokenKind::Describe) = self.peek_kind_skip_newlines(1) {
-                        return Err("Mismatched closing block. Found 'end describe' inside 'test' block. Expected 'end test'.".to_string());
+                        return Err(self.error("Mismatched closing block. Found 'end describe' inside 'test' block. Expected 'end test'."));
                     }
                     
                     self.advance(); // consume "end"
@@ -2414,13 +2710,13 @@ impl Parser {
 
     }
     
-    fn parse_expect_statement(&mut self) -> Result<Node, String> {
+    fn parse_expect_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // parse: "expect actual is expected"
         self.skip_newlines();
         if self.is_at_end() || self.check(&TokenKind::Eof) {
-            return Err(format!("{}:{}:{}: expect statement requires an actual value", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "expect statement requires an actual value"));
         }
         let actual = self.parse_expression()?;
         
@@ -2439,15 +2735,15 @@ impl Parser {
         
         // consume "is"
         self.skip_newlines();
-        // println!("DEBUG: parse_expect_statement check is: {:?}", self.current_token());
+        // println!("DEBUG: parse_expect_statement check is: {}", describe(self.current_token()));
         if !self.check(&TokenKind::Is) {
-            return Err(format!("{}:{}:{}: expect statement requires 'is' keyword, got {:?}", location.file, location.line, location.column, self.current_token()));
+            return Err(Self::error_at(&location, format!("expect statement requires 'is' keyword, got {}", describe(self.current_token()))));
         }
         self.consume(&TokenKind::Is, "Expected 'is' in expect statement")?;
         
         self.skip_newlines();
         if self.is_at_end() || self.check(&TokenKind::Eof) {
-            return Err(format!("{}:{}:{}: expect statement requires an expected value", location.file, location.line, location.column));
+            return Err(Self::error_at(&location, "expect statement requires an expected value"));
         }
         let expected = self.parse_expression()?;
         
@@ -2459,11 +2755,11 @@ impl Parser {
         }))
     }
     
-    fn parse_print_statement(&mut self) -> Result<Node, String> {
+    fn parse_print_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // skip optional "the variable" - handle explicitly
-        if self.check(&TokenKind::The) {
+        if self.check(&TokenKind::The) && !self.is_query_start() {
             self.advance(); // consume "the"
             if self.check(&TokenKind::Variable) {
                 self.advance(); // consume "variable"
@@ -2484,14 +2780,49 @@ impl Parser {
         }))
     }
     
-    fn parse_call_expression(&mut self) -> Result<Node, String> {
+    fn parse_exit_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
+
+        // "exit" alone ends the program successfully
+        let code = if self.check(&TokenKind::With) {
+            self.advance(); // consume "with"
+            if matches!(&self.current_token().kind, TokenKind::Identifier(s) if s.to_lowercase() == "code") {
+                self.advance(); // consume "code"
+            }
+            self.parse_expression()?
+        } else {
+            Node::LiteralExpression(LiteralExpression {
+                location: location.clone(),
+                value: LiteralValue::Number(0.0),
+            })
+        };
+
+        Ok(Node::ExpressionStatement(ExpressionStatement {
+            location: location.clone(),
+            expression: Box::new(Node::CallExpression(CallExpression {
+                location,
+                function_name: "exit".to_string(),
+                arguments: vec![code],
+            })),
+        }))
+    }
+
+    fn parse_call_expression(&mut self) -> Result<Node, SyntaxError> {
+        let location = self.current_token().location.clone();
         self.advance(); // consume "call"
         
         // skip optional "function"
         self.match_token(&[TokenKind::Function]);
         
-        let mut function_name = self.parse_identifier()?;
+        // "keys" and "values" are keywords but also dictionary method names
+        let mut function_name = match self.current_token().kind {
+            TokenKind::Keys | TokenKind::Values => {
+                let name = self.current_token().text.to_lowercase();
+                self.advance();
+                name
+            }
+            _ => self.parse_identifier()?,
+        };
         
         // check for dot notation (e.g. module.function)
         while self.match_token(&[TokenKind::Period]) {
@@ -2613,7 +2944,7 @@ impl Parser {
         }))
     }
     
-    fn _parse_call_expression_with_base(&mut self, base: Node) -> Result<Node, String> {
+    fn _parse_call_expression_with_base(&mut self, base: Node) -> Result<Node, SyntaxError> {
         // this is for method calls - simplified for now
         // self.parse_call_expression() // BUG: this consumes "call" token which might not be there!
         Ok(base)
@@ -2621,9 +2952,30 @@ impl Parser {
     
 
 
-    fn parse_primary(&mut self) -> Result<Node, String> {
+    fn parse_primary(&mut self) -> Result<Node, SyntaxError> {
         let location = self.current_token().location.clone();
         
+        // inside a query, "each" is the element and "each total" its property
+        if self.query_depth > 0 && self.check(&TokenKind::Each) {
+            self.advance(); // consume "each"
+            let element = Node::VariableExpression(VariableExpression {
+                location: location.clone(),
+                identifier: "each".to_string(),
+            });
+            if let TokenKind::Identifier(property) = &self.current_token().kind {
+                if property.to_lowercase() != "matches" {
+                    let property = property.clone();
+                    self.advance();
+                    return Ok(Node::AccessExpression(AccessExpression {
+                        location,
+                        object: Box::new(element),
+                        property,
+                    }));
+                }
+            }
+            return Ok(element);
+        }
+
         if self.match_token(&[TokenKind::True]) {
             return Ok(Node::LiteralExpression(LiteralExpression {
                 location,
@@ -2676,7 +3028,7 @@ impl Parser {
                     is_async: false,
                 }));
             } else {
-                return Err("Expected 'of' after 'function' in lambda expression".to_string());
+                return Err(self.error("Expected 'of' after 'function' in lambda expression"));
             }
         }
         
@@ -2700,6 +3052,55 @@ impl Parser {
             }));
         }
         
+        // program context: "the arguments of the program", "the environment variable <name>",
+        // "the lines of input"
+        if self.check(&TokenKind::The) {
+            let word_at = |parser: &Self, offset: usize, word: &str| {
+                matches!(parser.peek_kind(offset), Some(TokenKind::Identifier(s)) if s.to_lowercase() == word)
+            };
+            if word_at(self, 1, "arguments")
+                && matches!(self.peek_kind(2), Some(TokenKind::Of))
+                && matches!(self.peek_kind(3), Some(TokenKind::The))
+                && word_at(self, 4, "program")
+            {
+                for _ in 0..5 {
+                    self.advance(); // consume "the arguments of the program"
+                }
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__program_arguments".to_string(),
+                    arguments: Vec::new(),
+                }));
+            }
+            if let Some(query) = self.parse_query(&location)? {
+                return Ok(query);
+            }
+            if word_at(self, 1, "lines")
+                && matches!(self.peek_kind(2), Some(TokenKind::Of))
+                && word_at(self, 3, "input")
+            {
+                for _ in 0..4 {
+                    self.advance(); // consume "the lines of input"
+                }
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__io_input_lines".to_string(),
+                    arguments: Vec::new(),
+                }));
+            }
+            if word_at(self, 1, "environment") && matches!(self.peek_kind(2), Some(TokenKind::Variable)) {
+                self.advance(); // consume "the"
+                self.advance(); // consume "environment"
+                self.advance(); // consume "variable"
+                let name = self.parse_term()?;
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__env_get".to_string(),
+                    arguments: vec![name],
+                }));
+            }
+        }
+
         // list literal parsing
         if self.check(&TokenKind::The) || self.check(&TokenKind::A) {
             let is_list = if self.check(&TokenKind::The) {
@@ -2752,6 +3153,20 @@ impl Parser {
                 
                 self.consume(&TokenKind::Dictionary, "Expected 'dictionary'")?;
                 
+                // "a dictionary from pairs <list of [key, value] lists>"
+                if self.check(&TokenKind::From)
+                    && matches!(self.peek_kind(1), Some(TokenKind::Identifier(w)) if w.to_lowercase() == "pairs")
+                {
+                    self.advance(); // consume "from"
+                    self.advance(); // consume "pairs"
+                    let pairs = self.parse_term()?;
+                    return Ok(Node::CallExpression(CallExpression {
+                        location: location.clone(),
+                        function_name: "__dict_from_pairs".to_string(),
+                        arguments: vec![pairs],
+                    }));
+                }
+                
                 let mut args = Vec::new();
                 
                 // check for "containing" or "with"
@@ -2774,14 +3189,15 @@ impl Parser {
                              if self.match_token(&[TokenKind::Is]) || self.match_token(&[TokenKind::Equals]) {
                                  // ok
                              } else {
-                                 return Err("Expected 'is' after dictionary key".to_string());
+                                 return Err(self.error("Expected 'is' after dictionary key"));
                              }
                              
                              let value_expr = self.parse_term()?;
                              args.push(value_expr);
                              
                              // check for comma
-                             if self.check(&TokenKind::Comma) {
+                             let had_comma = self.check(&TokenKind::Comma);
+                             if had_comma {
                                  self.advance();
                              }
                              
@@ -2790,6 +3206,12 @@ impl Parser {
                                  break;
                              }
                              
+                             // operator lookahead skips newlines, so without a comma an entry
+                             // followed by the next line ends the literal
+                             if !had_comma && self.current_token().location.line != self.previous().location.line {
+                                 break;
+                             }
+                             
                              // heuristic: if next token is not start of an expression, break
                              // keys are usually strings or identifiers
                              if !matches!(self.current_token().kind, TokenKind::Text(_) | TokenKind::Identifier(_)) {
@@ -2845,7 +3267,8 @@ impl Parser {
                                 elements.push(elem);
                                 
                                 // check for comma
-                                if self.check(&TokenKind::Comma) {
+                                let had_comma = self.check(&TokenKind::Comma);
+                                if had_comma {
                                     self.advance();
                                 }
                                 
@@ -2854,6 +3277,11 @@ impl Parser {
                                     break;
                                 }
                                 
+                                // as with dictionaries, an item followed by the next line ends the list
+                                if !had_comma && self.current_token().location.line != self.previous().location.line {
+                                    break;
+                                }
+                                
                                 // heuristic: if next token is not start of an expression, break
                                 // This is tricky in natural language. 
                                 // For now, assume list ends at newline or when we can't parse another term
@@ -2923,7 +3351,7 @@ impl Parser {
                     }
                 }
                 
-                return Err(format!("Expected 'of' or 'in' after 'item <index>', got: {:?}", self.current_token()));
+                return Err(self.error(format!("Expected 'of' or 'in' after 'item <index>', got {}", describe(self.current_token()))));
             }
         }
     }
@@ -3045,10 +3473,172 @@ impl Parser {
                         property: property_name,
                     }));
                 }
-                return Err(format!("Expected 'from' after 'get {}', got: {:?}", property_name, self.current_token()));
+                return Err(self.error(format!("Expected 'from' after 'get {}', got: {}", property_name, describe(self.current_token()))));
             }
         }
-        
+
+        // standard input: "ask <prompt>" and "read all input"
+        if let TokenKind::Identifier(ref s) = &self.current_token().kind {
+            let word = s.to_lowercase();
+            if word == "read"
+                && matches!(self.peek_kind(1), Some(TokenKind::All))
+                && matches!(self.peek_kind(2), Some(TokenKind::Identifier(ref s)) if s.to_lowercase() == "input")
+            {
+                for _ in 0..3 {
+                    self.advance(); // consume "read all input"
+                }
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__io_read_all_input".to_string(),
+                    arguments: Vec::new(),
+                }));
+            }
+            // a lone "ask" is still a variable
+            if word == "ask"
+                && !matches!(self.peek_kind(1), None | Some(TokenKind::Newline | TokenKind::Eof | TokenKind::Is | TokenKind::Equals | TokenKind::Comma | TokenKind::RightParen | TokenKind::Then | TokenKind::Do))
+            {
+                self.advance(); // consume "ask"
+                let prompt = self.parse_term()?;
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__io_ask".to_string(),
+                    arguments: vec![prompt],
+                }));
+            }
+        }
+
+        // text patterns: "find all matches of <pattern> in <text>", "find groups of ...",
+        // "find all groups of ...", "find named groups of ...", "replace pattern <pattern> in <text> with <replacement>"
+        if let TokenKind::Identifier(ref s) = &self.current_token().kind {
+            let word = s.to_lowercase();
+            let word_at = |parser: &Self, offset: usize, word: &str| {
+                matches!(parser.peek_kind(offset), Some(TokenKind::Identifier(s)) if s.to_lowercase() == word)
+            };
+            let find = if word != "find" {
+                None
+            } else if matches!(self.peek_kind(1), Some(TokenKind::All)) && word_at(self, 2, "matches") {
+                Some("__pattern_find_all")
+            } else if matches!(self.peek_kind(1), Some(TokenKind::All)) && word_at(self, 2, "groups") {
+                Some("__pattern_find_all_groups")
+            } else if word_at(self, 1, "groups") {
+                Some("__pattern_find_groups")
+            } else if word_at(self, 1, "named") && word_at(self, 2, "groups") {
+                Some("__pattern_find_named_groups")
+            } else {
+                None
+            };
+            if let Some(function_name) = find {
+                while !matches!(self.current_token().kind, TokenKind::Of | TokenKind::Newline | TokenKind::Eof) {
+                    self.advance(); // consume "find [all|named] matches/groups"
+                }
+                self.consume(&TokenKind::Of, "Expected 'of' after 'find ... matches'")?;
+                if word_at(self, 0, "pattern") {
+                    self.advance(); // optional "pattern"
+                }
+                let pattern = self.parse_term()?;
+                self.consume(&TokenKind::In, "Expected 'in' after the pattern")?;
+                let text = self.parse_term()?;
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: function_name.to_string(),
+                    arguments: vec![text, pattern],
+                }));
+            }
+            if word == "replace" && word_at(self, 1, "pattern") {
+                self.advance(); // consume "replace"
+                self.advance(); // consume "pattern"
+                let pattern = self.parse_term()?;
+                self.consume(&TokenKind::In, "Expected 'in' after 'replace pattern <pattern>'")?;
+                let text = self.parse_term()?;
+                self.consume(&TokenKind::With, "Expected 'with' after 'replace pattern <pattern> in <text>'")?;
+                let replacement = self.parse_term()?;
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "__pattern_replace".to_string(),
+                    arguments: vec![text, pattern, replacement],
+                }));
+            }
+        }
+
+        // http: "fetch <url>" and "send a POST to <url> with body <body> and headers <headers>"
+        if let TokenKind::Identifier(ref s) = &self.current_token().kind {
+            let word = s.to_lowercase();
+            let is_send = word == "send"
+                && matches!(self.peek_kind(1), Some(TokenKind::A))
+                && matches!(self.peek_kind(2), Some(TokenKind::Identifier(_) | TokenKind::TypeIdentifier(_)))
+                && matches!(self.peek_kind(3), Some(TokenKind::To));
+            // a lone "fetch" is still a variable
+            let is_fetch = word == "fetch"
+                && !matches!(self.peek_kind(1), None | Some(TokenKind::Newline | TokenKind::Eof | TokenKind::Is | TokenKind::Equals | TokenKind::Comma | TokenKind::RightParen | TokenKind::Then | TokenKind::Do));
+            if is_send || is_fetch {
+                self.advance(); // consume "send" / "fetch"
+                let method = if is_send {
+                    self.advance(); // consume "a"
+                    let method = self.parse_identifier()?.to_uppercase();
+                    self.advance(); // consume "to"
+                    method
+                } else {
+                    "GET".to_string()
+                };
+                let url = self.parse_term()?;
+                let mut body = None;
+                let mut headers = None;
+                while matches!(self.current_token().kind, TokenKind::With | TokenKind::And) {
+                    let part = match self.peek_kind(1) {
+                        Some(TokenKind::Identifier(s)) if s.to_lowercase() == "body" && body.is_none() => &mut body,
+                        Some(TokenKind::Identifier(s)) if s.to_lowercase() == "headers" && headers.is_none() => &mut headers,
+                        _ => break,
+                    };
+                    self.advance(); // consume "with" / "and"
+                    self.advance(); // consume "body" / "headers"
+                    *part = Some(self.parse_term()?);
+                }
+                let nothing = || Node::LiteralExpression(LiteralExpression {
+                    location: location.clone(),
+                    value: LiteralValue::Nothing,
+                });
+                return Ok(Node::CallExpression(CallExpression {
+                    location: location.clone(),
+                    function_name: "__http_request".to_string(),
+                    arguments: vec![
+                        Node::LiteralExpression(LiteralExpression {
+                            location: location.clone(),
+                            value: LiteralValue::String(method),
+                        }),
+                        url,
+                        body.unwrap_or_else(nothing),
+                        headers.unwrap_or_else(nothing),
+                    ],
+                }));
+            }
+        }
+
+        // type conversion: "convert X to Type" (a lone "convert" is still a variable)
+        if let TokenKind::Identifier(ref s) = &self.current_token().kind {
+            if s.to_lowercase() == "convert"
+                && !matches!(self.peek_kind(1), None | Some(TokenKind::Newline | TokenKind::Eof | TokenKind::Is | TokenKind::Equals | TokenKind::Comma | TokenKind::RightParen | TokenKind::Then | TokenKind::Do))
+            {
+                self.advance(); // consume "convert"
+                let value = self.parse_term()?;
+                self.consume(&TokenKind::To, "Expected 'to' after 'convert <value>'")?;
+                let target_location = self.current_token().location.clone();
+                let target_name = self.parse_identifier()?;
+                let target = crate::stdlib::convert::canonical_target(&target_name)
+                    .ok_or_else(|| self.error(format!("Cannot convert to '{}': expected Number, Text, Bool, List or Date", target_name)))?;
+                return Ok(Node::CallExpression(CallExpression {
+                    location,
+                    function_name: "convert".to_string(),
+                    arguments: vec![
+                        value,
+                        Node::LiteralExpression(LiteralExpression {
+                            location: target_location,
+                            value: LiteralValue::String(target.to_string()),
+                        }),
+                    ],
+                }));
+            }
+        }
+
         // handle variable references - allow single-letter keywords as identifiers
         // this handles cases like "a modulo b" where "a" is a variable name
         // but NOT when it's part of "is a new" (which we checked above)
@@ -3184,12 +3774,12 @@ impl Parser {
             }));
         }
         
-        Err(format!("Unexpected token: {:?}", self.current_token()))
+        Err(self.error(format!("Unexpected token: {}", describe(self.current_token()))))
     }
     
 
     
-    fn parse_class_declaration(&mut self, location: Location) -> Result<Node, String> {
+    fn parse_class_declaration(&mut self, location: Location) -> Result<Node, SyntaxError> {
         // parse class name
         let class_name = self.parse_identifier()?;
         
@@ -3203,10 +3793,10 @@ impl Parser {
                 self.advance();
                 None
             } else {
-                return Err("Expected 'has' or 'extends' after 'that'".to_string());
+                return Err(self.error("Expected 'has' or 'extends' after 'that'"));
             }
         } else {
-            return Err("Expected 'has' or 'extends' after 'that'".to_string());
+            return Err(self.error("Expected 'has' or 'extends' after 'that'"));
         };
         
         // skip newlines
@@ -3231,22 +3821,28 @@ impl Parser {
             
             // check for "define" keyword (for methods)
             if self.check(&TokenKind::Define) {
+                let method_start = self.current;
                 let method_loc = self.current_token().location.clone();
                 self.advance(); // consume "define"
                 
                 // expect "function"
                 if self.check(&TokenKind::Function) {
                     self.advance(); // consume "function"
-                    let method_node = self.parse_function_declaration(method_loc, false)?;
+                    // a method gets a span like a statement of the class body, with its own nested inside
+                    self.statement_depth += 1;
+                    let method_node = self.parse_function_declaration(method_loc.clone(), false);
+                    self.statement_depth -= 1;
+                    let method_node = method_node?;
+                    self.record_span(method_start, method_loc.line, &method_node);
                     if let Node::FunctionDeclaration(method) = method_node {
                         methods.push(method);
                     } else {
-                        return Err("Expected function declaration".to_string());
+                        return Err(self.error("Expected function declaration"));
                     }
                     self.skip_newlines(); // Skip newlines after method
                     continue;
                 } else {
-                    return Err("Expected 'function' after 'define' in class body".to_string());
+                    return Err(self.error("Expected 'function' after 'define' in class body"));
                 }
             }
             
@@ -3285,10 +3881,10 @@ impl Parser {
                                 self.advance(); // consume "type"
                                 self.parse_type()?
                             } else {
-                                return Err(format!("Expected 'type' after 'of', got: {:?}", self.current_token()));
+                                return Err(self.error(format!("Expected 'type' after 'of', got {}", describe(self.current_token()))));
                             }
                         } else {
-                            return Err(format!("Expected 'type' after 'of', got: {:?}", self.current_token()));
+                            return Err(self.error(format!("Expected 'type' after 'of', got {}", describe(self.current_token()))));
                         }
                     } else if let TokenKind::Identifier(ref s) = &self.current_token().kind {
                         if s.to_lowercase() == "of" {
@@ -3299,10 +3895,10 @@ impl Parser {
                                     self.advance(); // consume "type"
                                     self.parse_type()?
                                 } else {
-                                    return Err(format!("Expected 'type' after 'of', got: {:?}", self.current_token()));
+                                    return Err(self.error(format!("Expected 'type' after 'of', got {}", describe(self.current_token()))));
                                 }
                             } else {
-                                return Err(format!("Expected 'type' after 'of', got: {:?}", self.current_token()));
+                                return Err(self.error(format!("Expected 'type' after 'of', got {}", describe(self.current_token()))));
                             }
                         } else {
                             // If "which is" was handled, and "of type" was not found,
@@ -3325,10 +3921,10 @@ impl Parser {
                                 }
                             }
                         }
-                    } else if matches!(&self.current_token().kind, TokenKind::TypeIdentifier(_) | TokenKind::Any | TokenKind::Dictionary) {
+                    } else if matches!(&self.current_token().kind, TokenKind::TypeIdentifier(_) | TokenKind::Any | TokenKind::Dictionary | TokenKind::Maybe) {
                         self.parse_type()?
                     } else {
-                        return Err(format!("Expected type, got: {:?}", self.current_token()));
+                        return Err(self.error(format!("Expected type, got {}", describe(self.current_token()))));
                     };
                     
                     properties.push(PropertyDeclaration {
@@ -3340,10 +3936,10 @@ impl Parser {
                     // skip newlines after property
                     self.skip_newlines();
                 } else {
-                    return Err(format!("Expected 'property' or 'define' in class body, got: {:?}", self.current_token()));
+                    return Err(self.error(format!("Expected 'property' or 'define' in class body, got {}", describe(self.current_token()))));
                 }
             } else {
-                 return Err(format!("Expected 'property' or 'define' in class body, got: {:?}", self.current_token()));
+                 return Err(self.error(format!("Expected 'property' or 'define' in class body, got {}", describe(self.current_token()))));
             }
         }
         
@@ -3356,7 +3952,7 @@ impl Parser {
         }))
     }
     
-    fn parse_identifier(&mut self) -> Result<String, String> {
+    fn parse_identifier(&mut self) -> Result<String, SyntaxError> {
         match &self.current_token().kind {
             TokenKind::Identifier(ref s) | TokenKind::TypeIdentifier(ref s) => {
                 let ident = s.clone();
@@ -3373,18 +3969,18 @@ impl Parser {
                 self.advance();
                 Ok("zero".to_string())
             }
-            _ => Err(format!("Expected identifier, got: {:?}", self.current_token())),
+            _ => Err(self.error(format!("Expected identifier, got {}", describe(self.current_token())))),
         }
     }
     
-    fn parse_type(&mut self) -> Result<Type, String> {
+    fn parse_type(&mut self) -> Result<Type, SyntaxError> {
         // simplified type parsing
         // skip newlines before parsing type
         self.skip_newlines();
         
         // Check if current token is Returns - if so, we have a parsing error upstream
         if matches!(&self.current_token().kind, TokenKind::Returns) {
-            return Err(format!("Unexpected 'returns' token when parsing type - this indicates a parser bug. Current token: {:?}", self.current_token()));
+            return Err(self.error(format!("Unexpected 'returns' token when parsing type - this indicates a parser bug. current token: {}", describe(self.current_token()))));
         }
         
         if matches!(&self.current_token().kind, TokenKind::Maybe) {
@@ -3410,6 +4006,8 @@ impl Parser {
                 "string" | "text" => Ok(Type::BasicType(BasicType::String)),  // text kept for backward compat
                 "bool" | "boolean" => Ok(Type::BasicType(BasicType::Bool)),    // boolean kept for backward compat
                 "void" | "nothing" => Ok(Type::BasicType(BasicType::Void)),     // nothing kept for backward compat
+                "date" => Ok(Type::BasicType(BasicType::Date)),
+                "duration" => Ok(Type::BasicType(BasicType::Duration)),
                 "list" => {
                     // check for "of <Type>"
                     if self.check(&TokenKind::Of) {
@@ -3432,7 +4030,7 @@ impl Parser {
                     } else {
                         // assume key is string if not specified? Or error?
                         // For now, error if comma missing
-                        Err("Expected ',' or 'to' after key type in Dictionary definition".to_string())
+                        Err(self.error("Expected ',' or 'to' after key type in Dictionary definition"))
                     }
                     } else {
                         // generic dictionary
@@ -3445,7 +4043,7 @@ impl Parser {
                 }
             }
         } else {
-            Err(format!("Expected type, got: {:?}", self.current_token()))
+            Err(self.error(format!("Expected type, got {}", describe(self.current_token()))))
         }
     }
     
@@ -3460,7 +4058,7 @@ impl Parser {
         false
     }
     
-    fn parse_run_concurrently_statement(&mut self) -> Result<Node, String> {
+    fn parse_run_concurrently_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         self.consume(&TokenKind::Concurrently, "Expected 'concurrently' after 'run'")?;
@@ -3499,7 +4097,7 @@ impl Parser {
         }))
     }
     
-    fn parse_inspect_statement(&mut self) -> Result<Node, String> {
+    fn parse_inspect_statement(&mut self) -> Result<Node, SyntaxError> {
         // inspect <expr>
         //   case <Variant> do ...
         //   case <Variant> do ...
@@ -3576,7 +4174,7 @@ impl Parser {
         }))
     }
 
-    fn parse_using_statement(&mut self) -> Result<Node, String> {
+    fn parse_using_statement(&mut self) -> Result<Node, SyntaxError> {
         let location = self.previous().location.clone();
         
         // "using" already consumed
@@ -3663,13 +4261,13 @@ impl Parser {
         }
     }
     
-    fn consume(&mut self, kind: &TokenKind, message: &str) -> Result<(), String> {
+    fn consume(&mut self, kind: &TokenKind, message: &str) -> Result<(), SyntaxError> {
         self.skip_newlines();
         if self.check(kind) {
             self.advance();
             Ok(())
         } else {
-            Err(format!("{}: expected {:?}, got {:?}", message, kind, self.current_token()))
+            Err(self.error(format!("{}: expected {}, got {}", message, describe_kind(kind), describe(self.current_token()))))
         }
     }
     
@@ -3723,6 +4321,106 @@ impl Parser {
         None
     }
     
+    /// English queries over a list, starting at "the":
+    /// "the orders where <condition>", "the orders sorted by <key>", "the orders grouped by <key>",
+    /// "the number of orders where <condition>" and "the first of the orders where <condition>";
+    /// conditions and keys refer to the element as "each"
+    fn parse_query(&mut self, location: &Location) -> Result<Option<Node>, SyntaxError> {
+        if !self.is_query_start() {
+            return Ok(None);
+        }
+        // "the number of [the] orders where", "the first of [the] orders where"
+        let summary = if matches!(self.peek_kind(2), Some(TokenKind::Of)) {
+            let function_name = if self.word_at(1, "number") { "__collections_count_where" } else { "__collections_find" };
+            let skip_the = usize::from(matches!(self.peek_kind(3), Some(TokenKind::The)));
+            for _ in 0..3 + skip_the {
+                self.advance(); // consume "the number/first of [the]"
+            }
+            Some(function_name)
+        } else {
+            self.advance(); // consume "the"
+            None
+        };
+
+        let collection_location = self.current_token().location.clone();
+        let name = self.parse_identifier()?;
+        let mut query = Node::VariableExpression(VariableExpression {
+            location: collection_location,
+            identifier: name,
+        });
+
+        if let Some(function_name) = summary {
+            self.advance(); // consume "where"
+            let condition = self.parse_element_function(location, true)?;
+            return Ok(Some(Node::CallExpression(CallExpression {
+                location: location.clone(),
+                function_name: function_name.to_string(),
+                arguments: vec![query, condition],
+            })));
+        }
+
+        while self.is_query_clause(0) {
+            let function_name = if self.word_at(0, "where") {
+                self.advance(); // consume "where"
+                "__collections_filter"
+            } else {
+                let grouped = self.word_at(0, "grouped");
+                self.advance(); // consume "sorted" / "grouped"
+                self.advance(); // consume "by"
+                if grouped { "__collections_group_by" } else { "__collections_sort_by" }
+            };
+            let function = self.parse_element_function(location, function_name == "__collections_filter")?;
+            query = Node::CallExpression(CallExpression {
+                location: location.clone(),
+                function_name: function_name.to_string(),
+                arguments: vec![query, function],
+            });
+        }
+        Ok(Some(query))
+    }
+
+    /// whether the tokens from the current "the" form a query
+    fn is_query_start(&self) -> bool {
+        if !self.check(&TokenKind::The) {
+            return false;
+        }
+        if (self.word_at(1, "number") || self.word_at(1, "first")) && matches!(self.peek_kind(2), Some(TokenKind::Of)) {
+            let skip_the = usize::from(matches!(self.peek_kind(3), Some(TokenKind::The)));
+            return matches!(self.peek_kind(3 + skip_the), Some(TokenKind::Identifier(_))) && self.word_at(4 + skip_the, "where");
+        }
+        matches!(self.peek_kind(1), Some(TokenKind::Identifier(_))) && self.is_query_clause(2)
+    }
+
+    /// "where", "sorted by" or "grouped by" at the given offset
+    fn is_query_clause(&self, offset: usize) -> bool {
+        self.word_at(offset, "where")
+            || ((self.word_at(offset, "sorted") || self.word_at(offset, "grouped"))
+                && matches!(self.peek_kind(offset + 1), Some(TokenKind::DividedBy)))
+    }
+
+    fn word_at(&self, offset: usize, word: &str) -> bool {
+        matches!(self.peek_kind(offset), Some(TokenKind::Identifier(s)) if s.to_lowercase() == word)
+    }
+
+    /// a lambda of "each" whose body is a condition (a full expression) or a key (a term)
+    fn parse_element_function(&mut self, location: &Location, condition: bool) -> Result<Node, SyntaxError> {
+        self.query_depth += 1;
+        let body = if condition { self.parse_expression() } else { self.parse_term() };
+        self.query_depth -= 1;
+        let body = body?;
+        Ok(Node::FunctionDeclaration(FunctionDeclaration {
+            location: location.clone(),
+            name: "<lambda>".to_string(),
+            parameters: vec![Parameter { name: "each".to_string(), type_annotation: None }],
+            return_type: None,
+            body: Box::new(Node::ReturnStatement(ReturnStatement {
+                location: body.location(),
+                expression: Some(Box::new(body)),
+            })),
+            is_async: false,
+        }))
+    }
+
     fn peek_kind(&self, offset: usize) -> Option<&TokenKind> {
         let idx = self.current + offset;
         if idx < self.tokens.len() {
@@ -3733,3 +4431,24 @@ impl Parser {
     }
 }
 
+/// a token as error messages name it: its text in quotes, or the end of a line or the file
+fn describe(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Eof => "end of file".to_string(),
+        TokenKind::Newline => "end of line".to_string(),
+        _ => format!("'{}'", token.text),
+    }
+}
+
+/// a kind of token that was expected, e.g. 'that' or a name
+fn describe_kind(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Eof => "end of file".to_string(),
+        TokenKind::Newline => "end of line".to_string(),
+        TokenKind::Identifier(_) => "a name".to_string(),
+        kind => {
+            let name = format!("{:?}", kind);
+            if name.chars().all(char::is_alphabetic) { format!("'{}'", name.to_lowercase()) } else { name }
+        }
+    }
+}
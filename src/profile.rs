@@ -0,0 +1,235 @@
+// profile - `layman profile file.lay`: where a program spends its time
+//
+// counts the calls, statements and time of every user function and method (and of the top level,
+// as <main>), then prints a table with the busiest first. time is inclusive (with the calls a
+// function makes) and own (without them). --steps ranks by statements executed instead, which is
+// the same on every run. --folded writes one "main;outer;inner weight" line per call stack for
+// flamegraph tools
+
+use crate::ast::{Location, Node};
+use crate::diagnostics::{codes, Diagnostic};
+use crate::evaluator::hook::ExecutionHook;
+use crate::evaluator::Evaluator;
+use crate::types::{Environment, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// the totals of one function
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    steps: u64,
+    own_steps: u64,
+    time: Duration,
+    own_time: Duration,
+}
+
+/// a call that has not returned yet
+struct Call {
+    name: String,
+    /// the names down the stack to this call joined with ';', the key of its folded samples
+    stack: String,
+    started: Instant,
+    /// the step count when it started
+    first_step: u64,
+    /// how much of its time and steps went to the calls it made
+    child_time: Duration,
+    child_steps: u64,
+}
+
+#[derive(Default)]
+struct Samples {
+    steps: u64,
+    time: Duration,
+}
+
+struct State {
+    stack: Vec<Call>,
+    functions: HashMap<String, Stats>,
+    /// own steps and time by call stack, joined with ';'
+    folded: HashMap<String, Samples>,
+    /// statements executed so far
+    steps: u64,
+    /// when the top of the stack last started running its own statements
+    resumed: Instant,
+}
+
+pub struct Profiler {
+    state: Mutex<State>,
+    /// the thread running the program; background tasks on other threads are not counted
+    thread: ThreadId,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        let main = Call {
+            name: "<main>".to_string(),
+            stack: "<main>".to_string(),
+            started: now,
+            first_step: 0,
+            child_time: Duration::ZERO,
+            child_steps: 0,
+        };
+        Profiler {
+            state: Mutex::new(State { stack: vec![main], functions: HashMap::new(), folded: HashMap::new(), steps: 0, resumed: now }),
+            thread: thread::current().id(),
+        }
+    }
+
+    fn state(&self) -> Option<std::sync::MutexGuard<'_, State>> {
+        (thread::current().id() == self.thread).then(|| self.state.lock().unwrap())
+    }
+
+    /// close the top level once the program has finished
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.stack.is_empty() {
+            state.pop();
+        }
+    }
+
+    /// the table, busiest first by own time, or by own statements with `by_steps`
+    pub fn report(&self, by_steps: bool) -> String {
+        let state = self.state.lock().unwrap();
+        let mut rows: Vec<(&String, &Stats)> = state.functions.iter().collect();
+        if by_steps {
+            rows.sort_by(|a, b| b.1.own_steps.cmp(&a.1.own_steps).then_with(|| a.0.cmp(b.0)));
+        } else {
+            rows.sort_by(|a, b| b.1.own_time.cmp(&a.1.own_time).then_with(|| a.0.cmp(b.0)));
+        }
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("Function".len());
+
+        let mut table = format!("{:<width$}  {:>8}  {:>10}  {:>14}", "Function", "Calls", "Statements", "Own statements");
+        if !by_steps {
+            table.push_str(&format!("  {:>10}  {:>14}", "Time (ms)", "Own time (ms)"));
+        }
+        table.push('\n');
+        for (name, stats) in rows {
+            table.push_str(&format!("{:<width$}  {:>8}  {:>10}  {:>14}", name, stats.calls, stats.steps, stats.own_steps));
+            if !by_steps {
+                let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+                table.push_str(&format!("  {:>10.3}  {:>14.3}", ms(stats.time), ms(stats.own_time)));
+            }
+            table.push('\n');
+        }
+        table
+    }
+
+    /// folded stacks, weighted by own statements with `by_steps` and by own microseconds otherwise
+    pub fn folded(&self, by_steps: bool) -> String {
+        let state = self.state.lock().unwrap();
+        let mut lines: Vec<String> = state
+            .folded
+            .iter()
+            .map(|(stack, samples)| (stack, if by_steps { samples.steps } else { samples.time.as_micros() as u64 }))
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(stack, weight)| format!("{} {}", stack, weight))
+            .collect();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+impl State {
+    /// charge the time since the top of the stack last resumed to its own time
+    fn charge(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.resumed;
+        self.resumed = now;
+        self.samples().time += elapsed;
+    }
+
+    /// the folded samples of the stack as it is now
+    fn samples(&mut self) -> &mut Samples {
+        let stack = self.stack.last().map_or("", |call| call.stack.as_str());
+        // only a stack not seen before costs a new key
+        if !self.folded.contains_key(stack) {
+            self.folded.insert(stack.to_string(), Samples::default());
+        }
+        self.folded.get_mut(stack).unwrap()
+    }
+
+    fn pop(&mut self) {
+        self.charge();
+        let Some(call) = self.stack.pop() else {
+            return;
+        };
+        let time = call.started.elapsed();
+        let steps = self.steps - call.first_step;
+        // a recursive call's time is already inside the outermost call of the same function
+        let recursive = self.stack.iter().any(|outer| outer.name == call.name);
+        let stats = self.functions.entry(call.name).or_default();
+        stats.calls += 1;
+        stats.own_time += time.saturating_sub(call.child_time);
+        stats.own_steps += steps - call.child_steps;
+        if !recursive {
+            stats.time += time;
+            stats.steps += steps;
+        }
+        if let Some(caller) = self.stack.last_mut() {
+            caller.child_time += time;
+            caller.child_steps += steps;
+        }
+    }
+}
+
+impl ExecutionHook for Profiler {
+    fn before_statement(&self, _statement: &Node, _location: &Location, _environment: &Arc<Environment>) {
+        if let Some(mut state) = self.state() {
+            state.steps += 1;
+            state.samples().steps += 1;
+        }
+    }
+
+    fn enter_function(&self, name: &str, _arguments: &[(String, Value)]) {
+        if let Some(mut state) = self.state() {
+            state.charge();
+            let first_step = state.steps;
+            let stack = match state.stack.last() {
+                Some(caller) => format!("{};{}", caller.stack, name),
+                None => name.to_string(),
+            };
+            state.stack.push(Call {
+                name: name.to_string(),
+                stack,
+                started: Instant::now(),
+                first_step,
+                child_time: Duration::ZERO,
+                child_steps: 0,
+            });
+        }
+    }
+
+    fn exit_function(&self, _name: &str, _result: Option<&Value>) {
+        if let Some(mut state) = self.state() {
+            state.pop();
+        }
+    }
+}
+
+/// run a file under the profiler, print its table and write the folded stacks to `folded`
+pub fn profile_file(filename: &str, folded: Option<&str>, by_steps: bool) -> Result<(), Vec<Diagnostic>> {
+    let content = fs::read_to_string(filename)
+        .map_err(|e| vec![Diagnostic::error(codes::IO, format!("Failed to read file {}: {}", filename, e))])?;
+    let ast = crate::check_source(filename, &content)?;
+
+    let profiler = Arc::new(Profiler::new());
+    let mut evaluator = Evaluator::new();
+    evaluator.set_hook(Arc::clone(&profiler) as Arc<dyn ExecutionHook>);
+    let result = evaluator.evaluate(&ast);
+    profiler.finish();
+
+    println!();
+    print!("{}", profiler.report(by_steps));
+    if let Some(path) = folded {
+        fs::write(path, profiler.folded(by_steps))
+            .map_err(|e| vec![Diagnostic::error(codes::IO, format!("Failed to write {}: {}", path, e))])?;
+    }
+    crate::exit_if_requested(&evaluator);
+    result.map_err(|e| vec![Diagnostic::from_runtime_error(&e)])?;
+    Ok(())
+}
@@ -0,0 +1,220 @@
+// interactive read-eval-print loop
+//
+// one evaluator and one type checker live for the whole session, so every entry sees the
+// variables, functions and classes of the entries before it. an entry that fails to type
+// check is discarded without changing the session.
+
+use crate::ast::Node;
+use crate::types::Value;
+use crate::{evaluator, lexer, parser, resolver, typechecker};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "\
+Enter Layman statements; the value of an expression is printed.
+A line that starts a block (if, for each, define function, try, ...) keeps reading until the
+matching 'end' line or a blank line.
+
+  :type <expression>   show the type of an expression
+  :ast <code>          show the syntax tree of some code
+  :load <file.lay>     run a file in this session
+  :reset               forget every variable, function and class
+  :help                show this message
+  :quit                leave the session";
+
+/// the words that open a block at the start of a line
+const BLOCK_OPENERS: &[&str] = &[
+    "if", "for", "while", "repeat", "try", "inspect", "describe", "test", "background", "concurrently",
+];
+
+/// what may follow `define` to open a block; `define variable` and `define type` fit on one line
+const DEFINE_BLOCKS: &[&str] = &["function", "class", "struct", "asynchronous", "background"];
+
+pub struct Session {
+    evaluator: evaluator::Evaluator,
+    typechecker: typechecker::TypeChecker,
+    entries: usize,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            evaluator: evaluator::Evaluator::new(),
+            typechecker: typechecker::TypeChecker::new(),
+            entries: 0,
+        }
+    }
+
+    /// handle one complete entry: a command or some code. returns the text to print, if any
+    pub fn handle(&mut self, entry: &str) -> Result<Option<String>, String> {
+        let trimmed = entry.trim();
+        let Some(command) = trimmed.strip_prefix(':') else {
+            self.entries += 1;
+            let file = format!("<repl:{}>", self.entries);
+            return self.run(entry, &file, PathBuf::from("."));
+        };
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "type" => self.type_of(argument).map(Some),
+            "ast" => show_ast(argument).map(Some),
+            "load" => {
+                if argument.is_empty() {
+                    return Err(":load expects a file".to_string());
+                }
+                let content = fs::read_to_string(argument)
+                    .map_err(|e| format!("Failed to read file {}: {}", argument, e))?;
+                let base_dir = Path::new(argument).parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+                self.run(&content, argument, base_dir)
+            }
+            "reset" => {
+                *self = Session::new();
+                Ok(Some("Session reset".to_string()))
+            }
+            "help" => Ok(Some(HELP.to_string())),
+            _ => Err(format!("Unknown command ':{}' (try :help)", name)),
+        }
+    }
+
+    fn run(&mut self, code: &str, file: &str, base_dir: PathBuf) -> Result<Option<String>, String> {
+        let program = parse(code, file)?;
+        let program = resolver::ImportResolver::new(base_dir).resolve_and_bundle(&program).map_err(|e| e.message)?;
+        let Node::Program(program) = program else {
+            return Err("Expected program node".to_string());
+        };
+        self.typechecker.check_entry(&program).map_err(format_type_errors)?;
+
+        let mut shown = None;
+        for stmt in &program.statements {
+            let value = self.evaluator.evaluate(stmt)?;
+            if let Some(code) = self.evaluator.exit_code() {
+                std::process::exit(code);
+            }
+            // only bare expressions echo their value; print and friends return void
+            shown = match (stmt, value) {
+                (_, Value::Void) => None,
+                (Node::ExpressionStatement(_), value) => Some(value.to_string()),
+                _ => None,
+            };
+        }
+        Ok(shown)
+    }
+
+    fn type_of(&mut self, code: &str) -> Result<String, String> {
+        let program = parse(code, "<repl>")?;
+        let Node::Program(program) = program else {
+            return Err("Expected program node".to_string());
+        };
+        match program.statements.as_slice() {
+            [Node::ExpressionStatement(stmt)] => self.typechecker.describe_type(&stmt.expression).map_err(format_type_errors),
+            _ => Err(":type expects a single expression".to_string()),
+        }
+    }
+}
+
+fn parse(code: &str, file: &str) -> Result<Node, String> {
+    let tokens = lexer::Lexer::new(code, file.to_string()).tokenize()?;
+    Ok(parser::Parser::new(tokens).parse()?)
+}
+
+fn show_ast(code: &str) -> Result<String, String> {
+    let ast = parse(code, "<repl>")?;
+    serde_json::to_string_pretty(&ast).map_err(|e| format!("Failed to serialize AST: {}", e))
+}
+
+fn format_type_errors(errors: Vec<typechecker::TypeError>) -> String {
+    let mut message = "Type errors:".to_string();
+    for err in errors {
+        message.push_str(&format!(
+            "\n  {}:{}:{}: {}",
+            err.location.file, err.location.line, err.location.column, err.message
+        ));
+        if let Some(note) = &err.note {
+            message.push_str(&format!("\n    note: {}", note));
+        }
+    }
+    message
+}
+
+/// how many more `end` lines a line needs: 1 when it opens a block, -1 when it closes one
+fn block_change(line: &str) -> i32 {
+    let trimmed = line.trim().to_lowercase();
+    let words: Vec<&str> = trimmed.split_whitespace().collect();
+    let first = words.first().copied().unwrap_or("");
+    // "a new Person with" lists its fields on the lines below
+    let creates_object = matches!(words.as_slice(), [.., "new" | "a" | "an", _, "with"]);
+    if first == "end" {
+        -1
+    } else if BLOCK_OPENERS.contains(&first)
+        || (first == "define" && words.get(1).is_some_and(|word| DEFINE_BLOCKS.contains(word)))
+        || creates_object
+    {
+        1
+    } else {
+        0
+    }
+}
+
+/// run the session on standard input until it ends or `:quit`
+pub fn run() -> Result<(), String> {
+    let interactive = io::stdin().is_terminal();
+    let mut session = Session::new();
+    if interactive {
+        println!("Layman REPL - type :help for commands, :quit to leave");
+    }
+
+    let mut entry = String::new();
+    let mut depth = 0;
+    loop {
+        if interactive {
+            print!("{}", if entry.is_empty() { "layman> " } else { "...> " });
+            io::stdout().flush().map_err(|e| e.to_string())?;
+        }
+        // read one line at a time without holding the lock, so programs can read input too
+        let mut line = String::new();
+        let read = io::stdin().lock().read_line(&mut line).map_err(|e| format!("Failed to read input: {}", e))?;
+        let at_end = read == 0;
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if entry.is_empty() {
+            if at_end {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if matches!(line.trim(), ":quit" | ":exit") {
+                break;
+            }
+            // commands are always a single line
+            if !line.trim_start().starts_with(':') {
+                depth = block_change(line);
+            }
+        } else if !line.trim().is_empty() {
+            depth += block_change(line);
+        }
+        if !at_end && !line.trim().is_empty() {
+            entry.push_str(line);
+            entry.push('\n');
+        }
+
+        let finished = at_end || depth <= 0 || line.trim().is_empty() || entry.trim_start().starts_with(':');
+        if !finished {
+            continue;
+        }
+        match session.handle(&entry) {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: {}", e.trim_end()),
+        }
+        entry.clear();
+        depth = 0;
+        if at_end {
+            break;
+        }
+    }
+    Ok(())
+}
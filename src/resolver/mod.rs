@@ -2,6 +2,7 @@
 // when compiling hello.test.lay that imports hello.lay, bundles both into single executable
 
 use crate::ast::*;
+use crate::diagnostics::{codes, Diagnostic, Span};
 use crate::lexer;
 use crate::parser;
 use std::path::PathBuf;
@@ -23,7 +24,7 @@ impl ImportResolver {
     }
     
     /// resolve all imports in a program and bundle into single AST
-    pub fn resolve_and_bundle(&mut self, ast: &Node) -> Result<Node, String> {
+    pub fn resolve_and_bundle(&mut self, ast: &Node) -> Result<Node, Box<Diagnostic>> {
         match ast {
             Node::Program(prog) => {
                 let mut bundled_statements = Vec::new();
@@ -33,10 +34,8 @@ impl ImportResolver {
                 let mut other_statements = Vec::new();
                 
                 for stmt in &prog.statements {
-                    // println!("DEBUG: checking stmt kind");
                     match stmt {
                         Node::ImportStatement(import) => {
-                            println!("DEBUG: Found ImportStatement for {}", import.module_name);
                             imports.push(import);
                         }
                         _ => {
@@ -47,7 +46,7 @@ impl ImportResolver {
                 
                 // resolve each import and bundle its statements
                 for import in imports {
-                    let (imported_ast, exports) = self.resolve_import(&import.module_name)?;
+                    let (imported_ast, exports) = self.resolve_import(import)?;
                     if let Node::Program(imported_prog) = imported_ast {
                         // add imported statements (excluding imports themselves to avoid cycles)
                         for stmt in imported_prog.statements {
@@ -105,11 +104,14 @@ impl ImportResolver {
         }
     }
     
-    /// resolve a single import: find file, parse it, return AST and exports
-    fn resolve_import(&mut self, module_name: &str) -> Result<(Node, Vec<String>), String> {
+    /// resolve a single import: find file, parse it, return AST and exports. a problem with the
+    /// import itself points at the import statement; one inside the imported file points there
+    fn resolve_import(&mut self, import: &ImportStatement) -> Result<(Node, Vec<String>), Box<Diagnostic>> {
+        let module_name = import.module_name.as_str();
+        let error = |message: String| Box::new(Diagnostic::error(codes::IMPORT, message).with_span(Span::from_location(&import.location)));
         // check for cycles
         if self.visiting.contains(module_name) {
-            return Err(format!("Circular import detected: {}", module_name));
+            return Err(error(format!("Circular import detected: {}", module_name)));
         }
         
         // if already visited, don't include it again (deduplication)
@@ -123,21 +125,21 @@ impl ImportResolver {
         self.visiting.insert(module_name.to_string());
         
         // resolve file path
-        let file_path = self.resolve_file_path(module_name)?;
+        let file_path = self.resolve_file_path(module_name).map_err(error)?;
         
         // read and parse file
         let content = std::fs::read_to_string(&file_path)
-            .map_err(|e| format!("Failed to read import {}: {}", module_name, e))?;
+            .map_err(|e| error(format!("Failed to read import {}: {}", module_name, e)))?;
         
         let file_name = file_path.display().to_string();
         
         // lex
         let mut lexer = lexer::Lexer::new(&content, file_name.clone());
-        let tokens = lexer.tokenize()?;
+        let tokens = lexer.tokenize().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::LEX, &e)))?;
         
         // parse
         let mut parser = parser::Parser::new(tokens);
-        let mut ast = parser.parse()?;
+        let mut ast = parser.parse().map_err(|e| Box::new(Diagnostic::from_syntax_error(codes::SYNTAX, &e)))?;
         
         // extract exports BEFORE recursive resolution (to avoid getting exports of sub-imports?)
         // actually, we want exports of THIS module.
@@ -153,7 +155,7 @@ impl ImportResolver {
     }
     
     /// resolve import path to actual file path
-    fn resolve_file_path(&self, module_name: &str) -> Result<PathBuf, String> {
+    pub fn resolve_file_path(&self, module_name: &str) -> Result<PathBuf, String> {
         // remove .lay extension if present
         let module = if module_name.ends_with(".lay") {
             &module_name[..module_name.len() - 4]
@@ -0,0 +1,133 @@
+use crate::evaluator::RuntimeError;
+use crate::types::{Shared, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// calls the function value passed to a collection operation; its errors keep their location
+pub type Callback<'a> = dyn FnMut(Vec<Value>) -> Result<Value, RuntimeError> + 'a;
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Shared::new(items))
+}
+
+fn test(operation: &str, f: &mut Callback, item: &Value) -> Result<bool, RuntimeError> {
+    match f(vec![item.clone()])? {
+        Value::Bool(b) => Ok(b),
+        other => Err(format!(
+            "The condition given to {} must be true or false, got {}",
+            operation,
+            other.to_string()
+        )
+        .into()),
+    }
+}
+
+pub fn map(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut mapped = Vec::with_capacity(items.len());
+    for item in items {
+        mapped.push(f(vec![item.clone()])?);
+    }
+    Ok(list(mapped))
+}
+
+pub fn filter(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut kept = Vec::new();
+    for item in items {
+        if test("filter", f, item)? {
+            kept.push(item.clone());
+        }
+    }
+    Ok(list(kept))
+}
+
+pub fn reduce(items: &[Value], initial: Value, f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut accumulator = initial;
+    for item in items {
+        accumulator = f(vec![accumulator, item.clone()])?;
+    }
+    Ok(accumulator)
+}
+
+/// the first item that satisfies the condition, or nothing
+pub fn find(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    for item in items {
+        if test("find", f, item)? {
+            return Ok(item.clone());
+        }
+    }
+    Ok(Value::Nothing)
+}
+
+pub fn any(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    for item in items {
+        if test("any", f, item)? {
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(false))
+}
+
+pub fn all(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    for item in items {
+        if !test("all", f, item)? {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+pub fn count_where(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut count = 0;
+    for item in items {
+        if test("count where", f, item)? {
+            count += 1;
+        }
+    }
+    Ok(Value::Number(count as f64))
+}
+
+/// a dictionary from each key (as text) to the items with that key, in their original order
+pub fn group_by(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut groups: HashMap<String, Value> = HashMap::new();
+    for item in items {
+        let key = f(vec![item.clone()])?.to_string();
+        let group = groups.entry(key).or_insert_with(|| list(Vec::new()));
+        if let Value::List(members) = group {
+            members.lock().unwrap().push(item.clone());
+        }
+    }
+    Ok(Value::Dictionary(Shared::new(groups)))
+}
+
+/// a new list ordered by each item's key; items with equal keys keep their order
+pub fn sort_by(items: &[Value], f: &mut Callback) -> Result<Value, RuntimeError> {
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        keyed.push((f(vec![item.clone()])?, item.clone()));
+    }
+    let mut error = None;
+    keyed.sort_by(|(a, _), (b, _)| {
+        compare_keys(a, b).unwrap_or_else(|e| {
+            error.get_or_insert(e);
+            Ordering::Equal
+        })
+    });
+    if let Some(e) = error {
+        return Err(e.into());
+    }
+    Ok(list(keyed.into_iter().map(|(_, item)| item).collect()))
+}
+
+fn compare_keys(a: &Value, b: &Value) -> Result<Ordering, String> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => Ok(x.partial_cmp(y).unwrap_or(Ordering::Equal)),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Ok(x.cmp(y)),
+        (Value::Date(x), Value::Date(y)) | (Value::Duration(x), Value::Duration(y)) => Ok(x.cmp(y)),
+        _ => Err(format!(
+            "Cannot sort by keys of different kinds: {} and {}",
+            a.to_string(),
+            b.to_string()
+        )),
+    }
+}
@@ -0,0 +1,121 @@
+use crate::types::{Shared, Value};
+
+/// canonical target names accepted by `convert X to <Type>`
+pub fn canonical_target(name: &str) -> Option<&'static str> {
+    match name.to_lowercase().as_str() {
+        "number" => Some("Number"),
+        "text" | "string" => Some("Text"),
+        "bool" | "boolean" => Some("Bool"),
+        "list" => Some("List"),
+        "date" => Some("Date"),
+        _ => None,
+    }
+}
+
+pub fn convert(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err("convert expects a value and a target type".to_string());
+    }
+    let target = match &args[1] {
+        Value::String(s) => s,
+        _ => return Err("convert target type must be a type name".to_string()),
+    };
+    let value = &args[0];
+    match canonical_target(target) {
+        Some("Number") => to_number(value),
+        Some("Text") => Ok(Value::String(value.to_string())),
+        Some("Bool") => to_bool(value),
+        Some("List") => to_list(value),
+        Some("Date") => to_date(value),
+        _ => Err(format!("Cannot convert to unknown type '{}' (expected Number, Text, Bool, List or Date)", target)),
+    }
+}
+
+fn to_number(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Number(n) => Ok(Value::Number(*n)),
+        Value::Bool(b) => Ok(Value::Number(if *b { 1.0 } else { 0.0 })),
+        Value::String(s) => parse_number_strict(s)
+            .map(Value::Number)
+            .ok_or_else(|| format!("Cannot convert \"{}\" to Number: not a valid number", s)),
+        other => Err(format!("Cannot convert {} to Number", describe(other))),
+    }
+}
+
+fn to_bool(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Bool(b) => Ok(Value::Bool(*b)),
+        Value::Number(n) if *n == 1.0 => Ok(Value::Bool(true)),
+        Value::Number(n) if *n == 0.0 => Ok(Value::Bool(false)),
+        Value::Number(n) => Err(format!("Cannot convert {} to Bool: only 1 and 0 are booleans", n)),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" => Ok(Value::Bool(true)),
+            "false" | "no" => Ok(Value::Bool(false)),
+            _ => Err(format!("Cannot convert \"{}\" to Bool: expected true, false, yes or no", s)),
+        },
+        other => Err(format!("Cannot convert {} to Bool", describe(other))),
+    }
+}
+
+fn to_list(value: &Value) -> Result<Value, String> {
+    match value {
+        // copy so the converted list does not alias the original
+        Value::List(items) => Ok(Value::List(Shared::new(items.lock().unwrap().clone()))),
+        Value::String(s) => {
+            let chars = s.chars().map(|c| Value::String(c.to_string())).collect();
+            Ok(Value::List(Shared::new(chars)))
+        }
+        other => Err(format!("Cannot convert {} to List", describe(other))),
+    }
+}
+
+fn to_date(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Date(d) => Ok(Value::Date(*d)),
+        Value::String(s) => crate::stdlib::time::parse_iso(s)
+            .map(Value::Date)
+            .ok_or_else(|| format!("Cannot convert \"{}\" to Date: expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ", s)),
+        other => Err(format!("Cannot convert {} to Date", describe(other))),
+    }
+}
+
+/// parse decimal text such as "42", "-3.5" or "1e3"; rejects empty text, "inf", "nan" and trailing junk
+pub fn parse_number_strict(text: &str) -> Option<f64> {
+    let t = text.trim();
+    let digits = t.strip_prefix(['+', '-']).unwrap_or(t);
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(i) => (&digits[..i], Some(&digits[i + 1..])),
+        None => (digits, None),
+    };
+    let (whole, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && frac.is_empty()) || !all_digits(whole) || !all_digits(frac) {
+        return None;
+    }
+    if let Some(exp) = exponent {
+        let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+        if exp.is_empty() || !all_digits(exp) {
+            return None;
+        }
+    }
+    t.parse::<f64>().ok()
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "a number",
+        Value::String(_) => "text",
+        Value::Bool(_) => "a boolean",
+        Value::List(_) => "a list",
+        Value::Dictionary(_) => "a dictionary",
+        Value::Function { .. } => "a function",
+        Value::Object(_) => "an object",
+        Value::Class { .. } => "a class",
+        Value::Future(_) => "a future",
+        Value::Variant(..) => "a variant",
+        Value::Date(_) => "a date",
+        Value::Duration(_) => "a duration",
+        Value::Nothing | Value::Void => "nothing",
+        Value::Return(_) => "a return value",
+    }
+}
@@ -0,0 +1,191 @@
+use crate::types::{Shared, Value};
+use std::collections::{BTreeSet, HashMap};
+
+/// a parsed row and the line it starts on (rows can span lines inside quotes)
+struct Row {
+    line: usize,
+    fields: Vec<String>,
+}
+
+/// split CSV text into rows of fields (RFC 4180: quoted fields may contain
+/// commas, newlines and doubled quotes); blank lines are skipped
+fn parse(text: &str) -> Result<Vec<Row>, String> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                let quote_line = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => return Err(format!("Invalid CSV at line {}: unterminated quoted field", quote_line)),
+                    }
+                }
+                match chars.peek() {
+                    None | Some(',') | Some('\n') | Some('\r') => {}
+                    Some(other) => {
+                        return Err(format!(
+                            "Invalid CSV at line {}: unexpected '{}' after closing quote",
+                            line, other
+                        ))
+                    }
+                }
+            }
+            '"' => {
+                return Err(format!("Invalid CSV at line {}: quote inside an unquoted field", line));
+            }
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                if fields.len() == 1 && fields[0].is_empty() {
+                    fields.clear();
+                } else {
+                    rows.push(Row { line: row_line, fields: std::mem::take(&mut fields) });
+                }
+                line += 1;
+                row_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        rows.push(Row { line: row_line, fields });
+    }
+    Ok(rows)
+}
+
+fn text_arg<'a>(args: &'a [Value], name: &str) -> Result<&'a str, String> {
+    match args {
+        [Value::String(s), ..] => Ok(s),
+        [_, ..] => Err(format!("{} expects CSV text", name)),
+        [] => Err(format!("{} expects 1 argument", name)),
+    }
+}
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Shared::new(items))
+}
+
+/// every row as a list of text fields, header included
+pub fn parse_rows(args: &[Value]) -> Result<Value, String> {
+    let text = text_arg(args, "__csv_parse_rows")?;
+    let rows = parse(text)?
+        .into_iter()
+        .map(|row| list(row.fields.into_iter().map(Value::String).collect()))
+        .collect();
+    Ok(list(rows))
+}
+
+/// one dictionary per row after the header, keyed by the header's column names
+pub fn parse_records(args: &[Value]) -> Result<Value, String> {
+    let text = text_arg(args, "__csv_parse_records")?;
+    let mut rows = parse(text)?.into_iter();
+    let header = match rows.next() {
+        Some(row) => row.fields,
+        None => return Ok(list(Vec::new())),
+    };
+    let mut records = Vec::new();
+    for row in rows {
+        if row.fields.len() != header.len() {
+            return Err(format!(
+                "Invalid CSV at line {}: expected {} fields but found {}",
+                row.line,
+                header.len(),
+                row.fields.len()
+            ));
+        }
+        let record: HashMap<String, Value> = header
+            .iter()
+            .cloned()
+            .zip(row.fields.into_iter().map(Value::String))
+            .collect();
+        records.push(Value::Dictionary(Shared::new(record)));
+    }
+    Ok(list(records))
+}
+
+fn format_field(value: &Value) -> String {
+    let text = value.to_string();
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn format_line(fields: &[Value]) -> String {
+    fields.iter().map(format_field).collect::<Vec<_>>().join(",")
+}
+
+/// CSV text for a list of rows, one line per row
+pub fn format_rows(args: &[Value]) -> Result<Value, String> {
+    let rows = match args {
+        [Value::List(rows)] => rows.lock().unwrap().clone(),
+        _ => return Err("__csv_format_rows expects a list of rows".to_string()),
+    };
+    let mut out = String::new();
+    for row in rows {
+        match row {
+            Value::List(fields) => out.push_str(&format_line(&fields.lock().unwrap())),
+            other => out.push_str(&format_field(&other)),
+        }
+        out.push('\n');
+    }
+    Ok(Value::String(out))
+}
+
+/// CSV text for a list of dictionaries with a header row; the columns are the
+/// given list, or every key in alphabetical order when that list is empty
+pub fn format_records(args: &[Value]) -> Result<Value, String> {
+    let (records, columns) = match args {
+        [Value::List(records), Value::List(columns)] => {
+            (records.lock().unwrap().clone(), columns.lock().unwrap().clone())
+        }
+        _ => return Err("__csv_format_records expects a list of dictionaries and a list of columns".to_string()),
+    };
+    let mut dictionaries = Vec::new();
+    for record in &records {
+        match record {
+            Value::Dictionary(entries) => dictionaries.push(entries.lock().unwrap().clone()),
+            other => return Err(format!("CSV records must be dictionaries, got {}", other.to_string())),
+        }
+    }
+    let columns: Vec<String> = if columns.is_empty() {
+        let keys: BTreeSet<&String> = dictionaries.iter().flat_map(|d| d.keys()).collect();
+        keys.into_iter().cloned().collect()
+    } else {
+        columns.iter().map(|c| c.to_string()).collect()
+    };
+
+    let header: Vec<Value> = columns.iter().cloned().map(Value::String).collect();
+    let mut out = format_line(&header);
+    out.push('\n');
+    for dictionary in &dictionaries {
+        let fields: Vec<Value> = columns
+            .iter()
+            .map(|c| dictionary.get(c).cloned().unwrap_or(Value::String(String::new())))
+            .collect();
+        out.push_str(&format_line(&fields));
+        out.push('\n');
+    }
+    Ok(Value::String(out))
+}
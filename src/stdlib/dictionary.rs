@@ -0,0 +1,73 @@
+use crate::types::{Shared, Value};
+use std::collections::HashMap;
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Shared::new(items))
+}
+
+/// the error for reading a key the dictionary does not have
+pub fn missing_key(key: &str) -> String {
+    format!("Key \"{}\" is not in the dictionary", key)
+}
+
+/// keys in sorted order, so keys, values and entries always line up
+pub fn sorted_keys(entries: &HashMap<String, Value>) -> Vec<String> {
+    let mut keys: Vec<String> = entries.keys().cloned().collect();
+    keys.sort();
+    keys
+}
+
+pub fn keys(entries: &HashMap<String, Value>) -> Value {
+    list(sorted_keys(entries).into_iter().map(Value::String).collect())
+}
+
+pub fn values(entries: &HashMap<String, Value>) -> Value {
+    list(sorted_keys(entries).iter().map(|k| entries[k].clone()).collect())
+}
+
+/// each entry as a two-item list of key and value, ordered by key
+pub fn entries(entries: &HashMap<String, Value>) -> Value {
+    list(
+        sorted_keys(entries)
+            .into_iter()
+            .map(|k| {
+                let value = entries[&k].clone();
+                list(vec![Value::String(k), value])
+            })
+            .collect(),
+    )
+}
+
+/// a new dictionary with the entries of both; `other` wins where keys overlap
+pub fn merge(base: &HashMap<String, Value>, other: &Value) -> Result<Value, String> {
+    let other = match other {
+        Value::Dictionary(other) => other.lock().unwrap().clone(),
+        value => return Err(format!("Can only merge a dictionary, got {}", value.to_string())),
+    };
+    let mut merged = base.clone();
+    merged.extend(other);
+    Ok(Value::Dictionary(Shared::new(merged)))
+}
+
+/// a dictionary built from a list of two-item [key, value] lists; later pairs win
+pub fn from_pairs(args: &[Value]) -> Result<Value, String> {
+    let pairs = match args {
+        [Value::List(pairs)] => pairs.lock().unwrap().clone(),
+        [other] => return Err(format!("A dictionary can only be built from a list of pairs, got {}", other.to_string())),
+        _ => return Err("__dict_from_pairs expects a list of pairs".to_string()),
+    };
+    let mut entries = HashMap::new();
+    for pair in pairs {
+        let items = match &pair {
+            Value::List(items) => items.lock().unwrap().clone(),
+            _ => Vec::new(),
+        };
+        match items.as_slice() {
+            [key, value] => {
+                entries.insert(key.to_string(), value.clone());
+            }
+            _ => return Err(format!("Each pair must be a list of a key and a value, got {}", pair.to_string())),
+        }
+    }
+    Ok(Value::Dictionary(Shared::new(entries)))
+}
@@ -0,0 +1,211 @@
+use crate::types::{Shared, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    /// header names as written by the program
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    /// header names in lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// how `fetch` and `send` reach the network; tests can substitute canned responses
+pub trait Transport: Send + Sync {
+    fn send(&self, request: &Request) -> Result<Response, String>;
+}
+
+/// HTTP/1.1 over a plain TCP connection (no TLS), one connection per request
+pub struct PlainHttpTransport {
+    pub timeout: Duration,
+}
+
+impl Default for PlainHttpTransport {
+    fn default() -> Self {
+        Self { timeout: Duration::from_secs(30) }
+    }
+}
+
+impl Transport for PlainHttpTransport {
+    fn send(&self, request: &Request) -> Result<Response, String> {
+        let fail = |reason: String| format!("{} {} failed: {}", request.method, request.url, reason);
+        let (host, port, path) = split_url(&request.url).map_err(fail)?;
+
+        let mut stream = TcpStream::connect((host.as_str(), port)).map_err(|e| fail(e.to_string()))?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(|e| fail(e.to_string()))?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(|e| fail(e.to_string()))?;
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", request.method, path);
+        if port == 80 {
+            head.push_str(&format!("Host: {}\r\n", host));
+        } else {
+            head.push_str(&format!("Host: {}:{}\r\n", host, port));
+        }
+        head.push_str("User-Agent: layman\r\nConnection: close\r\n");
+        let has_header = |name: &str| request.headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        let body = request.body.as_deref().unwrap_or("");
+        if request.body.is_some() {
+            if !has_header("content-type") {
+                head.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+            }
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes()).map_err(|e| fail(e.to_string()))?;
+        stream.write_all(body.as_bytes()).map_err(|e| fail(e.to_string()))?;
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).map_err(|e| fail(e.to_string()))?;
+        parse_response(&raw).map_err(fail)
+    }
+}
+
+/// "http://host[:port][/path]" into its parts
+fn split_url(url: &str) -> Result<(String, u16, String), String> {
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None if url.starts_with("https://") => return Err("https is not supported, only http:// URLs".to_string()),
+        None => return Err("the URL must start with http://".to_string()),
+    };
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(i) if rest[i..].starts_with('/') => (&rest[..i], rest[i..].to_string()),
+        Some(i) => (&rest[..i], format!("/{}", &rest[i..])),
+        None => (rest, "/".to_string()),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("invalid port '{}'", port))?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err("the URL has no host".to_string());
+    }
+    if path.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("the URL path contains a space or control character".to_string());
+    }
+    Ok((host.to_string(), port, path))
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response, String> {
+    let split = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("incomplete response from server")?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let mut body = raw[split + 4..].to_vec();
+
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or("");
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("malformed status line '{}'", status_line))?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+    if header("transfer-encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        body = decode_chunked(&body)?;
+    } else if let Some(length) = header("content-length").and_then(|v| v.parse::<usize>().ok()) {
+        body.truncate(length);
+    }
+
+    Ok(Response {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or("truncated chunked response")?;
+        let size_text = String::from_utf8_lossy(&data[..line_end]);
+        let size_text = size_text.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_text, 16).map_err(|_| format!("invalid chunk size '{}'", size_text))?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err("truncated chunked response".to_string());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or(&[]);
+    }
+}
+
+/// build a request from the evaluated arguments of `__http_request`:
+/// method, url, body (nothing for none) and headers (a dictionary or nothing)
+pub fn request_from_args(args: &[Value]) -> Result<Request, String> {
+    let (method, url, body, headers) = match args {
+        [Value::String(method), Value::String(url), body, headers] => (method, url, body, headers),
+        [_, url, _, _] => return Err(format!("Cannot send a request to {}: the URL must be text", url.to_string())),
+        _ => return Err("__http_request expects a method, url, body and headers".to_string()),
+    };
+    let body = match body {
+        Value::Nothing | Value::Void => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    };
+    let headers = match headers {
+        Value::Nothing | Value::Void => Vec::new(),
+        Value::Dictionary(entries) => {
+            let mut headers: Vec<(String, String)> = entries
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect();
+            headers.sort();
+            headers
+        }
+        other => return Err(format!("Request headers must be a dictionary, got {}", other.to_string())),
+    };
+    // a space or line break would end the request line or a header early and let the rest pass as another
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("Cannot send a request to {:?}: the URL contains a space or control character", url));
+    }
+    if let Some((name, _)) = headers.iter().find(|(name, value)| name.contains(['\r', '\n']) || value.contains(['\r', '\n'])) {
+        return Err(format!("The request header {:?} contains a line break", name));
+    }
+    Ok(Request {
+        method: method.to_uppercase(),
+        url: url.clone(),
+        headers,
+        body,
+    })
+}
+
+/// a response as a dictionary with "status", "ok", "headers" and "body"
+pub fn response_value(response: Response) -> Value {
+    let headers: HashMap<String, Value> = response
+        .headers
+        .into_iter()
+        .map(|(name, value)| (name, Value::String(value)))
+        .collect();
+    let mut fields = HashMap::new();
+    fields.insert("status".to_string(), Value::Number(response.status as f64));
+    fields.insert("ok".to_string(), Value::Bool((200..300).contains(&response.status)));
+    fields.insert("headers".to_string(), Value::Dictionary(Shared::new(headers)));
+    fields.insert("body".to_string(), Value::String(response.body));
+    Value::Dictionary(Shared::new(fields))
+}
@@ -1,4 +1,6 @@
-use crate::types::Value;
+use crate::types::{Shared, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 pub fn read_file(args: &[Value]) -> Result<Value, String> {
     if args.len() != 1 {
@@ -34,3 +36,200 @@ pub fn write_file(args: &[Value]) -> Result<Value, String> {
         Err(e) => Err(format!("Failed to write file {}: {}", path, e)),
     }
 }
+
+fn expect_args(name: &str, args: &[Value], count: usize) -> Result<(), String> {
+    if args.len() != count {
+        let plural = if count == 1 { "argument" } else { "arguments" };
+        return Err(format!("{} expects {} {}", name, count, plural));
+    }
+    Ok(())
+}
+
+fn text_arg<'a>(args: &'a [Value], index: usize, what: &str) -> Result<&'a str, String> {
+    match &args[index] {
+        Value::String(s) => Ok(s),
+        _ => Err(format!("{} must be a string", what)),
+    }
+}
+
+pub fn append_file(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_append_file", args, 2)?;
+    let path = text_arg(args, 0, "path")?;
+    let content = text_arg(args, 1, "content")?;
+    use std::io::Write as _;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to append to file {}: {}", path, e))
+}
+
+pub fn exists(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_exists", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    Ok(Value::Bool(std::path::Path::new(path).exists()))
+}
+
+/// entry names (not full paths), sorted so listings are deterministic
+pub fn list_directory(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_list_directory", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    let entries = std::fs::read_dir(path)
+        .map_err(|e| format!("Failed to list directory {}: {}", path, e))?;
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list directory {}: {}", path, e))?;
+        names.push(entry.file_name().to_string_lossy().to_string());
+    }
+    names.sort();
+    let items = names.into_iter().map(Value::String).collect();
+    Ok(Value::List(Shared::new(items)))
+}
+
+/// creates missing parent directories too; an existing directory is not an error
+pub fn make_directory(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_make_directory", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    std::fs::create_dir_all(path)
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to create directory {}: {}", path, e))
+}
+
+/// removes the directory and everything inside it
+pub fn remove_directory(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_remove_directory", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    std::fs::remove_dir_all(path)
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to remove directory {}: {}", path, e))
+}
+
+pub fn delete_file(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_delete_file", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    std::fs::remove_file(path)
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to delete file {}: {}", path, e))
+}
+
+pub fn copy_file(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_copy_file", args, 2)?;
+    let from = text_arg(args, 0, "source path")?;
+    let to = text_arg(args, 1, "destination path")?;
+    std::fs::copy(from, to)
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to copy {} to {}: {}", from, to, e))
+}
+
+pub fn move_file(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_move_file", args, 2)?;
+    let from = text_arg(args, 0, "source path")?;
+    let to = text_arg(args, 1, "destination path")?;
+    std::fs::rename(from, to)
+        .map(|_| Value::Void)
+        .map_err(|e| format!("Failed to move {} to {}: {}", from, to, e))
+}
+
+pub fn join_path(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_join_path", args, 2)?;
+    let base = text_arg(args, 0, "base path")?;
+    let part = text_arg(args, 1, "path part")?;
+    Ok(Value::String(std::path::Path::new(base).join(part).to_string_lossy().to_string()))
+}
+
+/// dictionary with "bytes" (the file size), "modified" (seconds since the unix epoch),
+/// "is_file" and "is_directory"
+pub fn file_info(args: &[Value]) -> Result<Value, String> {
+    expect_args("__io_file_info", args, 1)?;
+    let path = text_arg(args, 0, "path")?;
+    let meta = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read metadata for {}: {}", path, e))?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as f64)
+        .unwrap_or(0.0);
+    let mut info = HashMap::new();
+    info.insert("bytes".to_string(), Value::Number(meta.len() as f64));
+    info.insert("modified".to_string(), Value::Number(modified));
+    info.insert("is_file".to_string(), Value::Bool(meta.is_file()));
+    info.insert("is_directory".to_string(), Value::Bool(meta.is_dir()));
+    Ok(Value::Dictionary(Shared::new(info)))
+}
+
+/// where `ask`, `read all input` and `the lines of input` read from
+pub trait InputSource: Send + Sync {
+    /// the next line without its line ending, or None at the end of input
+    fn read_line(&self) -> Option<String>;
+    /// everything that has not been read yet
+    fn read_all(&self) -> String;
+}
+
+pub struct StdinInput;
+
+impl InputSource for StdinInput {
+    fn read_line(&self) -> Option<String> {
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(strip_line_ending(line)),
+        }
+    }
+
+    fn read_all(&self) -> String {
+        let mut text = String::new();
+        let _ = std::io::Read::read_to_string(&mut std::io::stdin(), &mut text);
+        text
+    }
+}
+
+/// input supplied up front, for tests and embedders without a terminal
+#[allow(dead_code)]
+pub struct ScriptedInput {
+    remaining: Mutex<String>,
+}
+
+impl ScriptedInput {
+    #[allow(dead_code)]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { remaining: Mutex::new(text.into()) }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn read_line(&self) -> Option<String> {
+        let mut remaining = self.remaining.lock().unwrap();
+        if remaining.is_empty() {
+            return None;
+        }
+        let line = match remaining.find('\n') {
+            Some(end) => remaining.drain(..=end).collect(),
+            None => std::mem::take(&mut *remaining),
+        };
+        Some(strip_line_ending(line))
+    }
+
+    fn read_all(&self) -> String {
+        std::mem::take(&mut *self.remaining.lock().unwrap())
+    }
+}
+
+fn strip_line_ending(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+/// the rest of the input as a list of lines; a `for each` over the lines of input reads them
+/// one at a time instead
+pub fn input_lines(input: &dyn InputSource) -> Value {
+    let lines = std::iter::from_fn(|| input.read_line()).map(Value::String).collect();
+    Value::List(Shared::new(lines))
+}
@@ -0,0 +1,61 @@
+use crate::types::{Shared, Value};
+use serde_json::{Map, Number, Value as Json};
+use std::collections::HashMap;
+
+/// JSON text to a Layman value: objects become dictionaries, arrays lists, null nothing
+pub fn parse(args: &[Value]) -> Result<Value, String> {
+    let text = match args {
+        [Value::String(s)] => s,
+        _ => return Err("__json_parse expects JSON text".to_string()),
+    };
+    let json: Json = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    Ok(from_json(json))
+}
+
+/// a Layman value as compact JSON text; dictionary keys are written in sorted order
+pub fn format(args: &[Value]) -> Result<Value, String> {
+    let value = match args {
+        [value] => value,
+        _ => return Err("__json_format expects 1 argument".to_string()),
+    };
+    Ok(Value::String(to_json(value)?.to_string()))
+}
+
+fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nothing,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => Value::Number(n.as_f64().unwrap_or(0.0)),
+        Json::String(s) => Value::String(s),
+        Json::Array(items) => Value::List(Shared::new(items.into_iter().map(from_json).collect())),
+        Json::Object(entries) => {
+            let entries: HashMap<String, Value> = entries.into_iter().map(|(k, v)| (k, from_json(v))).collect();
+            Value::Dictionary(Shared::new(entries))
+        }
+    }
+}
+
+fn to_json(value: &Value) -> Result<Json, String> {
+    Ok(match value {
+        Value::Nothing | Value::Void => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 9.0e15 => Json::Number(Number::from(*n as i64)),
+        Value::Number(n) => Json::Number(Number::from_f64(*n).ok_or_else(|| format!("Cannot write {} as JSON", n))?),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Date(_) | Value::Duration(_) => Json::String(value.to_string()),
+        Value::List(items) => Json::Array(items.lock().unwrap().iter().map(to_json).collect::<Result<_, _>>()?),
+        Value::Dictionary(entries) => object(&entries.lock().unwrap())?,
+        Value::Object(object_data) => object(&object_data.lock().unwrap().properties)?,
+        other => return Err(format!("Cannot write {} as JSON", other.to_string())),
+    })
+}
+
+fn object(entries: &HashMap<String, Value>) -> Result<Json, String> {
+    let mut keys: Vec<&String> = entries.keys().collect();
+    keys.sort();
+    let mut map = Map::new();
+    for key in keys {
+        map.insert(key.clone(), to_json(&entries[key])?);
+    }
+    Ok(Json::Object(map))
+}
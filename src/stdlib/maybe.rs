@@ -0,0 +1,40 @@
+use crate::types::Value;
+
+/// `maybe xs[i]` / `maybe d[key]`: the item or entry, or nothing when it is missing;
+/// looking up in nothing gives nothing
+pub fn index(args: &[Value]) -> Result<Value, String> {
+    let (object, key) = match args {
+        [object, key] => (object, key),
+        _ => return Err("__maybe_index expects an object and an index".to_string()),
+    };
+    match object {
+        Value::Nothing | Value::Void => Ok(Value::Nothing),
+        Value::List(items) => {
+            let position = match key {
+                Value::Number(n) => *n,
+                _ => return Err("List index must be a number".to_string()),
+            };
+            let items = items.lock().unwrap();
+            if position < 1.0 || position.fract() != 0.0 || position as usize > items.len() {
+                return Ok(Value::Nothing);
+            }
+            Ok(items[position as usize - 1].clone())
+        }
+        Value::Dictionary(map) => Ok(map.lock().unwrap().get(&key.to_string()).cloned().unwrap_or(Value::Nothing)),
+        other => Err(format!("Cannot index {}", other.to_string())),
+    }
+}
+
+/// `maybe get name from x`: the property or dictionary entry, or nothing when it is missing
+pub fn property(args: &[Value]) -> Result<Value, String> {
+    let (object, name) = match args {
+        [object, Value::String(name)] => (object, name),
+        _ => return Err("__maybe_property expects an object and a property name".to_string()),
+    };
+    match object {
+        Value::Nothing | Value::Void => Ok(Value::Nothing),
+        Value::Object(data) => Ok(data.lock().unwrap().properties.get(name).cloned().unwrap_or(Value::Nothing)),
+        Value::Dictionary(map) => Ok(map.lock().unwrap().get(name).cloned().unwrap_or(Value::Nothing)),
+        other => Err(format!("Cannot look up property '{}' on {}", name, other.to_string())),
+    }
+}
@@ -1,2 +1,11 @@
+pub mod collections;
+pub mod convert;
+pub mod csv;
+pub mod dictionary;
+pub mod http;
 pub mod io;
+pub mod json;
+pub mod maybe;
+pub mod pattern;
 pub mod string;
+pub mod time;
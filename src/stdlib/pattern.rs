@@ -0,0 +1,124 @@
+use crate::types::{Shared, Value};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// compiled patterns, so a pattern used inside a loop is only compiled once
+static CACHE: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+
+/// how many compiled patterns the cache holds before it starts over
+const CACHE_LIMIT: usize = 256;
+
+/// compile a pattern; the regex engine matches in time linear in the text,
+/// so untrusted patterns cannot hang the interpreter
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(regex) = cache.lock().unwrap().get(pattern) {
+        return Ok(regex.clone());
+    }
+    let regex = regex::RegexBuilder::new(pattern)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| describe_error(pattern, e))?;
+    let mut cache = cache.lock().unwrap();
+    // patterns built from data would otherwise grow the cache without end
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+fn describe_error(pattern: &str, error: regex::Error) -> String {
+    let reason = match error {
+        // syntax errors are several lines with a caret; keep the final "error: ..." line
+        regex::Error::Syntax(text) => text
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix("error: "))
+            .unwrap_or("invalid syntax")
+            .to_string(),
+        regex::Error::CompiledTooBig(_) => "pattern is too large".to_string(),
+        other => other.to_string(),
+    };
+    format!("Invalid pattern \"{}\": {}", pattern, reason)
+}
+
+fn text_and_pattern<'a>(name: &str, args: &'a [Value], count: usize) -> Result<(&'a str, Regex), String> {
+    if args.len() != count {
+        return Err(format!("{} expects {} arguments", name, count));
+    }
+    let text = match &args[0] {
+        Value::String(s) => s,
+        other => return Err(format!("Can only match patterns against text, got {}", other.to_string())),
+    };
+    let pattern = match &args[1] {
+        Value::String(s) => s,
+        other => return Err(format!("A pattern must be text, got {}", other.to_string())),
+    };
+    Ok((text, compile(pattern)?))
+}
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Shared::new(items))
+}
+
+/// the numbered groups of one match; groups that did not take part are empty text
+fn groups(captures: &regex::Captures) -> Value {
+    list(
+        captures
+            .iter()
+            .skip(1)
+            .map(|group| Value::String(group.map(|m| m.as_str().to_string()).unwrap_or_default()))
+            .collect(),
+    )
+}
+
+pub fn matches(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_matches", args, 2)?;
+    Ok(Value::Bool(regex.is_match(text)))
+}
+
+/// every non-overlapping match, as text
+pub fn find_all(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_find_all", args, 2)?;
+    Ok(list(regex.find_iter(text).map(|m| Value::String(m.as_str().to_string())).collect()))
+}
+
+/// the groups of the first match (an empty list when nothing matches)
+pub fn find_groups(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_find_groups", args, 2)?;
+    Ok(match regex.captures(text) {
+        Some(captures) => groups(&captures),
+        None => list(Vec::new()),
+    })
+}
+
+/// the groups of every match, one list per match
+pub fn find_all_groups(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_find_all_groups", args, 2)?;
+    Ok(list(regex.captures_iter(text).map(|captures| groups(&captures)).collect()))
+}
+
+/// the named groups (`(?<name>...)`) of the first match, keyed by name
+pub fn find_named_groups(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_find_named_groups", args, 2)?;
+    let mut named = HashMap::new();
+    if let Some(captures) = regex.captures(text) {
+        for name in regex.capture_names().flatten() {
+            let value = captures.name(name).map(|m| m.as_str().to_string()).unwrap_or_default();
+            named.insert(name.to_string(), Value::String(value));
+        }
+    }
+    Ok(Value::Dictionary(Shared::new(named)))
+}
+
+/// replace every match; the replacement may refer to groups as `$1` or `${name}`
+pub fn replace(args: &[Value]) -> Result<Value, String> {
+    let (text, regex) = text_and_pattern("__pattern_replace", args, 3)?;
+    let replacement = match &args[2] {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    Ok(Value::String(regex.replace_all(text, replacement.as_str()).into_owned()))
+}
@@ -1,5 +1,4 @@
-use crate::types::Value;
-use std::sync::{Arc, Mutex};
+use crate::types::{Shared, Value};
 
 pub fn split(args: &[Value]) -> Result<Value, String> {
     if args.len() != 2 {
@@ -20,7 +19,7 @@ pub fn split(args: &[Value]) -> Result<Value, String> {
         .split(delim)
         .map(|s| Value::String(s.to_string()))
         .collect();
-    Ok(Value::List(Arc::new(Mutex::new(parts))))
+    Ok(Value::List(Shared::new(parts)))
 }
 
 pub fn trim(args: &[Value]) -> Result<Value, String> {
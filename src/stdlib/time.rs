@@ -0,0 +1,325 @@
+use crate::ast::Operator;
+use crate::types::{Shared, Value};
+use std::collections::HashMap;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// source of the current time; the evaluator never reads the system clock directly
+/// so that runs can be frozen with a fixed clock
+pub trait Clock: Send + Sync {
+    /// seconds since the unix epoch, UTC
+    fn now(&self) -> i64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// a clock that always reports the same instant (`layman run --fixed-time`)
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.0
+    }
+}
+
+pub fn start_of_day(seconds: i64) -> i64 {
+    seconds - seconds.rem_euclid(SECONDS_PER_DAY)
+}
+
+/// "now", or "today" (midnight of the current day), for a name that is not a variable in scope
+pub fn read_clock(name: &str, clock: &dyn Clock) -> Option<Value> {
+    match name.to_lowercase().as_str() {
+        "now" => Some(Value::Date(clock.now())),
+        "today" => Some(Value::Date(start_of_day(clock.now()))),
+        _ => None,
+    }
+}
+
+// days since 1970-01-01 for a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        _ => 28,
+    }
+}
+
+struct Parts {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    weekday: usize, // 0 = monday
+}
+
+fn split(seconds: i64) -> Parts {
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let rest = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    Parts {
+        year,
+        month,
+        day,
+        hour: rest / 3600,
+        minute: rest % 3600 / 60,
+        second: rest % 60,
+        // 1970-01-01 was a thursday
+        weekday: (days + 3).rem_euclid(7) as usize,
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// parse `YYYY-MM-DD`, optionally followed by `THH:MM[:SS]` and a trailing `Z`
+pub fn parse_iso(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let text = text.strip_suffix('Z').unwrap_or(text);
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((d, t)) => (d, Some(t)),
+        None => (text, None),
+    };
+    let number = |s: &str, len: usize| -> Option<i64> {
+        if s.len() == len && s.chars().all(|c| c.is_ascii_digit()) { s.parse().ok() } else { None }
+    };
+    let mut date_parts = date.split('-');
+    let year = number(date_parts.next()?, 4)?;
+    let month = number(date_parts.next()?, 2)?;
+    let day = number(date_parts.next()?, 2)?;
+    if date_parts.next().is_some() || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut seconds_of_day = 0;
+    if let Some(time) = time {
+        let mut time_parts = time.split(':');
+        let hour = number(time_parts.next()?, 2)?;
+        let minute = number(time_parts.next()?, 2)?;
+        let second = match time_parts.next() {
+            Some(s) => number(s, 2)?,
+            None => 0,
+        };
+        if time_parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        seconds_of_day = hour * 3600 + minute * 60 + second;
+    }
+    Some(days_from_civil(year, month, day) * SECONDS_PER_DAY + seconds_of_day)
+}
+
+/// dates at midnight print as `YYYY-MM-DD`, other instants as `YYYY-MM-DDTHH:MM:SSZ`
+pub fn format_iso(seconds: i64) -> String {
+    if seconds.rem_euclid(SECONDS_PER_DAY) == 0 {
+        format_pattern(seconds, "YYYY-MM-DD")
+    } else {
+        format_pattern(seconds, "YYYY-MM-DDThh:mm:ssZ")
+    }
+}
+
+/// replace YYYY, MM, DD, hh, mm, ss, Month and Weekday in `pattern`; other text is kept as is
+pub fn format_pattern(seconds: i64, pattern: &str) -> String {
+    let p = split(seconds);
+    let tokens: [(&str, String); 8] = [
+        ("Weekday", WEEKDAYS[p.weekday].to_string()),
+        ("Month", MONTHS[(p.month - 1) as usize].to_string()),
+        ("YYYY", format!("{:04}", p.year)),
+        ("MM", format!("{:02}", p.month)),
+        ("DD", format!("{:02}", p.day)),
+        ("hh", format!("{:02}", p.hour)),
+        ("mm", format!("{:02}", p.minute)),
+        ("ss", format!("{:02}", p.second)),
+    ];
+    let mut out = String::new();
+    let mut rest = pattern;
+    'outer: while !rest.is_empty() {
+        for (token, value) in &tokens {
+            if let Some(after) = rest.strip_prefix(token) {
+                out.push_str(value);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        let ch = rest.chars().next().unwrap();
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
+
+/// seconds in one `unit` (singular or plural): second, minute, hour, day or week
+pub fn unit_seconds(unit: &str) -> Option<i64> {
+    let unit = unit.to_lowercase();
+    match unit.strip_suffix('s').unwrap_or(&unit) {
+        "second" => Some(1),
+        "minute" => Some(60),
+        "hour" => Some(3600),
+        "day" => Some(SECONDS_PER_DAY),
+        "week" => Some(7 * SECONDS_PER_DAY),
+        _ => None,
+    }
+}
+
+/// "1 day 2 hours", "90 seconds" style text; weeks are shown as days
+pub fn describe_duration(seconds: i64) -> String {
+    if seconds == 0 {
+        return "0 seconds".to_string();
+    }
+    let sign = if seconds < 0 { "-" } else { "" };
+    let mut rest = seconds.abs();
+    let mut parts = Vec::new();
+    for (name, size) in [("day", SECONDS_PER_DAY), ("hour", 3600), ("minute", 60), ("second", 1)] {
+        let count = rest / size;
+        rest %= size;
+        if count > 0 {
+            parts.push(format!("{} {}{}", count, name, if count == 1 { "" } else { "s" }));
+        }
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// date and duration arithmetic and comparisons; `None` when neither operand is a
+/// date or duration (or the operator is not arithmetic), so normal rules apply
+pub fn apply_operator(op: &Operator, left: &Value, right: &Value) -> Option<Result<Value, String>> {
+    use Value::{Date, Duration, Number};
+    let is_time = |v: &Value| matches!(v, Date(_) | Duration(_));
+    if !(is_time(left) || is_time(right)) || matches!(left, Value::String(_)) || matches!(right, Value::String(_)) {
+        return None;
+    }
+    let result = match (op, left, right) {
+        (Operator::Plus, Date(d), Duration(s)) | (Operator::Plus, Duration(s), Date(d)) => Ok(Date(d + s)),
+        (Operator::Minus, Date(d), Duration(s)) => Ok(Date(d - s)),
+        (Operator::Minus, Date(a), Date(b)) => Ok(Duration(a - b)),
+        (Operator::Plus, Duration(a), Duration(b)) => Ok(Duration(a + b)),
+        (Operator::Minus, Duration(a), Duration(b)) => Ok(Duration(a - b)),
+        (Operator::Times, Duration(s), Number(n)) | (Operator::Times, Number(n), Duration(s)) => {
+            Ok(Duration((*s as f64 * n).round() as i64))
+        }
+        (Operator::DividedBy, Duration(_), Number(n)) if *n == 0.0 => Err("Division by zero".to_string()),
+        (Operator::DividedBy, Duration(s), Number(n)) => Ok(Duration((*s as f64 / n).round() as i64)),
+        (Operator::GreaterThan | Operator::LessThan | Operator::GreaterThanOrEqual | Operator::LessThanOrEqual, a, b) => {
+            let (a, b) = match (a, b) {
+                (Date(a), Date(b)) | (Duration(a), Duration(b)) => (a, b),
+                _ => return Some(Err(format!("Cannot compare {} with {}", kind(a), kind(b)))),
+            };
+            Ok(Value::Bool(match op {
+                Operator::GreaterThan => a > b,
+                Operator::LessThan => a < b,
+                Operator::GreaterThanOrEqual => a >= b,
+                _ => a <= b,
+            }))
+        }
+        (Operator::Equals | Operator::NotEquals | Operator::And | Operator::Or, _, _) => return None,
+        _ => Err(format!("Cannot combine {} and {} with this operator", kind(left), kind(right))),
+    };
+    Some(result)
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Date(_) => "a date",
+        Value::Duration(_) => "a duration",
+        Value::Number(_) => "a number",
+        _ => "this value",
+    }
+}
+
+fn date_arg(name: &str, args: &[Value], index: usize) -> Result<i64, String> {
+    match args.get(index) {
+        Some(Value::Date(s)) => Ok(*s),
+        _ => Err(format!("{} expects a date", name)),
+    }
+}
+
+fn text_arg<'a>(name: &str, args: &'a [Value], index: usize, what: &str) -> Result<&'a str, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("{} expects {} as text", name, what)),
+    }
+}
+
+pub fn parse_date(args: &[Value]) -> Result<Value, String> {
+    let text = text_arg("__time_parse_date", args, 0, "the date")?;
+    parse_iso(text)
+        .map(Value::Date)
+        .ok_or_else(|| format!("Cannot parse date \"{}\": expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ", text))
+}
+
+pub fn format_date(args: &[Value]) -> Result<Value, String> {
+    let date = date_arg("__time_format_date", args, 0)?;
+    let pattern = text_arg("__time_format_date", args, 1, "the pattern")?;
+    Ok(Value::String(format_pattern(date, pattern)))
+}
+
+/// dictionary with year, month, day, hour, minute, second and weekday (a name)
+pub fn date_parts(args: &[Value]) -> Result<Value, String> {
+    let p = split(date_arg("__time_date_parts", args, 0)?);
+    let mut map = HashMap::new();
+    map.insert("year".to_string(), Value::Number(p.year as f64));
+    map.insert("month".to_string(), Value::Number(p.month as f64));
+    map.insert("day".to_string(), Value::Number(p.day as f64));
+    map.insert("hour".to_string(), Value::Number(p.hour as f64));
+    map.insert("minute".to_string(), Value::Number(p.minute as f64));
+    map.insert("second".to_string(), Value::Number(p.second as f64));
+    map.insert("weekday".to_string(), Value::String(WEEKDAYS[p.weekday].to_string()));
+    Ok(Value::Dictionary(Shared::new(map)))
+}
+
+pub fn duration(args: &[Value]) -> Result<Value, String> {
+    let amount = match args.first() {
+        Some(Value::Number(n)) => *n,
+        _ => return Err("a duration needs a number of units".to_string()),
+    };
+    let unit = text_arg("__time_duration", args, 1, "the unit")?;
+    let size = unit_seconds(unit)
+        .ok_or_else(|| format!("Unknown time unit '{}' (expected seconds, minutes, hours, days or weeks)", unit))?;
+    Ok(Value::Duration((amount * size as f64).round() as i64))
+}
+
+/// length of a duration counted in `unit`, possibly fractional
+pub fn duration_in(args: &[Value]) -> Result<Value, String> {
+    let seconds = match args.first() {
+        Some(Value::Duration(s)) => *s,
+        _ => return Err("__time_duration_in expects a duration".to_string()),
+    };
+    let unit = text_arg("__time_duration_in", args, 1, "the unit")?;
+    let size = unit_seconds(unit)
+        .ok_or_else(|| format!("Unknown time unit '{}' (expected seconds, minutes, hours, days or weeks)", unit))?;
+    Ok(Value::Number(seconds as f64 / size as f64))
+}
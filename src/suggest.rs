@@ -0,0 +1,79 @@
+// suggest - "did you mean" hints for names that do not exist
+// compares a misspelt name with the names that do, and recognises words brought over from other languages
+
+use crate::lexer::KEYWORDS;
+
+/// words from other languages and how layman says them
+const PHRASES: &[(&str, &str)] = &[
+    ("let", "the variable <name> is <value>"),
+    ("var", "the variable <name> is <value>"),
+    ("const", "the constant <name> is <value>"),
+    ("def", "define function <name> that takes ..."),
+    ("fn", "define function <name> that takes ..."),
+    ("func", "define function <name> that takes ..."),
+    ("elif", "otherwise if"),
+    ("elsif", "otherwise if"),
+    ("elseif", "otherwise if"),
+    ("println", "print"),
+    ("printf", "print"),
+    ("echo", "print"),
+    ("puts", "print"),
+    ("null", "nothing"),
+    ("nil", "nothing"),
+    ("none", "nothing"),
+];
+
+/// the note for an error about `name`: how layman phrases it when it is a word from another
+/// language, otherwise the closest of `candidates` (or of the keywords). None when nothing is close
+pub fn hint<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let lower = name.to_lowercase();
+    if let Some((_, phrase)) = PHRASES.iter().find(|(word, _)| *word == lower) {
+        return Some(format!("layman writes this as `{}`", phrase));
+    }
+    // short words are a single edit away from too many keywords to guess between
+    let keywords = || if name.chars().count() > 3 { closest(name, KEYWORDS.iter().copied()) } else { None };
+    closest(name, candidates).or_else(keywords).map(|suggestion| format!("did you mean '{}'?", suggestion))
+}
+
+/// the candidate nearest to `name`, if it is close enough to be a typo of it. ties go to the
+/// alphabetically first candidate so the hint does not depend on hash order
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    // one mistake in a short name, roughly one in three in a longer one;
+    // a single letter is one edit from every other name
+    let length = name.chars().count();
+    if length < 2 {
+        return None;
+    }
+    let limit = (length / 3).max(1);
+    let lower = name.to_lowercase();
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name && !candidate.starts_with("__"))
+        .map(|candidate| (distance(&lower, &candidate.to_lowercase()), candidate))
+        .filter(|(d, _)| *d <= limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// edits (insert, delete, replace, or swap two neighbours) to turn one word into the other
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j] is the distance between the first i characters of a and the first j of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    rows[0] = (0..=b.len()).collect();
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,147 @@
+// trace - `layman run --trace`: narrate a program as it runs
+//
+// every statement is printed with its file and line, followed by what it did: the variables it
+// changed, the functions it called and what they returned, and which way each `if` went.
+// the trace goes to stderr so the program's own output stays on stdout
+
+use crate::ast::{Location, Node};
+use crate::evaluator::hook::{describe, ExecutionHook};
+use crate::types::{Environment, Value};
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum TraceFormat {
+    /// sentences, indented by call depth
+    English,
+    /// one JSON object per line
+    Json,
+}
+
+pub struct Tracer {
+    format: TraceFormat,
+    /// how many function calls deep the program is
+    depth: Mutex<usize>,
+    /// the lines of each file, to show the statement being run
+    sources: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl Tracer {
+    pub fn new(format: TraceFormat) -> Self {
+        Tracer { format, depth: Mutex::new(0), sources: Mutex::new(HashMap::new()) }
+    }
+
+    /// write one English line, `extra` levels past the current call depth
+    fn say(&self, extra: usize, text: &str) {
+        let indent = "  ".repeat(*self.depth.lock().unwrap() + extra);
+        let _ = writeln!(io::stderr(), "{}{}", indent, text);
+    }
+
+    fn emit(&self, event: Json) {
+        let _ = writeln!(io::stderr(), "{}", event);
+    }
+
+    fn source_line(&self, location: &Location) -> String {
+        let mut sources = self.sources.lock().unwrap();
+        let lines = sources.entry(location.file.clone()).or_insert_with(|| {
+            fs::read_to_string(&location.file).map(|text| text.lines().map(str::to_string).collect()).unwrap_or_default()
+        });
+        lines.get(location.line.wrapping_sub(1)).map(|line| line.trim().to_string()).unwrap_or_default()
+    }
+}
+
+impl ExecutionHook for Tracer {
+    fn before_statement(&self, _statement: &Node, location: &Location, _environment: &Arc<Environment>) {
+        let text = self.source_line(location);
+        match self.format {
+            TraceFormat::English => self.say(0, &format!("{}:{}: {}", location.file, location.line, text)),
+            TraceFormat::Json => self.emit(json!({
+                "event": "statement",
+                "file": location.file,
+                "line": location.line,
+                "text": text,
+            })),
+        }
+    }
+
+    fn enter_function(&self, name: &str, arguments: &[(String, Value)]) {
+        match self.format {
+            TraceFormat::English => {
+                let given: Vec<String> = arguments.iter().map(|(parameter, value)| format!("{} = {}", parameter, describe(value))).collect();
+                if given.is_empty() {
+                    self.say(1, &format!("calling {}", name));
+                } else {
+                    self.say(1, &format!("calling {} with {}", name, given.join(", ")));
+                }
+            }
+            TraceFormat::Json => {
+                let arguments: Vec<Json> =
+                    arguments.iter().map(|(parameter, value)| json!({ "name": parameter, "value": to_json(value) })).collect();
+                self.emit(json!({ "event": "call", "function": name, "arguments": arguments }));
+            }
+        }
+        *self.depth.lock().unwrap() += 1;
+    }
+
+    fn exit_function(&self, name: &str, result: Option<&Value>) {
+        {
+            let mut depth = self.depth.lock().unwrap();
+            *depth = depth.saturating_sub(1);
+        }
+        match (self.format, result) {
+            (TraceFormat::English, Some(Value::Void)) => self.say(1, &format!("{} finished", name)),
+            (TraceFormat::English, Some(value)) => self.say(1, &format!("{} returned {}", name, describe(value))),
+            (TraceFormat::English, None) => self.say(1, &format!("{} stopped with an error", name)),
+            (TraceFormat::Json, Some(value)) => self.emit(json!({ "event": "return", "function": name, "value": to_json(value) })),
+            (TraceFormat::Json, None) => self.emit(json!({ "event": "return", "function": name, "error": true })),
+        }
+    }
+
+    fn variable_changed(&self, name: &str, old: Option<&Value>, new: &Value) {
+        match (self.format, old) {
+            (TraceFormat::English, Some(old)) => {
+                self.say(1, &format!("{} changes from {} to {}", name, describe(old), describe(new)))
+            }
+            (TraceFormat::English, None) => self.say(1, &format!("{} is now {}", name, describe(new))),
+            (TraceFormat::Json, old) => self.emit(json!({
+                "event": "variable",
+                "name": name,
+                "old": old.map(to_json),
+                "new": to_json(new),
+            })),
+        }
+    }
+
+    fn branch_taken(&self, location: &Location, condition: bool, has_otherwise: bool) {
+        let (runs, sentence) = match (condition, has_otherwise) {
+            (true, _) => ("if", "the condition is true, so the `if` branch runs"),
+            (false, true) => ("otherwise", "the condition is false, so the `otherwise` branch runs"),
+            (false, false) => ("nothing", "the condition is false, so the block is skipped"),
+        };
+        match self.format {
+            TraceFormat::English => self.say(1, sentence),
+            TraceFormat::Json => self.emit(json!({
+                "event": "branch",
+                "file": location.file,
+                "line": location.line,
+                "condition": condition,
+                "runs": runs,
+            })),
+        }
+    }
+}
+
+/// numbers, text and true/false as themselves; other values as they print
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => json!(*n as i64),
+        Value::Number(n) => json!(n),
+        Value::String(text) => json!(text),
+        Value::Bool(b) => json!(b),
+        Value::Void | Value::Nothing => Json::Null,
+        value => json!(value.to_string()),
+    }
+}
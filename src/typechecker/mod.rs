@@ -3,8 +3,9 @@
 // follows principles: readability, determinism, clear error messages
 
 use crate::ast::*;
-use crate::types::RuntimeType;
-use crate::{lexer, parser};
+use crate::diagnostics::codes;
+use crate::types::{RuntimeType, Value, DICTIONARY_METHODS, LIST_METHODS};
+use crate::{lexer, parser, suggest};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::fs;
@@ -16,6 +17,8 @@ pub struct TypeContext {
     functions: HashMap<String, FunctionSignature>,
     classes: HashMap<String, ClassDefinition>,
     types: HashMap<String, TypeDefinition>,
+    /// variables whose contents cannot change (constants and frozen copies), with how errors name them
+    immutable: HashMap<String, String>,
     parent: Option<Box<TypeContext>>,
 }
 
@@ -47,6 +50,7 @@ impl TypeContext {
             functions: HashMap::new(),
             classes: HashMap::new(),
             types: HashMap::new(),
+            immutable: HashMap::new(),
             parent: None,
         }
     }
@@ -57,6 +61,7 @@ impl TypeContext {
             functions: HashMap::new(),
             classes: HashMap::new(),
             types: HashMap::new(),
+            immutable: HashMap::new(),
             parent: Some(parent),
         }
     }
@@ -131,9 +136,68 @@ impl TypeContext {
     pub fn set_class(&mut self, def: ClassDefinition) {
         self.classes.insert(def.name.clone(), def);
     }
+
+    /// every variable and function name visible from this scope
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.variables.keys().chain(self.functions.keys()).cloned().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
+
+    /// every class and type name visible from this scope
+    pub fn type_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.classes.keys().chain(self.types.keys()).cloned().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.type_names());
+        }
+        names
+    }
+
+    /// how to name the variable in an error if its contents are immutable; a variable of the
+    /// same name in a nearer scope (such as a parameter) hides an outer constant
+    pub fn get_immutable_label(&self, name: &str) -> Option<String> {
+        if self.variables.contains_key(name) {
+            self.immutable.get(name).cloned()
+        } else if let Some(parent) = &self.parent {
+            parent.get_immutable_label(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_immutable_label(&mut self, name: String, label: Option<String>) {
+        match label {
+            Some(label) => self.immutable.insert(name, label),
+            None => self.immutable.remove(&name),
+        };
+    }
 }
 
 // type checking result
+/// what kind of name a symbol is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function,
+    Class,
+}
+
+/// a name declared by the checked program, with where it was declared and its type;
+/// editor tooling uses these for hover, go-to-definition and completion
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+    pub ty: RuntimeType,
+    /// where the function or method it was declared in starts; None at the top level
+    pub function: Option<Location>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum TypeCheckResult {
@@ -144,12 +208,22 @@ pub enum TypeCheckResult {
 pub struct TypeChecker {
     context: TypeContext,
     errors: Vec<TypeError>,
+    /// variables narrowed from `maybe T` to `T` by an enclosing `if x exists`, with their declared type
+    narrowed: HashMap<String, RuntimeType>,
+    /// every declaration seen so far, in the order they were checked
+    symbols: Vec<Symbol>,
+    /// the function or method whose body is being checked
+    function: Option<Location>,
 }
 
 #[derive(Debug, Clone)]
 pub struct TypeError {
+    /// one of `diagnostics::codes`
+    pub code: &'static str,
     pub location: Location,
     pub message: String,
+    /// how to fix it, e.g. the name a misspelt one was probably meant to be
+    pub note: Option<String>,
 }
 
 impl TypeChecker {
@@ -157,6 +231,9 @@ impl TypeChecker {
         Self {
             context: TypeContext::new(),
             errors: Vec::new(),
+            narrowed: HashMap::new(),
+            symbols: Vec::new(),
+            function: None,
         }
     }
     
@@ -177,6 +254,85 @@ impl TypeChecker {
         }
     }
     
+    // type check one entry of an interactive session against everything accepted before it;
+    // an entry with errors leaves the context as it was
+    pub fn check_entry(&mut self, program: &Program) -> Result<(), Vec<TypeError>> {
+        if self.context.get_function_signature("print").is_none() {
+            self.register_stdlib();
+        }
+        let saved = self.context.clone();
+        self.errors.clear();
+        self.narrowed.clear();
+        self.function = None;
+
+        let mut result = Ok(());
+        for stmt in &program.statements {
+            if let Err(errors) = self.check_statement(stmt) {
+                result = Err(errors);
+                break;
+            }
+        }
+        if result.is_ok() && !self.errors.is_empty() {
+            result = Err(self.errors.clone());
+        }
+        if result.is_err() {
+            self.context = saved;
+        }
+        result
+    }
+
+    // the type of an expression in the current context, as it is written in error messages
+    pub fn describe_type(&mut self, expr: &Node) -> Result<String, Vec<TypeError>> {
+        if self.context.get_function_signature("print").is_none() {
+            self.register_stdlib();
+        }
+        let saved = self.context.clone();
+        self.errors.clear();
+        let result = self.check_expression(expr);
+        self.context = saved;
+        match result {
+            Ok(_) if !self.errors.is_empty() => Err(self.errors.clone()),
+            Ok(ty) => Ok(self.type_to_string(&ty)),
+            Err(errors) => Err(errors),
+        }
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    // a top-level function with the types worked out for it
+    pub fn function_signature(&self, name: &str) -> Option<FunctionSignature> {
+        self.context.get_function_signature(name)
+    }
+
+    // a class with the members it inherits from its parents
+    pub fn class_members(&self, name: &str) -> Option<ClassDefinition> {
+        let mut class = self.context.get_class(name)?.clone();
+        let mut parent = class.parent.clone();
+        while let Some(parent_name) = parent {
+            let Some(parent_class) = self.context.get_class(&parent_name) else { break };
+            for (prop, ty) in &parent_class.properties {
+                class.properties.entry(prop.clone()).or_insert_with(|| ty.clone());
+            }
+            for (method, sig) in &parent_class.methods {
+                class.methods.entry(method.clone()).or_insert_with(|| sig.clone());
+            }
+            parent = parent_class.parent.clone();
+        }
+        Some(class)
+    }
+
+    fn record_symbol(&mut self, name: &str, kind: SymbolKind, location: &Location, ty: RuntimeType) {
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            location: location.clone(),
+            ty,
+            function: self.function.clone(),
+        });
+    }
+
     fn register_stdlib(&mut self) {
         // register print function
         self.context.set_function_signature(FunctionSignature {
@@ -266,6 +422,282 @@ impl TypeChecker {
             return_type: RuntimeType::Void,
         });
 
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_append_file".to_string(),
+            parameters: vec![
+                ("path".to_string(), RuntimeType::String),
+                ("content".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_exists".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Bool,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_list_directory".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::List(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_make_directory".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_remove_directory".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_delete_file".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_copy_file".to_string(),
+            parameters: vec![
+                ("from".to_string(), RuntimeType::String),
+                ("to".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_move_file".to_string(),
+            parameters: vec![
+                ("from".to_string(), RuntimeType::String),
+                ("to".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_join_path".to_string(),
+            parameters: vec![
+                ("base".to_string(), RuntimeType::String),
+                ("part".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_file_info".to_string(),
+            parameters: vec![("path".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::Any),
+            },
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__time_parse_date".to_string(),
+            parameters: vec![("text".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Date,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__time_format_date".to_string(),
+            parameters: vec![
+                ("date".to_string(), RuntimeType::Date),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__time_date_parts".to_string(),
+            parameters: vec![("date".to_string(), RuntimeType::Date)],
+            return_type: RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::Any),
+            },
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__time_duration".to_string(),
+            parameters: vec![
+                ("amount".to_string(), RuntimeType::Number),
+                ("unit".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Duration,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__time_duration_in".to_string(),
+            parameters: vec![
+                ("duration".to_string(), RuntimeType::Duration),
+                ("unit".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Number,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__program_arguments".to_string(),
+            parameters: vec![],
+            return_type: RuntimeType::List(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__env_get".to_string(),
+            parameters: vec![("name".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Maybe(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_ask".to_string(),
+            parameters: vec![("prompt".to_string(), RuntimeType::Any)],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_read_all_input".to_string(),
+            parameters: vec![],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__io_input_lines".to_string(),
+            parameters: vec![],
+            return_type: RuntimeType::List(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "exit".to_string(),
+            parameters: vec![("code".to_string(), RuntimeType::Number)],
+            return_type: RuntimeType::Void,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__csv_parse_rows".to_string(),
+            parameters: vec![("text".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::List(Box::new(RuntimeType::List(Box::new(RuntimeType::String)))),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__csv_parse_records".to_string(),
+            parameters: vec![("text".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::List(Box::new(RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::String),
+            })),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__csv_format_rows".to_string(),
+            parameters: vec![("rows".to_string(), RuntimeType::Any)],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__csv_format_records".to_string(),
+            parameters: vec![
+                ("records".to_string(), RuntimeType::Any),
+                ("columns".to_string(), RuntimeType::Any)
+            ],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_matches".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Bool,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_find_all".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::List(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_find_groups".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::List(Box::new(RuntimeType::String)),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_find_all_groups".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::List(Box::new(RuntimeType::List(Box::new(RuntimeType::String)))),
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_find_named_groups".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::String),
+            },
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__pattern_replace".to_string(),
+            parameters: vec![
+                ("text".to_string(), RuntimeType::String),
+                ("pattern".to_string(), RuntimeType::String),
+                ("replacement".to_string(), RuntimeType::String)
+            ],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__http_request".to_string(),
+            parameters: vec![
+                ("method".to_string(), RuntimeType::String),
+                ("url".to_string(), RuntimeType::String),
+                ("body".to_string(), RuntimeType::Any),
+                ("headers".to_string(), RuntimeType::Any)
+            ],
+            return_type: RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::Any),
+            },
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__json_parse".to_string(),
+            parameters: vec![("text".to_string(), RuntimeType::String)],
+            return_type: RuntimeType::Any,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__json_format".to_string(),
+            parameters: vec![("value".to_string(), RuntimeType::Any)],
+            return_type: RuntimeType::String,
+        });
+
+        self.context.set_function_signature(FunctionSignature {
+            name: "__dict_from_pairs".to_string(),
+            parameters: vec![("pairs".to_string(), RuntimeType::List(Box::new(RuntimeType::Any)))],
+            return_type: RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::Any),
+            },
+        });
+
         self.context.set_function_signature(FunctionSignature {
             name: "__str_split".to_string(),
             parameters: vec![
@@ -301,12 +733,22 @@ impl TypeChecker {
                 // check expression type
                 let expr_type = self.check_expression(&assign.expression)?;
                 
+                // a narrowed variable still accepts anything its declared maybe type does
+                let declared_type = self.narrowed.get(&assign.identifier).cloned();
+                if let (Some(declared), Some(current)) = (&declared_type, self.context.get_variable_type(&assign.identifier)) {
+                    if !self.is_compatible(&expr_type, &current) || matches!(expr_type, RuntimeType::Nothing) {
+                        // the new value may be nothing again
+                        let is_mut = self.context.get_variable_mutability(&assign.identifier).unwrap_or(true);
+                        self.context.set_variable_type(assign.identifier.clone(), declared.clone(), is_mut);
+                    }
+                }
+                
                 // if variable already exists, check compatibility
             if let Some(existing_type) = self.context.get_variable_type(&assign.identifier) {
                 // Check mutability
                 if let Some(is_mut) = self.context.get_variable_mutability(&assign.identifier) {
                      if !is_mut && existing_type != RuntimeType::Void {
-                         self.add_error(&assign.location, format!("Cannot reassign constant '{}'", assign.identifier));
+                         self.add_error(codes::IMMUTABLE, &assign.location, format!("Cannot reassign constant '{}'", assign.identifier));
                      }
                 }
 
@@ -315,7 +757,7 @@ impl TypeChecker {
                     let is_mut = self.context.get_variable_mutability(&assign.identifier).unwrap_or(true);
                     self.context.set_variable_type(assign.identifier.clone(), expr_type.clone(), is_mut);
                 } else if !self.is_compatible(&expr_type, &existing_type) {
-                        self.add_error(&assign.location, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &assign.location, format!(
                             "cannot assign {} to variable '{}' which has type {}",
                             self.type_to_string(&expr_type),
                             assign.identifier,
@@ -325,6 +767,13 @@ impl TypeChecker {
                 } else {
                     // new variable: infer type from expression
                     self.context.set_variable_type(assign.identifier.clone(), expr_type.clone(), assign.is_mutable);
+                    let kind = if assign.is_mutable { SymbolKind::Variable } else { SymbolKind::Constant };
+                    self.record_symbol(&assign.identifier, kind, &assign.location, expr_type.clone());
+                }
+                // a constant cannot be reassigned, so only new constants and rebound variables change
+                if assign.is_mutable || self.context.get_immutable_label(&assign.identifier).is_none() {
+                    let label = self.immutable_label(&assign.identifier, &assign.expression, assign.is_mutable);
+                    self.context.set_immutable_label(assign.identifier.clone(), label);
                 }
                 
                 Ok(expr_type)
@@ -338,7 +787,7 @@ impl TypeChecker {
                     let annotated_runtime = self.ast_type_to_runtime_type(annotated_type);
                     
                     if !self.is_compatible(&init_type, &annotated_runtime) {
-                        self.add_error(&decl.location, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &decl.location, format!(
                             "initial value has type {} but variable '{}' is declared as {}",
                             self.type_to_string(&init_type),
                             decl.name,
@@ -353,6 +802,10 @@ impl TypeChecker {
                 
                 // register variable in context
                 self.context.set_variable_type(decl.name.clone(), var_type.clone(), decl.is_mutable);
+                let kind = if decl.is_mutable { SymbolKind::Variable } else { SymbolKind::Constant };
+                self.record_symbol(&decl.name, kind, &decl.location, var_type.clone());
+                let label = self.immutable_label(&decl.name, &decl.value, decl.is_mutable);
+                self.context.set_immutable_label(decl.name.clone(), label);
                 Ok(var_type)
             }
             
@@ -361,13 +814,17 @@ impl TypeChecker {
                 let cond_type = self.check_expression(&cond.condition)?;
                 if !self.is_boolean_compatible(&cond_type) {
                     let cond_loc = cond.condition.location();
-                    self.add_error(&cond_loc, format!(
+                    self.add_error(codes::TYPE_MISMATCH, &cond_loc, format!(
                         "condition in if statement must be boolean, got {}",
                         self.type_to_string(&cond_type)
                     ));
                 }
                 
-                self.check_statement(&cond.then_branch)?;
+                // "if x exists" lets the then branch use a maybe T as a T
+                let narrowed = self.narrow(&cond.condition);
+                let then_result = self.check_statement(&cond.then_branch);
+                self.restore_narrowed(narrowed);
+                then_result?;
                 if let Some(else_branch) = &cond.else_branch {
                     self.check_statement(else_branch)?;
                 }
@@ -390,6 +847,7 @@ impl TypeChecker {
                             if let Some(iter_name) = &loop_stmt.iterator {
                                 // register iterator in loop body scope?
                                 // For now, register in current scope (simplified)
+                                self.record_symbol(iter_name, SymbolKind::Variable, &loop_stmt.location, iter_type.clone());
                                 self.context.set_variable_type(iter_name.clone(), iter_type, false);
                             }
                         }
@@ -402,7 +860,7 @@ impl TypeChecker {
                             let cond_type = self.check_expression(condition)?;
                             if !self.is_boolean_compatible(&cond_type) {
                                 let cond_loc = condition.location();
-                                self.add_error(&cond_loc, format!(
+                                self.add_error(codes::TYPE_MISMATCH, &cond_loc, format!(
                                     "while loop condition must be boolean, got {}",
                                     self.type_to_string(&cond_type)
                                 ));
@@ -433,6 +891,7 @@ impl TypeChecker {
                 let mut func_context = TypeContext::with_parent(Box::new(self.context.clone()));
                 
                 // register parameters in function context
+                let outer_function = self.function.replace(func.location.clone());
                 let mut param_types = Vec::new();
                 for param in &func.parameters {
                     let param_type = if let Some(annotated) = &param.type_annotation {
@@ -442,6 +901,7 @@ impl TypeChecker {
                         RuntimeType::Any
                     };
                     param_types.push((param.name.clone(), param_type.clone()));
+                    self.record_symbol(&param.name, SymbolKind::Parameter, &func.location, param_type.clone());
                     func_context.set_variable_type(param.name.clone(), param_type, false);
                 }
                 
@@ -451,6 +911,7 @@ impl TypeChecker {
                 let old_context = std::mem::replace(&mut self.context, func_context);
                 let body_type = self.check_statement(&func.body)?;
                 self.context = old_context;
+                self.function = outer_function;
                 
                 // check return type matches annotation if present
                 let expected_return = if let Some(annotated_return) = &func.return_type {
@@ -461,6 +922,11 @@ impl TypeChecker {
                 };
                 
                 // register function in context
+                let function_type = RuntimeType::Function {
+                    parameters: param_types.iter().map(|(_, ty)| ty.clone()).collect(),
+                    return_type: Box::new(expected_return.clone()),
+                };
+                self.record_symbol(&func.name, SymbolKind::Function, &func.location, function_type);
                 self.context.set_function_signature(FunctionSignature {
                     name: func.name.clone(),
                     parameters: param_types,
@@ -518,6 +984,7 @@ impl TypeChecker {
                     properties,
                     methods: methods.clone(),
                 });
+                self.record_symbol(&class.name, SymbolKind::Class, &class.location, RuntimeType::Class(class.name.clone()));
 
                 // check method bodies
                 for method in &class.methods {
@@ -542,8 +1009,10 @@ impl TypeChecker {
                     
                     // check method body
                     let old_context = std::mem::replace(&mut self.context, method_context);
+                    let outer_function = self.function.replace(method.location.clone());
                     self.check_statement(&method.body)?;
                     self.context = old_context;
+                    self.function = outer_function;
                 }
                 
                 Ok(RuntimeType::Void)
@@ -602,10 +1071,11 @@ impl TypeChecker {
                                     case_context.set_variable_type(field_name.clone(), field_type.clone(), false);
                                 }
                             } else {
-                                self.add_error(&stmt.expression.location(), format!(
+                                let hint = suggest::hint(&case.variant_name, type_def.variants.keys().map(String::as_str));
+                                self.add_error_with_note(codes::UNKNOWN_NAME, &stmt.expression.location(), format!(
                                     "Variant '{}' not found in type '{}'",
                                     case.variant_name, type_name
-                                ));
+                                ), hint);
                             }
                             
                             let old_context = std::mem::replace(&mut self.context, case_context);
@@ -613,12 +1083,11 @@ impl TypeChecker {
                             self.context = old_context;
                         }
                     } else {
-                        self.add_error(&stmt.expression.location(), format!(
-                            "Unknown type '{}'", type_name
-                        ));
+                        let hint = self.type_hint(&type_name);
+                        self.add_error_with_note(codes::UNKNOWN_NAME, &stmt.expression.location(), format!("Unknown type '{}'", type_name), hint);
                     }
                 } else {
-                    self.add_error(&stmt.expression.location(), format!(
+                    self.add_error(codes::TYPE_MISMATCH, &stmt.expression.location(), format!(
                         "inspect statement requires a variant type, got {}",
                         self.type_to_string(&expr_type)
                     ));
@@ -667,6 +1136,7 @@ impl TypeChecker {
             }
             
             Node::SetStatement(set_stmt) => {
+                self.check_mutable(&set_stmt.object, &set_stmt.location);
                 // check object is an object
                 let obj_type = self.check_expression(&set_stmt.object)?;
                 
@@ -675,7 +1145,7 @@ impl TypeChecker {
                     if let Some(prop_type) = self.find_property_in_class(&class_name, &set_stmt.property) {
                         let val_type = self.check_expression(&set_stmt.value)?;
                         if !self.is_compatible(&val_type, &prop_type) {
-                            self.add_error(&set_stmt.location, format!(
+                            self.add_error(codes::TYPE_MISMATCH, &set_stmt.location, format!(
                                 "Cannot assign {} to property '{}' of type {}",
                                 self.type_to_string(&val_type),
                                 set_stmt.property,
@@ -683,19 +1153,67 @@ impl TypeChecker {
                             ));
                         }
                     } else {
-                        self.add_error(&set_stmt.location, format!("Class '{}' has no property '{}'", class_name, set_stmt.property));
+                        let hint = self.member_hint(&class_name, &set_stmt.property, false);
+                        self.add_error_with_note(codes::UNKNOWN_MEMBER, &set_stmt.location, format!("Class '{}' has no property '{}'", class_name, set_stmt.property), hint);
                     }
                 } else if let RuntimeType::Dictionary { key: _, value } = obj_type {
                     let val_type = self.check_expression(&set_stmt.value)?;
                     if !self.is_compatible(&val_type, &value) {
-                        self.add_error(&set_stmt.location, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &set_stmt.location, format!(
                             "Cannot assign {} to dictionary value of type {}",
                             self.type_to_string(&val_type),
                             self.type_to_string(&value)
                         ));
                     }
                 } else if !matches!(obj_type, RuntimeType::Any) {
-                    self.add_error(&set_stmt.location, format!("Cannot set property on non-object type {}", self.type_to_string(&obj_type)));
+                    self.add_error(codes::UNKNOWN_MEMBER, &set_stmt.location, format!("Cannot set property on non-object type {}", self.type_to_string(&obj_type)));
+                }
+                
+                Ok(RuntimeType::Void)
+            }
+            
+            Node::IndexSetStatement(stmt) => {
+                self.check_mutable(&stmt.object, &stmt.location);
+                let obj_type = self.check_expression(&stmt.object)?;
+                let index_type = self.check_expression(&stmt.index)?;
+                let val_type = self.check_expression(&stmt.value)?;
+                
+                match obj_type {
+                    RuntimeType::List(elem_type) => {
+                        if !matches!(index_type, RuntimeType::Number) {
+                            self.add_error(codes::TYPE_MISMATCH, &stmt.index.location(), format!(
+                                "List index must be a number, got {}",
+                                self.type_to_string(&index_type)
+                            ));
+                        }
+                        if !self.is_compatible(&val_type, &elem_type) {
+                            self.add_error(codes::TYPE_MISMATCH, &stmt.location, format!(
+                                "Cannot assign {} to list item of type {}",
+                                self.type_to_string(&val_type),
+                                self.type_to_string(&elem_type)
+                            ));
+                        }
+                    }
+                    RuntimeType::Dictionary { key: key_type, value: value_type } => {
+                        if !self.is_key_compatible(&index_type, &key_type) {
+                            self.add_error(codes::TYPE_MISMATCH, &stmt.index.location(), format!(
+                                "Dictionary key must be {}, got {}",
+                                self.type_to_string(&key_type),
+                                self.type_to_string(&index_type)
+                            ));
+                        }
+                        if !self.is_compatible(&val_type, &value_type) {
+                            self.add_error(codes::TYPE_MISMATCH, &stmt.location, format!(
+                                "Cannot assign {} to dictionary value of type {}",
+                                self.type_to_string(&val_type),
+                                self.type_to_string(&value_type)
+                            ));
+                        }
+                    }
+                    RuntimeType::Any => {}
+                    other => {
+                        self.add_error(codes::UNKNOWN_MEMBER, &stmt.location, format!("Type {} is not indexable", self.type_to_string(&other)));
+                    }
                 }
                 
                 Ok(RuntimeType::Void)
@@ -710,6 +1228,84 @@ impl TypeChecker {
         }
     }
     
+    /// methods of a dictionary, called as `call <method> on <dictionary> with <arguments>`
+    fn check_dictionary_method(&mut self, method_call: &MethodCall, key: &RuntimeType, value: &RuntimeType) -> Result<RuntimeType, Vec<TypeError>> {
+        let expected = match method_call.method_name.as_str() {
+            "length" | "count" | "size" | "keys" | "values" | "entries" | "clear" => 0,
+            "remove" | "contains_key" | "merge" => 1,
+            "get" => 2,
+            _ => {
+                return Err(vec![TypeError {
+                    code: codes::UNKNOWN_MEMBER,
+                    location: method_call.location.clone(),
+                    message: format!("Dictionary has no method '{}'", method_call.method_name),
+                    note: suggest::hint(&method_call.method_name, DICTIONARY_METHODS.iter().copied()),
+                }]);
+            }
+        };
+        if method_call.arguments.len() != expected {
+            self.add_error(codes::ARGUMENT_COUNT, &method_call.location, format!(
+                "Dictionary method '{}' expects {} argument(s), got {}",
+                method_call.method_name,
+                expected,
+                method_call.arguments.len()
+            ));
+        }
+        let mut arg_types = Vec::new();
+        for arg in &method_call.arguments {
+            arg_types.push(self.check_expression(arg)?);
+        }
+        // the key argument of remove, contains_key and get
+        if matches!(method_call.method_name.as_str(), "remove" | "contains_key" | "get") {
+            if let Some(key_arg) = arg_types.first() {
+                if !self.is_key_compatible(key_arg, key) {
+                    self.add_error(codes::TYPE_MISMATCH, &method_call.arguments[0].location(), format!(
+                        "Dictionary key must be {}, got {}",
+                        self.type_to_string(key),
+                        self.type_to_string(key_arg)
+                    ));
+                }
+            }
+        }
+        let dictionary = RuntimeType::Dictionary { key: Box::new(key.clone()), value: Box::new(value.clone()) };
+        Ok(match method_call.method_name.as_str() {
+            "length" | "count" | "size" => RuntimeType::Number,
+            "remove" | "clear" => RuntimeType::Void,
+            "contains_key" => RuntimeType::Bool,
+            // keys are stored as text whatever their declared type
+            "keys" => RuntimeType::List(Box::new(RuntimeType::String)),
+            "values" => RuntimeType::List(Box::new(value.clone())),
+            "entries" => {
+                let pair = if matches!(value, RuntimeType::String) { RuntimeType::String } else { RuntimeType::Any };
+                RuntimeType::List(Box::new(RuntimeType::List(Box::new(pair))))
+            }
+            "get" => {
+                if let Some(default) = arg_types.get(1) {
+                    if !self.is_compatible(default, value) {
+                        self.add_error(codes::TYPE_MISMATCH, &method_call.arguments[1].location(), format!(
+                            "Default value must be {}, got {}",
+                            self.type_to_string(value),
+                            self.type_to_string(default)
+                        ));
+                    }
+                }
+                value.clone()
+            }
+            _ => {
+                if let Some(other) = arg_types.first() {
+                    if !matches!(other, RuntimeType::Any) && !self.is_compatible(other, &dictionary) {
+                        self.add_error(codes::TYPE_MISMATCH, &method_call.arguments[0].location(), format!(
+                            "Cannot merge {} into {}",
+                            self.type_to_string(other),
+                            self.type_to_string(&dictionary)
+                        ));
+                    }
+                }
+                dictionary
+            }
+        })
+    }
+    
     fn check_expression(&mut self, expr: &Node) -> Result<RuntimeType, Vec<TypeError>> {
         match expr {
             Node::FunctionDeclaration(func) => {
@@ -750,8 +1346,10 @@ impl TypeChecker {
                 // check class exists
                 if self.context.get_class(&obj.class_name).is_none() {
                     return Err(vec![TypeError {
+                        code: codes::UNKNOWN_NAME,
                         location: obj.location.clone(),
                         message: format!("Unknown class '{}'", obj.class_name),
+                        note: self.type_hint(&obj.class_name),
                     }]);
                 }
                 
@@ -760,8 +1358,12 @@ impl TypeChecker {
             }
             
             Node::MethodCall(method_call) => {
+                if matches!(method_call.method_name.as_str(), "add" | "remove" | "clear") {
+                    self.check_mutable(&method_call.object, &method_call.object.location());
+                }
                 // check object type and method exists
                 let obj_type = self.check_expression(&method_call.object)?;
+                let obj_type = self.present_type(&method_call.object, obj_type);
                 
                 if let RuntimeType::Object(class_name) = obj_type {
                     if let Some(method_sig) = self.find_method_in_class(&class_name, &method_call.method_name) {
@@ -769,10 +1371,14 @@ impl TypeChecker {
                         return Ok(method_sig.return_type.clone());
                     } else {
                         return Err(vec![TypeError {
+                            code: codes::UNKNOWN_MEMBER,
                             location: method_call.location.clone(),
                             message: format!("Class '{}' has no method '{}'", class_name, method_call.method_name),
+                            note: self.member_hint(&class_name, &method_call.method_name, true),
                         }]);
                     }
+                } else if let RuntimeType::Dictionary { key, value } = &obj_type {
+                    return self.check_dictionary_method(method_call, key, value);
                 } else if let RuntimeType::List(elem_type) = obj_type {
                     match method_call.method_name.as_str() {
                         "add" => {
@@ -780,17 +1386,15 @@ impl TypeChecker {
                             // TODO: check argument count and type
                             return Ok(RuntimeType::Void);
                         }
-                        "remove" => {
-                            // remove(index)
-                            return Ok(RuntimeType::Void);
-                        }
-                        "size" => {
+                        "length" | "count" | "size" => {
                             return Ok(RuntimeType::Number);
                         }
                         _ => {
                             return Err(vec![TypeError {
+                                code: codes::UNKNOWN_MEMBER,
                                 location: method_call.location.clone(),
                                 message: format!("List has no method '{}'", method_call.method_name),
+                                note: suggest::hint(&method_call.method_name, LIST_METHODS.iter().copied()),
                             }]);
                         }
                     }
@@ -802,8 +1406,10 @@ impl TypeChecker {
                 }
                 
                 Err(vec![TypeError {
+                    code: codes::UNKNOWN_MEMBER,
                     location: method_call.location.clone(),
                     message: format!("Cannot call method '{}' on type {:?}", method_call.method_name, obj_type),
+                    note: None,
                 }])
             }
             Node::LiteralExpression(lit) => {
@@ -825,11 +1431,16 @@ impl TypeChecker {
                         parameters: sig.parameters.iter().map(|(_, t)| t.clone()).collect(),
                         return_type: Box::new(sig.return_type.clone()),
                     })
+                } else if matches!(var.identifier.to_lowercase().as_str(), "now" | "today") {
+                    // the clock, when no variable of that name is in scope
+                    Ok(RuntimeType::Date)
                 } else {
                     // Return error instead of Void
                     Err(vec![TypeError {
+                        code: codes::UNKNOWN_NAME,
                         location: var.location.clone(),
                         message: format!("Variable '{}' not found", var.identifier),
+                        note: self.name_hint(&var.identifier),
                     }])
                 }
             }
@@ -871,7 +1482,14 @@ impl TypeChecker {
                         let mut i = 3;
                         while i < call.arguments.len() {
                             if let Ok(next_val_type) = self.check_expression(&call.arguments[i]) {
-                                if !self.is_compatible(&next_val_type, &value_type) {
+                                // numbers are "compatible" with text, but a dictionary of both holds Any
+                                let mixes_text_and_numbers = matches!(
+                                    (&next_val_type, &value_type),
+                                    (RuntimeType::Number, RuntimeType::String) | (RuntimeType::String, RuntimeType::Number)
+                                );
+                                if mixes_text_and_numbers {
+                                    value_type = RuntimeType::Any;
+                                } else if !self.is_compatible(&next_val_type, &value_type) {
                                     if self.is_compatible(&value_type, &next_val_type) {
                                         value_type = next_val_type; // Upgrade to more general type
                                     } else {
@@ -889,6 +1507,37 @@ impl TypeChecker {
                     });
                 }
 
+                if call.function_name == "convert" && call.arguments.len() == 2 {
+                    return self.check_conversion(call);
+                }
+
+                if call.function_name.starts_with("__collections_") {
+                    return self.check_collection_call(call);
+                }
+
+                if call.function_name == "__freeze" && call.arguments.len() == 1 {
+                    return self.check_expression(&call.arguments[0]);
+                }
+
+                if call.function_name == "append" {
+                    if let Some(list) = call.arguments.first() {
+                        self.check_mutable(list, &list.location());
+                    }
+                }
+
+                if call.function_name == "__maybe_index" || call.function_name == "__maybe_property" {
+                    return self.check_maybe_lookup(call);
+                }
+
+                // literal patterns are compiled now so mistakes are reported before running
+                if call.function_name.starts_with("__pattern_") {
+                    if let Some(Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(pattern), location })) = call.arguments.get(1) {
+                        if let Err(e) = crate::stdlib::pattern::compile(pattern) {
+                            self.add_error(codes::INVALID_LITERAL, location, e);
+                        }
+                    }
+                }
+
                 // check for dot notation in function name (e.g. mu.add)
                 if call.function_name.contains('.') {
                     let parts: Vec<&str> = call.function_name.split('.').collect();
@@ -923,7 +1572,7 @@ impl TypeChecker {
                 if let Some(sig) = self.context.get_function_signature(&call.function_name) {
                     // check argument count matches
                     if call.arguments.len() != sig.parameters.len() {
-                        self.add_error(&call.location, format!(
+                        self.add_error(codes::ARGUMENT_COUNT, &call.location, format!(
                             "function '{}' expects {} arguments but got {}",
                             call.function_name,
                             sig.parameters.len(),
@@ -937,7 +1586,7 @@ impl TypeChecker {
                         if i < sig.parameters.len() {
                             let expected_type = &sig.parameters[i].1;
                             if !self.is_compatible(&arg_type, expected_type) {
-                                self.add_error(&arg.location(), format!(
+                                self.add_error(codes::TYPE_MISMATCH, &arg.location(), format!(
                                     "Argument {} of '{}' expects {}, got {}",
                                     i + 1,
                                     call.function_name,
@@ -954,7 +1603,7 @@ impl TypeChecker {
                     if let RuntimeType::Function { parameters, return_type } = var_type {
                         // check argument count matches
                         if call.arguments.len() != parameters.len() {
-                            self.add_error(&call.location, format!(
+                            self.add_error(codes::ARGUMENT_COUNT, &call.location, format!(
                                 "function variable '{}' expects {} arguments but got {}",
                                 call.function_name,
                                 parameters.len(),
@@ -968,7 +1617,7 @@ impl TypeChecker {
                             if i < parameters.len() {
                                 let expected_type = &parameters[i];
                                 if !self.is_compatible(&arg_type, expected_type) {
-                                    self.add_error(&arg.location(), format!(
+                                    self.add_error(codes::TYPE_MISMATCH, &arg.location(), format!(
                                         "Argument {} of '{}' expects {}, got {}",
                                         i + 1,
                                         call.function_name,
@@ -985,14 +1634,18 @@ impl TypeChecker {
                          Ok(RuntimeType::Any)
                     } else {
                         Err(vec![TypeError {
+                            code: codes::TYPE_MISMATCH,
                             location: call.location.clone(),
                             message: format!("Variable '{}' is not a function (type: {})", call.function_name, self.type_to_string(&var_type)),
+                            note: None,
                         }])
                     }
                 } else {
                     Err(vec![TypeError {
+                        code: codes::UNKNOWN_NAME,
                         location: call.location.clone(),
                         message: format!("Unknown function or variable '{}'", call.function_name),
+                        note: self.call_hint(&call.function_name),
                     }])
                 }
             }
@@ -1004,7 +1657,7 @@ impl TypeChecker {
             Node::ConditionalExpression(cond) => {
                 let cond_type = self.check_expression(&cond.condition)?;
                 if !self.is_boolean_compatible(&cond_type) {
-                    self.add_error(&cond.condition.location(), format!(
+                    self.add_error(codes::TYPE_MISMATCH, &cond.condition.location(), format!(
                         "condition must be boolean, got {}",
                         self.type_to_string(&cond_type)
                     ));
@@ -1018,7 +1671,7 @@ impl TypeChecker {
                 } else if self.is_compatible(&else_type, &then_type) {
                     Ok(else_type)
                 } else {
-                    self.add_error(&cond.location, format!(
+                    self.add_error(codes::TYPE_MISMATCH, &cond.location, format!(
                         "if branches have incompatible types: {} and {}",
                         self.type_to_string(&then_type),
                         self.type_to_string(&else_type)
@@ -1029,14 +1682,17 @@ impl TypeChecker {
             
             Node::AccessExpression(access) => {
                 let obj_type = self.check_expression(&access.object)?;
+                let obj_type = self.present_type(&access.object, obj_type);
                 
                 if let RuntimeType::Object(class_name) = &obj_type {
                     if let Some(prop_type) = self.find_property_in_class(class_name, &access.property) {
                         return Ok(prop_type);
                     } else {
                         return Err(vec![TypeError {
+                            code: codes::UNKNOWN_MEMBER,
                             location: access.location.clone(),
                             message: format!("Class '{}' has no property '{}'", class_name, access.property),
+                            note: None,
                         }]);
                     }
                 }
@@ -1046,8 +1702,10 @@ impl TypeChecker {
                          return Ok(RuntimeType::Number);
                      } else {
                          return Err(vec![TypeError {
+                             code: codes::UNKNOWN_MEMBER,
                              location: access.location.clone(),
                              message: format!("List has no property '{}'", access.property),
+                             note: None,
                          }]);
                      }
                 }
@@ -1068,19 +1726,22 @@ impl TypeChecker {
                 }
                 
                 Err(vec![TypeError {
+                    code: codes::UNKNOWN_MEMBER,
                     location: access.location.clone(),
                     message: format!("Cannot access property '{}' on type {:?}", access.property, obj_type),
+                    note: None,
                 }])
             }
             
             Node::IndexExpression(index) => {
                 let obj_type = self.check_expression(&index.object)?;
+                let obj_type = self.present_type(&index.object, obj_type);
                 let index_type = self.check_expression(&index.index)?;
                 
                 match obj_type {
                     RuntimeType::List(elem_type) => {
                         if !matches!(index_type, RuntimeType::Number) {
-                            self.add_error(&index.index.location(), format!(
+                            self.add_error(codes::TYPE_MISMATCH, &index.index.location(), format!(
                                 "List index must be a number, got {}",
                                 self.type_to_string(&index_type)
                             ));
@@ -1088,8 +1749,8 @@ impl TypeChecker {
                         Ok(*elem_type)
                     }
                     RuntimeType::Dictionary { key: key_type, value: val_type } => {
-                        if !self.is_compatible(&index_type, &key_type) {
-                            self.add_error(&index.index.location(), format!(
+                        if !self.is_key_compatible(&index_type, &key_type) {
+                            self.add_error(codes::TYPE_MISMATCH, &index.index.location(), format!(
                                 "Dictionary key must be {}, got {}",
                                 self.type_to_string(&key_type),
                                 self.type_to_string(&index_type)
@@ -1100,8 +1761,10 @@ impl TypeChecker {
                     RuntimeType::Any => Ok(RuntimeType::Any),
                     _ => {
                         Err(vec![TypeError {
+                            code: codes::UNKNOWN_MEMBER,
                             location: index.location.clone(),
                             message: format!("Type {:?} is not indexable", obj_type),
+                            note: None,
                         }])
                     }
                 }
@@ -1118,7 +1781,7 @@ impl TypeChecker {
         if matches!(op.operator, Operator::Not) {
             if !self.is_boolean_compatible(&left_type) {
                 let left_loc = op.left.location();
-                self.add_error(&left_loc, format!(
+                self.add_error(codes::TYPE_MISMATCH, &left_loc, format!(
                     "not operator requires boolean operand, got {}",
                     self.type_to_string(&left_type)
                 ));
@@ -1132,7 +1795,32 @@ impl TypeChecker {
         
         // handle binary operators
         if let Some(right) = &op.right {
-            let right_type = self.check_expression(right)?;
+            // "x exists and <condition>" may use x as present on the right
+            let narrowed = if matches!(op.operator, Operator::And) { self.narrow(&op.left) } else { Vec::new() };
+            let right_type = self.check_expression(right);
+            self.restore_narrowed(narrowed);
+            let right_type = right_type?;
+
+            // arithmetic and ordering need a value that is there
+            let (left_type, right_type) = if matches!(
+                op.operator,
+                Operator::Plus | Operator::Minus | Operator::Times | Operator::DividedBy | Operator::Modulo
+                    | Operator::GreaterThan | Operator::LessThan | Operator::GreaterThanOrEqual | Operator::LessThanOrEqual
+            ) {
+                (self.present_type(&op.left, left_type), self.present_type(right, right_type))
+            } else {
+                (left_type, right_type)
+            };
+
+            if let Some(result) = self.time_operation_type(&op.operator, &left_type, &right_type) {
+                return match result {
+                    Ok(ty) => Ok(ty),
+                    Err(message) => {
+                        self.add_error(codes::TYPE_MISMATCH, &op.location, message);
+                        Ok(RuntimeType::Void)
+                    }
+                };
+            }
             
             match op.operator {
                 Operator::Plus => {
@@ -1146,7 +1834,7 @@ impl TypeChecker {
                     } else if matches!(left_type, RuntimeType::String) || matches!(right_type, RuntimeType::String) {
                         Ok(RuntimeType::String)
                     } else {
-                        self.add_error(&op.location, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &op.location, format!(
                             "plus operator not applicable to {} and {}",
                             self.type_to_string(&left_type),
                             self.type_to_string(&right_type)
@@ -1159,7 +1847,7 @@ impl TypeChecker {
                     // arithmetic operations require numbers
                     if !self.is_compatible(&left_type, &RuntimeType::Number) {
                         let left_loc = op.left.location();
-                self.add_error(&left_loc, format!(
+                self.add_error(codes::TYPE_MISMATCH, &left_loc, format!(
                             "{} operator requires number on left, got {}",
                             self.op_to_string(&op.operator),
                             self.type_to_string(&left_type)
@@ -1167,7 +1855,7 @@ impl TypeChecker {
                     }
                     if !self.is_compatible(&right_type, &RuntimeType::Number) {
                         let right_loc = right.location();
-                        self.add_error(&right_loc, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &right_loc, format!(
                             "{} operator requires number on right, got {}",
                             self.op_to_string(&op.operator),
                             self.type_to_string(&right_type)
@@ -1180,7 +1868,7 @@ impl TypeChecker {
                     // comparison returns boolean
                     // types should be compatible
                     if !self.is_compatible(&left_type, &right_type) {
-                        self.add_error(&op.location, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &op.location, format!(
                             "cannot compare {} with {}",
                             self.type_to_string(&left_type),
                             self.type_to_string(&right_type)
@@ -1193,14 +1881,14 @@ impl TypeChecker {
                     // comparison operators require numbers
                     if !self.is_compatible(&left_type, &RuntimeType::Number) {
                         let left_loc = op.left.location();
-                        self.add_error(&left_loc, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &left_loc, format!(
                             "comparison operator requires number on left, got {}",
                             self.type_to_string(&left_type)
                         ));
                     }
                     if !self.is_compatible(&right_type, &RuntimeType::Number) {
                         let right_loc = right.location();
-                        self.add_error(&right_loc, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &right_loc, format!(
                             "comparison operator requires number on right, got {}",
                             self.type_to_string(&right_type)
                         ));
@@ -1213,14 +1901,14 @@ impl TypeChecker {
                     // operands should be boolean-compatible
                     if !self.is_boolean_compatible(&left_type) {
                         let left_loc = op.left.location();
-                self.add_error(&left_loc, format!(
+                self.add_error(codes::TYPE_MISMATCH, &left_loc, format!(
                             "logical operator requires boolean operand, got {}",
                             self.type_to_string(&left_type)
                         ));
                     }
                     if !self.is_boolean_compatible(&right_type) {
                         let right_loc = right.location();
-                        self.add_error(&right_loc, format!(
+                        self.add_error(codes::TYPE_MISMATCH, &right_loc, format!(
                             "logical operator requires boolean operand, got {}",
                             self.type_to_string(&right_type)
                         ));
@@ -1231,12 +1919,344 @@ impl TypeChecker {
                 _ => Ok(RuntimeType::Void),
             }
         } else {
-            self.add_error(&op.location, "binary operator missing right operand".to_string());
+            self.add_error(codes::TYPE_MISMATCH, &op.location, "binary operator missing right operand".to_string());
             Ok(RuntimeType::Void)
         }
     }
     
     // helper methods
+    /// result type of date/duration arithmetic and comparisons, mirroring
+    /// `stdlib::time::apply_operator`; `None` when no operand is a date or duration
+    fn time_operation_type(&self, op: &Operator, left: &RuntimeType, right: &RuntimeType) -> Option<Result<RuntimeType, String>> {
+        use RuntimeType::{Date, Duration, Number};
+        let is_time = |t: &RuntimeType| matches!(t, Date | Duration);
+        if !(is_time(left) || is_time(right)) || matches!(left, RuntimeType::String) || matches!(right, RuntimeType::String) {
+            return None;
+        }
+        let result = match (op, left, right) {
+            (Operator::Equals | Operator::NotEquals | Operator::And | Operator::Or, _, _) => return None,
+            (Operator::GreaterThan | Operator::LessThan | Operator::GreaterThanOrEqual | Operator::LessThanOrEqual, RuntimeType::Any, _)
+            | (Operator::GreaterThan | Operator::LessThan | Operator::GreaterThanOrEqual | Operator::LessThanOrEqual, _, RuntimeType::Any) => Ok(RuntimeType::Bool),
+            (_, RuntimeType::Any, _) | (_, _, RuntimeType::Any) => Ok(RuntimeType::Any),
+            (Operator::Plus, Date, Duration) | (Operator::Plus, Duration, Date) | (Operator::Minus, Date, Duration) => Ok(Date),
+            (Operator::Minus, Date, Date) => Ok(Duration),
+            (Operator::Plus | Operator::Minus, Duration, Duration) => Ok(Duration),
+            (Operator::Times, Duration, Number) | (Operator::Times, Number, Duration) | (Operator::DividedBy, Duration, Number) => Ok(Duration),
+            (Operator::GreaterThan | Operator::LessThan | Operator::GreaterThanOrEqual | Operator::LessThanOrEqual, l, r) => {
+                if l == r {
+                    Ok(RuntimeType::Bool)
+                } else {
+                    Err(format!("cannot compare {} with {}", self.type_to_string(l), self.type_to_string(r)))
+                }
+            }
+            _ => Err(format!(
+                "{} operator not applicable to {} and {}",
+                self.op_to_string(op),
+                self.type_to_string(left),
+                self.type_to_string(right)
+            )),
+        };
+        Some(result)
+    }
+
+    /// `convert X to Type`: the result has the target type; impossible conversions and
+    /// literal text that can never parse are reported here instead of at runtime
+    fn check_conversion(&mut self, call: &CallExpression) -> Result<RuntimeType, Vec<TypeError>> {
+        let source_type = self.check_expression(&call.arguments[0])?;
+        let target = match &call.arguments[1] {
+            Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(name), .. }) => name.clone(),
+            other => {
+                // dynamic target (e.g. "call function convert with x, t") - checked at runtime
+                self.check_expression(other)?;
+                return Ok(RuntimeType::Any);
+            }
+        };
+        let result_type = match crate::stdlib::convert::canonical_target(&target) {
+            Some("Number") => RuntimeType::Number,
+            Some("Text") => RuntimeType::String,
+            Some("Bool") => RuntimeType::Bool,
+            Some("Date") => RuntimeType::Date,
+            Some("List") => match &source_type {
+                RuntimeType::String => RuntimeType::List(Box::new(RuntimeType::String)),
+                RuntimeType::List(inner) => RuntimeType::List(inner.clone()),
+                _ => RuntimeType::List(Box::new(RuntimeType::Any)),
+            },
+            _ => {
+                self.add_error(codes::UNKNOWN_NAME, &call.location, format!("Cannot convert to unknown type '{}'", target));
+                return Ok(RuntimeType::Any);
+            }
+        };
+
+        let convertible = matches!(
+            (&source_type, &result_type),
+            (RuntimeType::Any | RuntimeType::Void | RuntimeType::String, _)
+                | (_, RuntimeType::String)
+                | (RuntimeType::Number | RuntimeType::Bool, RuntimeType::Number | RuntimeType::Bool)
+                | (RuntimeType::List(_), RuntimeType::List(_))
+                | (RuntimeType::Date, RuntimeType::Date)
+        );
+        if !convertible {
+            self.add_error(codes::TYPE_MISMATCH, &call.location, format!(
+                "Cannot convert {} to {}",
+                self.type_to_string(&source_type),
+                self.type_to_string(&result_type)
+            ));
+        } else if let Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(text), .. }) = &call.arguments[0] {
+            let args = [Value::String(text.clone()), Value::String(target)];
+            if let Err(e) = crate::stdlib::convert::convert(&args) {
+                self.add_error(codes::INVALID_LITERAL, &call.location, e);
+            }
+        }
+        Ok(result_type)
+    }
+
+    /// how errors name a variable bound to `value`: constants and frozen copies (or variables
+    /// holding another immutable value) cannot change; anything else can
+    fn immutable_label(&self, name: &str, value: &Node, is_mutable: bool) -> Option<String> {
+        if !is_mutable {
+            return Some(format!("the constant '{}'", name));
+        }
+        match value {
+            Node::CallExpression(call) if call.function_name == "__freeze" => Some(format!("the frozen value '{}'", name)),
+            Node::VariableExpression(var) if self.context.get_immutable_label(&var.identifier).is_some() => {
+                Some(format!("the frozen value '{}'", name))
+            }
+            _ => None,
+        }
+    }
+
+    /// report changing the contents of a constant or frozen value, found through the variable
+    /// at the root of `target` (`items`, `items[1]`, `get name from items`, ...)
+    fn check_mutable(&mut self, target: &Node, location: &Location) {
+        let mut root = target;
+        loop {
+            match root {
+                Node::IndexExpression(index) => root = &index.object,
+                Node::AccessExpression(access) => root = &access.object,
+                _ => break,
+            }
+        }
+        if let Node::VariableExpression(var) = root {
+            if let Some(label) = self.context.get_immutable_label(&var.identifier) {
+                self.add_error(codes::IMMUTABLE, location, format!("Cannot change {}", label));
+            }
+        }
+    }
+
+    /// report using a `maybe T` where a value is required; returns the type to carry on
+    /// checking with, so one missing check is reported once
+    fn present_type(&mut self, node: &Node, ty: RuntimeType) -> RuntimeType {
+        match ty {
+            RuntimeType::Maybe(inner) => {
+                let message = match node {
+                    Node::VariableExpression(var) => format!(
+                        "'{}' might be nothing; check it with 'if {} exists' before using it",
+                        var.identifier, var.identifier
+                    ),
+                    _ => "this value might be nothing; store it in a variable and check it with 'if ... exists' before using it".to_string(),
+                };
+                self.add_error(codes::MAYBE_NOTHING, &node.location(), message);
+                *inner
+            }
+            other => other,
+        }
+    }
+
+    /// narrow every `maybe T` variable the condition checks with `exists` (directly or joined by
+    /// `and`) to `T`; returns what `restore_narrowed` needs to undo it
+    fn narrow(&mut self, condition: &Node) -> Vec<(String, RuntimeType, bool, Option<RuntimeType>)> {
+        let mut names = Vec::new();
+        collect_exists_checks(condition, &mut names);
+        let mut saved = Vec::new();
+        for name in names {
+            if let Some(RuntimeType::Maybe(inner)) = self.context.get_variable_type(&name) {
+                let is_mut = self.context.get_variable_mutability(&name).unwrap_or(true);
+                let declared = RuntimeType::Maybe(inner.clone());
+                let previous = self.narrowed.insert(name.clone(), declared.clone());
+                self.context.set_variable_type(name.clone(), *inner, is_mut);
+                saved.push((name, declared, is_mut, previous));
+            }
+        }
+        saved
+    }
+
+    fn restore_narrowed(&mut self, saved: Vec<(String, RuntimeType, bool, Option<RuntimeType>)>) {
+        for (name, declared, is_mut, previous) in saved.into_iter().rev() {
+            self.context.set_variable_type(name.clone(), declared, is_mut);
+            match previous {
+                Some(previous) => self.narrowed.insert(name, previous),
+                None => self.narrowed.remove(&name),
+            };
+        }
+    }
+
+    /// `maybe xs[i]`, `maybe item i of xs` and `maybe get p from x`: the looked-up type, as a maybe;
+    /// looking up in a maybe value is allowed and stays a maybe
+    fn check_maybe_lookup(&mut self, call: &CallExpression) -> Result<RuntimeType, Vec<TypeError>> {
+        if call.arguments.len() != 2 {
+            return Err(vec![TypeError {
+                code: codes::ARGUMENT_COUNT,
+                location: call.location.clone(),
+                message: format!("{} expects an object and a key", call.function_name),
+                note: None,
+            }]);
+        }
+        let obj_type = match self.check_expression(&call.arguments[0])? {
+            RuntimeType::Maybe(inner) => *inner,
+            other => other,
+        };
+        let found = if call.function_name == "__maybe_index" {
+            let index_type = self.check_expression(&call.arguments[1])?;
+            match obj_type {
+                RuntimeType::List(elem_type) => {
+                    if !matches!(index_type, RuntimeType::Number | RuntimeType::Any) {
+                        self.add_error(codes::TYPE_MISMATCH, &call.arguments[1].location(), format!(
+                            "List index must be a number, got {}",
+                            self.type_to_string(&index_type)
+                        ));
+                    }
+                    *elem_type
+                }
+                RuntimeType::Dictionary { key, value } => {
+                    if !self.is_key_compatible(&index_type, &key) {
+                        self.add_error(codes::TYPE_MISMATCH, &call.arguments[1].location(), format!(
+                            "Dictionary key must be {}, got {}",
+                            self.type_to_string(&key),
+                            self.type_to_string(&index_type)
+                        ));
+                    }
+                    *value
+                }
+                RuntimeType::Any => return Ok(RuntimeType::Any),
+                other => {
+                    return Err(vec![TypeError {
+                        code: codes::UNKNOWN_MEMBER,
+                        location: call.location.clone(),
+                        message: format!("Type {} is not indexable", self.type_to_string(&other)),
+                        note: None,
+                    }]);
+                }
+            }
+        } else {
+            let property = match &call.arguments[1] {
+                Node::LiteralExpression(LiteralExpression { value: LiteralValue::String(name), .. }) => name.clone(),
+                _ => String::new(),
+            };
+            match obj_type {
+                RuntimeType::Object(class_name) => match self.find_property_in_class(&class_name, &property) {
+                    Some(prop_type) => prop_type,
+                    None => {
+                        return Err(vec![TypeError {
+                            code: codes::UNKNOWN_MEMBER,
+                            location: call.location.clone(),
+                            message: format!("Class '{}' has no property '{}'", class_name, property),
+                            note: None,
+                        }]);
+                    }
+                },
+                RuntimeType::Dictionary { value, .. } => *value,
+                RuntimeType::Any => return Ok(RuntimeType::Any),
+                other => {
+                    return Err(vec![TypeError {
+                        code: codes::UNKNOWN_MEMBER,
+                        location: call.location.clone(),
+                        message: format!("Cannot look up property '{}' on {}", property, self.type_to_string(&other)),
+                        note: None,
+                    }]);
+                }
+            }
+        };
+        Ok(match found {
+            RuntimeType::Maybe(_) | RuntimeType::Any => found,
+            other => RuntimeType::Maybe(Box::new(other)),
+        })
+    }
+
+    /// map, filter, reduce and friends: the function's parameters take the list's element type
+    fn check_collection_call(&mut self, call: &CallExpression) -> Result<RuntimeType, Vec<TypeError>> {
+        let operation = call.function_name.trim_start_matches("__collections_").replace('_', " ");
+        let expected_args = if operation == "reduce" { 3 } else { 2 };
+        if call.arguments.len() != expected_args {
+            self.add_error(codes::ARGUMENT_COUNT, &call.location, format!(
+                "function '{}' expects {} arguments but got {}",
+                call.function_name,
+                expected_args,
+                call.arguments.len()
+            ));
+            return Ok(RuntimeType::Any);
+        }
+
+        let element = match self.check_expression(&call.arguments[0])? {
+            RuntimeType::List(inner) => *inner,
+            RuntimeType::Any => RuntimeType::Any,
+            other => {
+                self.add_error(codes::TYPE_MISMATCH, &call.arguments[0].location(), format!(
+                    "{} expects a list, got {}",
+                    operation,
+                    self.type_to_string(&other)
+                ));
+                RuntimeType::Any
+            }
+        };
+        let callback_params = if operation == "reduce" {
+            vec![self.check_expression(&call.arguments[2])?, element.clone()]
+        } else {
+            vec![element.clone()]
+        };
+
+        let result = match &call.arguments[1] {
+            Node::FunctionDeclaration(func) if func.name == "<lambda>" => {
+                if func.parameters.len() != callback_params.len() {
+                    self.add_error(codes::ARGUMENT_COUNT, &func.location, format!(
+                        "The function given to {} must take {} parameter{}",
+                        operation,
+                        callback_params.len(),
+                        if callback_params.len() == 1 { "" } else { "s" }
+                    ));
+                }
+                let mut func_context = TypeContext::with_parent(Box::new(self.context.clone()));
+                for (param, inferred) in func.parameters.iter().zip(callback_params.iter().chain(std::iter::repeat(&RuntimeType::Any))) {
+                    let param_type = match &param.type_annotation {
+                        Some(annotated) => self.ast_type_to_runtime_type(annotated),
+                        None => inferred.clone(),
+                    };
+                    func_context.set_variable_type(param.name.clone(), param_type, false);
+                }
+                let old_context = std::mem::replace(&mut self.context, func_context);
+                let body_type = self.check_statement(&func.body);
+                self.context = old_context;
+                body_type?
+            }
+            other => match self.check_expression(other)? {
+                RuntimeType::Function { return_type, .. } => *return_type,
+                _ => RuntimeType::Any,
+            },
+        };
+
+        let is_condition = matches!(operation.as_str(), "filter" | "find" | "any" | "all" | "count where");
+        if is_condition && !matches!(result, RuntimeType::Bool | RuntimeType::Any) {
+            self.add_error(codes::TYPE_MISMATCH, &call.arguments[1].location(), format!(
+                "The condition given to {} must be true or false, got {}",
+                operation,
+                self.type_to_string(&result)
+            ));
+        }
+
+        Ok(match operation.as_str() {
+            "map" => RuntimeType::List(Box::new(result)),
+            "filter" | "sort by" => RuntimeType::List(Box::new(element)),
+            "reduce" => callback_params[0].clone(),
+            "find" => RuntimeType::Maybe(Box::new(element)),
+            "any" | "all" => RuntimeType::Bool,
+            "count where" => RuntimeType::Number,
+            "group by" => RuntimeType::Dictionary {
+                key: Box::new(RuntimeType::String),
+                value: Box::new(RuntimeType::List(Box::new(element))),
+            },
+            _ => RuntimeType::Any,
+        })
+    }
+
     fn is_compatible(&self, from: &RuntimeType, to: &RuntimeType) -> bool {
         match (from, to) {
             // same types are compatible
@@ -1283,6 +2303,11 @@ impl TypeChecker {
         }
     }
     
+    /// a dictionary key must have the declared key type exactly; numbers are not taken as text here
+    fn is_key_compatible(&self, from: &RuntimeType, key: &RuntimeType) -> bool {
+        from == key || matches!(from, RuntimeType::Any) || matches!(key, RuntimeType::Any)
+    }
+
     fn is_boolean_compatible(&self, _ty: &RuntimeType) -> bool {
         // All types have truthy/falsy semantics in Layman
         true
@@ -1296,6 +2321,8 @@ impl TypeChecker {
                 BasicType::Bool => RuntimeType::Bool,
                 BasicType::Void => RuntimeType::Void,
                 BasicType::Any => RuntimeType::Any,
+                BasicType::Date => RuntimeType::Date,
+                BasicType::Duration => RuntimeType::Duration,
             },
             Type::CompositeType(composite) => match composite {
                 CompositeType::List(inner) => {
@@ -1332,7 +2359,7 @@ impl TypeChecker {
         }
     }
     
-    fn type_to_string(&self, ty: &RuntimeType) -> String {
+    pub fn type_to_string(&self, ty: &RuntimeType) -> String {
         match ty {
             RuntimeType::Number => "number".to_string(),
             RuntimeType::String => "text".to_string(),
@@ -1351,6 +2378,8 @@ impl TypeChecker {
             RuntimeType::Future(inner) => format!("future of {}", self.type_to_string(inner)),
             RuntimeType::Variant(name) => name.clone(),
             RuntimeType::Maybe(inner) => format!("maybe {}", self.type_to_string(inner)),
+            RuntimeType::Date => "date".to_string(),
+            RuntimeType::Duration => "duration".to_string(),
             RuntimeType::Nothing => "nothing".to_string(),
             RuntimeType::Any => "any".to_string(),
         }
@@ -1377,10 +2406,16 @@ impl TypeChecker {
         .to_string()
     }
     
-    fn add_error(&mut self, location: &Location, message: String) {
+    fn add_error(&mut self, code: &'static str, location: &Location, message: String) {
+        self.add_error_with_note(code, location, message, None);
+    }
+
+    fn add_error_with_note(&mut self, code: &'static str, location: &Location, message: String, note: Option<String>) {
         self.errors.push(TypeError {
+            code,
             location: location.clone(),
             message,
+            note,
         });
     }
 
@@ -1400,6 +2435,42 @@ impl TypeChecker {
         None
     }
 
+    /// "did you mean" for an unknown variable or function
+    fn name_hint(&self, name: &str) -> Option<String> {
+        let names = self.context.names();
+        suggest::hint(name, names.iter().map(String::as_str))
+    }
+
+    /// "did you mean" for an unknown class or type
+    fn type_hint(&self, name: &str) -> Option<String> {
+        let names = self.context.type_names();
+        suggest::hint(name, names.iter().map(String::as_str))
+    }
+
+    /// "did you mean" for a method (or property) a class does not have, from its own and inherited members
+    fn member_hint(&self, class_name: &str, name: &str, method: bool) -> Option<String> {
+        let class = self.class_members(class_name)?;
+        if method {
+            suggest::hint(name, class.methods.keys().map(String::as_str))
+        } else {
+            suggest::hint(name, class.properties.keys().map(String::as_str))
+        }
+    }
+
+    /// "did you mean" for an unknown call; `list.ad` is matched against the methods of `list`
+    fn call_hint(&self, name: &str) -> Option<String> {
+        let Some((object, member)) = name.split_once('.') else {
+            return self.name_hint(name);
+        };
+        match self.context.get_variable_type(object) {
+            Some(RuntimeType::List(_)) => suggest::hint(member, LIST_METHODS.iter().copied()),
+            Some(RuntimeType::Dictionary { .. }) => suggest::hint(member, DICTIONARY_METHODS.iter().copied()),
+            Some(RuntimeType::Object(class_name)) => self.member_hint(&class_name, member, true),
+            Some(_) => None,
+            None => self.name_hint(object),
+        }
+    }
+
     fn find_method_in_class(&self, class_name: &str, method_name: &str) -> Option<FunctionSignature> {
         let mut current_class_name = Some(class_name.to_string());
         
@@ -1446,30 +2517,38 @@ impl TypeChecker {
         if !found {
             // If file doesn't exist, we can't check it.
             return Err(vec![TypeError {
+                code: codes::IMPORT,
                 location: stmt.location.clone(),
                 message: format!("Module not found: {}", module_key),
+                note: None,
             }]);
         }
         
         // Read and parse
         let source = fs::read_to_string(path)
             .map_err(|e| vec![TypeError {
+                code: codes::IMPORT,
                 location: stmt.location.clone(),
                 message: format!("Failed to read module: {}", e),
+                note: None,
             }])?;
             
         let mut lexer = lexer::Lexer::new(&source, module_key.clone());
         let tokens = lexer.tokenize()
             .map_err(|e| vec![TypeError {
+                code: codes::IMPORT,
                 location: stmt.location.clone(),
                 message: format!("Lexer error: {}", e),
+                note: None,
             }])?;
             
         let mut parser = parser::Parser::new(tokens);
         let ast = parser.parse()
             .map_err(|e| vec![TypeError {
+                code: codes::IMPORT,
                 location: stmt.location.clone(),
                 message: format!("Parser error: {}", e),
+                note: None,
             }])?;
             
         // Check module in new context
@@ -1542,3 +2621,22 @@ impl LocationExt for Node {
 
 }
 
+/// the variables a condition checks with `x exists`, looking through `and`
+fn collect_exists_checks(condition: &Node, names: &mut Vec<String>) {
+    if let Node::OperationExpression(op) = condition {
+        match op.operator {
+            Operator::Exists => {
+                if let Node::VariableExpression(var) = op.left.as_ref() {
+                    names.push(var.identifier.clone());
+                }
+            }
+            Operator::And => {
+                collect_exists_checks(&op.left, names);
+                if let Some(right) = &op.right {
+                    collect_exists_checks(right, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// deep immutability for constants and frozen copies
+//
+// lists, dictionaries and objects are shared `Arc<Shared<..>>` containers, and each one carries
+// the name it was frozen under, so freezing a value marks every container it reaches.
+
+use super::{ObjectData, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// how errors refer to a frozen value that has not been bound to a name yet
+pub const UNNAMED: &str = "a frozen value";
+
+/// the contents of a list, dictionary or object, and the name it was frozen under if it is
+/// frozen; derefs to the contents' mutex
+pub struct Shared<T> {
+    value: Mutex<T>,
+    frozen: Mutex<Option<String>>,
+}
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Arc<Self> {
+        Arc::new(Shared { value: Mutex::new(value), frozen: Mutex::new(None) })
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = Mutex<T>;
+
+    fn deref(&self) -> &Mutex<T> {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+fn address<T>(container: &Arc<Shared<T>>) -> usize {
+    Arc::as_ptr(container) as *const () as usize
+}
+
+/// mark a container frozen; returns false when it already was, so shared and cyclic
+/// values are only walked once
+fn register<T>(container: &Shared<T>, label: &str) -> bool {
+    let mut frozen = container.frozen.lock().unwrap();
+    match frozen.as_mut() {
+        // a frozen copy takes the name of the first variable or constant it is bound to
+        Some(existing) if existing == UNNAMED && label != UNNAMED => {
+            *existing = label.to_string();
+            true
+        }
+        Some(_) => false,
+        None => {
+            *frozen = Some(label.to_string());
+            true
+        }
+    }
+}
+
+/// make a value and everything it contains immutable; `label` names it in errors,
+/// e.g. "the constant 'items'"
+pub fn freeze(value: &Value, label: &str) {
+    let children: Vec<Value> = match value {
+        Value::List(items) if register(items, label) => items.lock().unwrap().clone(),
+        Value::Dictionary(map) if register(map, label) => map.lock().unwrap().values().cloned().collect(),
+        Value::Object(data) if register(data, label) => data.lock().unwrap().properties.values().cloned().collect(),
+        Value::Variant(_, _, fields) => fields.values().cloned().collect(),
+        _ => return,
+    };
+    for child in &children {
+        freeze(child, label);
+    }
+}
+
+/// the value to store when binding a name: a constant gets a frozen deep copy, so other
+/// variables sharing its lists, dictionaries or objects can still change them; a frozen copy
+/// is named after the variable it is bound to
+pub fn bind(value: Value, name: &str, is_mutable: bool) -> Value {
+    if !is_mutable {
+        let value = if is_frozen(&value) { value } else { deep_copy(&value) };
+        freeze(&value, &format!("the constant '{}'", name));
+        value
+    } else {
+        if is_frozen(&value) {
+            freeze(&value, &format!("the frozen value '{}'", name));
+        }
+        value
+    }
+}
+
+/// whether the value's outermost container is frozen
+pub fn is_frozen(value: &Value) -> bool {
+    match value {
+        Value::List(items) => items.frozen.lock().unwrap().is_some(),
+        Value::Dictionary(map) => map.frozen.lock().unwrap().is_some(),
+        Value::Object(data) => data.frozen.lock().unwrap().is_some(),
+        _ => false,
+    }
+}
+
+/// fails with an error naming the constant when the container is frozen
+pub fn check_mutable<T>(container: &Shared<T>) -> Result<(), String> {
+    match container.frozen.lock().unwrap().as_ref() {
+        Some(label) => Err(format!("Cannot change {}", label)),
+        None => Ok(()),
+    }
+}
+
+/// a copy that shares no containers with the original
+pub fn deep_copy(value: &Value) -> Value {
+    copy_with(value, &mut HashMap::new())
+}
+
+fn copy_with(value: &Value, copies: &mut HashMap<usize, Value>) -> Value {
+    match value {
+        Value::List(items) => {
+            if let Some(copy) = copies.get(&address(items)) {
+                return copy.clone();
+            }
+            let copy = Shared::new(Vec::new());
+            copies.insert(address(items), Value::List(copy.clone()));
+            let originals = items.lock().unwrap().clone();
+            let copied: Vec<Value> = originals.iter().map(|item| copy_with(item, copies)).collect();
+            *copy.lock().unwrap() = copied;
+            Value::List(copy)
+        }
+        Value::Dictionary(map) => {
+            if let Some(copy) = copies.get(&address(map)) {
+                return copy.clone();
+            }
+            let copy = Shared::new(HashMap::new());
+            copies.insert(address(map), Value::Dictionary(copy.clone()));
+            let originals = map.lock().unwrap().clone();
+            let copied: HashMap<String, Value> = originals.iter().map(|(k, v)| (k.clone(), copy_with(v, copies))).collect();
+            *copy.lock().unwrap() = copied;
+            Value::Dictionary(copy)
+        }
+        Value::Object(data) => {
+            if let Some(copy) = copies.get(&address(data)) {
+                return copy.clone();
+            }
+            let original = data.lock().unwrap().clone();
+            let copy = Shared::new(ObjectData {
+                class_name: original.class_name.clone(),
+                properties: HashMap::new(),
+            });
+            copies.insert(address(data), Value::Object(copy.clone()));
+            let copied: HashMap<String, Value> = original.properties.iter().map(|(k, v)| (k.clone(), copy_with(v, copies))).collect();
+            copy.lock().unwrap().properties = copied;
+            Value::Object(copy)
+        }
+        Value::Variant(type_name, variant_name, fields) => Value::Variant(
+            type_name.clone(),
+            variant_name.clone(),
+            fields.iter().map(|(k, v)| (k.clone(), copy_with(v, copies))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
@@ -1,5 +1,9 @@
 // type system for layman
 
+pub mod frozen;
+
+pub use frozen::Shared;
+
 use crate::ast::{Type, BasicType, CompositeType};
 use std::fmt;
 use std::collections::HashMap;
@@ -39,6 +43,15 @@ impl Environment {
             .or_else(|| self.parent.as_ref().and_then(|p| p.get(name)))
     }
     
+    /// every name visible from this scope
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.variables.lock().unwrap().keys().cloned().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
+
     pub fn define(&self, name: String, value: Value, is_mutable: bool) {
         self.variables.lock().unwrap().insert(name, (value, is_mutable));
     }
@@ -86,6 +99,8 @@ pub enum RuntimeType {
     Future(Box<RuntimeType>),
     Variant(String), // type name (e.g. "Shape")
     Maybe(Box<RuntimeType>),
+    Date,
+    Duration,
     Nothing,
     Any,
 }
@@ -100,6 +115,8 @@ impl RuntimeType {
                 BasicType::Bool => RuntimeType::Bool,
                 BasicType::Void => RuntimeType::Void,
                 BasicType::Any => RuntimeType::Any,
+                BasicType::Date => RuntimeType::Date,
+                BasicType::Duration => RuntimeType::Duration,
             },
             Type::CompositeType(composite) => match composite {
                 CompositeType::List(inner) => RuntimeType::List(Box::new(Self::from_ast_type(inner))),
@@ -116,21 +133,27 @@ impl RuntimeType {
     }
 }
 
+/// the methods of a list, called as `call <method> on <list>`; the checker and the evaluator both go by these
+pub const LIST_METHODS: &[&str] = &["add", "length", "count", "size"];
+/// the methods of a dictionary
+pub const DICTIONARY_METHODS: &[&str] =
+    &["length", "count", "size", "keys", "values", "entries", "clear", "remove", "contains_key", "merge", "get"];
+
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
     String(String),  // changed from Text
     Bool(bool),      // changed from Boolean
     Void,            // changed from Nothing
-    List(Arc<Mutex<Vec<Value>>>),
-    Dictionary(Arc<Mutex<HashMap<String, Value>>>),
+    List(Arc<Shared<Vec<Value>>>),
+    Dictionary(Arc<Shared<HashMap<String, Value>>>),
     Function {
         name: String,
         parameters: Vec<String>,
         body: Box<crate::ast::Node>,
         env: Option<Arc<Environment>>,
     },
-    Object(Arc<Mutex<ObjectData>>),
+    Object(Arc<Shared<ObjectData>>),
     Class {
         name: String,
         methods: HashMap<String, crate::ast::FunctionDeclaration>,
@@ -138,6 +161,8 @@ pub enum Value {
     Return(Box<Value>), // internal use only for control flow
     Future(Arc<Mutex<FutureState>>),
     Variant(String, String, HashMap<String, Value>), // TypeName, VariantName, Fields
+    Date(i64),     // seconds since the unix epoch, UTC
+    Duration(i64), // length in seconds
     Nothing,
 }
 
@@ -145,7 +170,7 @@ pub enum Value {
 pub enum FutureState {
     Pending,
     Completed(Value),
-    Failed(String),
+    Failed(crate::evaluator::RuntimeError),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -191,6 +216,8 @@ impl Value {
                 }
             },
             Value::Variant(type_name, _, _) => RuntimeType::Variant(type_name.clone()),
+            Value::Date(_) => RuntimeType::Date,
+            Value::Duration(_) => RuntimeType::Duration,
             Value::Nothing => RuntimeType::Nothing,
         }
     }
@@ -205,41 +232,36 @@ impl Value {
                 let items_str: Vec<String> = items.lock().unwrap().iter().map(|v| v.to_string()).collect();
                 format!("[{}]", items_str.join(", "))
             }
-            Value::Dictionary(dict) => {
-                let mut s = String::from("{");
-                for (i, (k, v)) in dict.lock().unwrap().iter().enumerate() {
-                    if i > 0 { s.push_str(", "); }
-                    s.push_str(&format!("{}: {}", k, v.to_string()));
-                }
-                s.push('}');
-                s
-            }
+            Value::Dictionary(dict) => format!("{{{}}}", sorted_fields(&dict.lock().unwrap()).join(", ")),
             Value::Function { name, .. } => format!("function({})", name),
             Value::Object(data) => {
                 let data = data.lock().unwrap();
-                let props: Vec<String> = data.properties.iter()
-                    .map(|(k, v)| format!("{}: {}", k, v.to_string()))
-                    .collect();
-                format!("{} object with {}", data.class_name, props.join(", "))
+                format!("{} object with {}", data.class_name, sorted_fields(&data.properties).join(", "))
             },
             Value::Class { name, .. } => format!("class {}", name),
             Value::Return(val) => val.to_string(),
             Value::Future(_) => "future".to_string(),
             Value::Variant(_, variant_name, fields) => {
-                let props: Vec<String> = fields.iter()
-                    .map(|(k, v)| format!("{}: {}", k, v.to_string()))
-                    .collect();
+                let props = sorted_fields(fields);
                 if props.is_empty() {
                     variant_name.clone()
                 } else {
                     format!("{} with {}", variant_name, props.join(", "))
                 }
             },
+            Value::Date(seconds) => crate::stdlib::time::format_iso(*seconds),
+            Value::Duration(seconds) => crate::stdlib::time::describe_duration(*seconds),
             Value::Nothing => "nothing".to_string(),
         }
     }
 }
 
+/// `name: value` for each entry, sorted by name so the same value always prints the same way
+fn sorted_fields(map: &HashMap<String, Value>) -> Vec<String> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries.into_iter().map(|(k, v)| format!("{}: {}", k, v.to_string())).collect()
+}
 
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
@@ -271,6 +293,8 @@ impl PartialEq for Value {
             (Value::Class { name: n1, .. }, Value::Class { name: n2, .. }) => n1 == n2,
             (Value::Return(a), Value::Return(b)) => a == b,
             (Value::Future(a), Value::Future(b)) => Arc::ptr_eq(a, b),
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
             (Value::Nothing, Value::Nothing) => true,
 
             _ => false,
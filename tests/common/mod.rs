@@ -0,0 +1,25 @@
+// helpers shared by the integration tests; each test binary uses only some of them
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+/// the layman binary cargo built for this test run
+pub fn layman_bin() -> PathBuf {
+    PathBuf::from(env!("CARGO_BIN_EXE_layman"))
+}
+
+/// a fresh directory under the system temp directory holding the given files
+pub fn temp_project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let temp_dir = std::env::temp_dir().join(name);
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    for (file, source) in files {
+        let path = temp_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    temp_dir
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+#[test]
+fn test_coverage_writes_lcov_and_enforces_minimum() {
+    let dir = std::env::temp_dir().join("layman_coverage_test");
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(dir.join("cases")).unwrap();
+    fs::write(
+        dir.join("cases/grade.lay"),
+        "\
+define function grade that takes score as Number and returns String
+  if score is greater than 50 then
+    return \"pass\"
+  otherwise
+    return \"fail\"
+  end if
+
+print call function grade with 70
+",
+    )
+    .unwrap();
+    fs::write(dir.join("cases/total.lay"), "the variable x is 1\nprint x\n").unwrap();
+
+    let output = Command::new(layman_bin()).current_dir(&dir).args(["test", "cases", "--coverage"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("cases/grade.lay       4       3    75.0%"), "{}", stdout);
+    assert!(stdout.contains("Total                 6       5    83.3%"), "{}", stdout);
+
+    // the `otherwise` branch on line 5 never ran
+    let lcov = fs::read_to_string(dir.join("lcov.info")).unwrap();
+    assert_eq!(
+        lcov,
+        "\
+TN:\nSF:cases/grade.lay\nDA:2,1\nDA:3,1\nDA:5,0\nDA:8,1\nLF:4\nLH:3\nend_of_record
+TN:\nSF:cases/total.lay\nDA:1,1\nDA:2,1\nLF:2\nLH:2\nend_of_record
+"
+    );
+
+    let output = Command::new(layman_bin())
+        .current_dir(&dir)
+        .args(["test", "cases", "--min-coverage", "90", "--lcov", "out.info"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Coverage is 83.3%, below the minimum of 90%"));
+    assert!(dir.join("out.info").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_coverage_leaves_out_libraries_and_counts_nothing_as_zero() {
+    let dir = temp_project(
+        "layman_coverage_libraries",
+        &[
+            ("std/helper.lay", "define function twice that takes n and returns Number\n  return n times 2\n\ndefine function unused that takes n and returns Number\n  return n\n"),
+            ("cases/main.lay", "import \"helper\"\nprint call function twice with 2\n"),
+            ("empty/notes.lay", "# nothing to run\n"),
+        ],
+    );
+
+    let output = Command::new(layman_bin()).current_dir(&dir).args(["test", "cases", "--coverage"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("helper.lay"), "{}", stdout);
+    assert!(stdout.contains("Total                1       1   100.0%"), "{}", stdout);
+
+    let output = Command::new(layman_bin()).current_dir(&dir).args(["test", "cases", "--coverage", "--include-libraries"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("std/helper.lay"), "{}", stdout);
+
+    let output = Command::new(layman_bin()).current_dir(&dir).args(["test", "empty", "--min-coverage", "50"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Coverage is 0.0%, below the minimum of 50%"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
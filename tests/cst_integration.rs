@@ -0,0 +1,94 @@
+use layman_lib::cst::{self, SyntaxKind};
+use layman_lib::lexer::{Lexer, TriviaKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn lay_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            lay_files(&path, files);
+        } else if path.extension().is_some_and(|e| e == "lay") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn test_tokens_round_trip_every_test_case() {
+    let mut files = Vec::new();
+    lay_files(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-cases"), &mut files);
+    assert!(!files.is_empty());
+
+    let mut trees = 0;
+    for file in files {
+        let source = fs::read_to_string(&file).unwrap();
+        let name = file.display().to_string();
+        // some negative cases do not even lex
+        let Ok(tokens) = Lexer::new(&source, name.clone()).tokenize() else {
+            continue;
+        };
+        let rendered: String = tokens.iter().map(|t| t.full_text()).collect();
+        assert_eq!(rendered, source, "tokens of {} do not round-trip", name);
+
+        // files the parser rejects (the negative cases) have no tree
+        if let Ok(tree) = cst::parse(&source, &name) {
+            assert_eq!(tree.root.text(), source, "tree of {} does not round-trip", name);
+            trees += 1;
+        }
+    }
+    assert!(trees > 0);
+}
+
+#[test]
+fn test_trivia_keeps_comments_blank_lines_and_odd_characters() {
+    let source = "# greeting\r\n\n  print \"hi\"   # say it\n\tprint 1 ?! \n# the end";
+    let tokens = Lexer::new(source, "odd.lay".to_string()).tokenize().unwrap();
+    assert_eq!(tokens.iter().map(|t| t.full_text()).collect::<String>(), source);
+
+    let print = &tokens[tokens.iter().position(|t| t.text == "print").unwrap()];
+    let leading: Vec<_> = print.leading_trivia.iter().map(|t| (t.kind, t.text.as_str())).collect();
+    assert_eq!(leading, vec![(TriviaKind::Whitespace, "  ")]);
+
+    let hi = &tokens[tokens.iter().position(|t| t.text == "\"hi\"").unwrap()];
+    let trailing: Vec<_> = hi.trailing_trivia.iter().map(|t| (t.kind, t.text.as_str())).collect();
+    assert_eq!(trailing, vec![(TriviaKind::Whitespace, "   "), (TriviaKind::Comment, "# say it")]);
+
+    let one = &tokens[tokens.iter().position(|t| t.text == "1").unwrap()];
+    let trailing: Vec<_> = one.trailing_trivia.iter().map(|t| (t.kind, t.text.as_str())).collect();
+    assert_eq!(trailing, vec![(TriviaKind::Whitespace, " "), (TriviaKind::Skipped, "?!"), (TriviaKind::Whitespace, " ")]);
+
+    let eof = tokens.last().unwrap();
+    assert_eq!(eof.leading_trivia.len(), 1);
+    assert_eq!(eof.leading_trivia[0].text, "# the end");
+}
+
+#[test]
+fn test_tree_nests_statements_and_attaches_doc_comments() {
+    let source = "\
+# licence text, not documentation
+
+# adds one
+# to a number
+define function bump that takes n as Number and returns Number
+  # the answer
+  return n plus 1
+
+the variable x is call function bump with 2   # three
+";
+    let tree = cst::parse(source, "bump.lay").unwrap();
+    assert_eq!(tree.root.text(), source);
+
+    let statements: Vec<_> = tree.root.child_nodes().collect();
+    assert_eq!(statements.len(), 2);
+    assert_eq!(statements[0].kind, SyntaxKind::Statement("FunctionDeclaration"));
+    assert_eq!(statements[0].leading_comments(), vec!["# adds one", "# to a number"]);
+    assert_eq!(statements[1].kind, SyntaxKind::Statement("DeclareStatement"));
+    assert!(statements[1].leading_comments().is_empty());
+
+    let body: Vec<_> = statements[0].child_nodes().collect();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].kind, SyntaxKind::Statement("ReturnStatement"));
+    assert_eq!(body[0].leading_comments(), vec!["# the answer"]);
+    assert_eq!(body[0].text(), "  # the answer\n  return n plus 1");
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::layman_bin;
+
+#[test]
+fn test_csv_embedded_newlines_and_error_lines() {
+    let temp_dir = std::env::temp_dir().join("layman_csv_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    // a quoted field spanning two lines, CRLF line endings and a blank line
+    fs::write(
+        temp_dir.join("notes.csv"),
+        "id,note\r\n1,\"first line\nsecond line\"\r\n\r\n2,\"said \"\"hi\"\"\"\r\n",
+    )
+    .unwrap();
+    // the short row starts on line 4, after a record spanning lines 2-3
+    fs::write(temp_dir.join("broken.csv"), "id,note\n1,\"a\nb\"\n2\n").unwrap();
+
+    let program = temp_dir.join("notes.lay");
+    let dir = temp_dir.display();
+    fs::write(&program, format!("\
+import csv as csv
+the variable notes is call function csv.read with \"{dir}/notes.csv\"
+print notes.size
+for each entry in notes do
+    print get note from entry
+end for
+the variable rows is call function csv.read_rows with \"{dir}/notes.csv\"
+call function csv.write_rows with \"{dir}/copy.csv\", rows
+try
+    call function csv.read with \"{dir}/broken.csv\"
+catch error
+    print error
+end try
+")).unwrap();
+
+    let output = Command::new(layman_bin())
+        .arg("run")
+        .arg(&program)
+        .output()
+        .expect("Failed to run layman run");
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // the resolver logs imports on stdout
+    let stdout: String = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("DEBUG:"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_eq!(
+        stdout,
+        "2\nfirst line\nsecond line\nsaid \"hi\"\nInvalid CSV at line 4: expected 2 fields but found 1\n"
+    );
+
+    // rows are written back with LF endings and only the fields that need it quoted
+    assert_eq!(
+        fs::read_to_string(temp_dir.join("copy.csv")).unwrap(),
+        "id,note\n1,\"first line\nsecond line\"\n2,\"said \"\"hi\"\"\"\n"
+    );
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
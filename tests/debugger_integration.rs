@@ -0,0 +1,115 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+mod common;
+use common::{layman_bin, temp_project};
+
+const PROGRAM: &str = "\
+define function double that takes n as Number and returns Number
+  the variable result is n times 2
+  return result
+
+the variable total is 5
+the variable answer is call function double with total
+print answer
+";
+
+/// run `layman debug` in `dir` with `args`, feeding it `input`; returns stdout and the exit code
+fn debug(dir: &Path, args: &[&str], input: &[u8]) -> (String, Option<i32>) {
+    let mut child = Command::new(layman_bin())
+        .current_dir(dir)
+        .arg("debug")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run layman debug");
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (String::from_utf8(output.stdout).unwrap(), output.status.code())
+}
+
+#[test]
+fn test_terminal_debugger_stops_at_breakpoints() {
+    let dir = temp_project("layman_debug_cli", &[("main.lay", PROGRAM)]);
+
+    let commands = "break 2\ncontinue\nstack\nvars\nfinish\nprint answer\ncontinue\n";
+    let (stdout, code) = debug(&dir, &["main.lay"], commands.as_bytes());
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("main.lay:5: the variable total is 5"), "{}", stdout);
+    assert!(stdout.contains("Breakpoint at main.lay:2: the variable result is n times 2"), "{}", stdout);
+    assert!(stdout.contains("#0 double at main.lay:2\n#1 <main> at main.lay:6"), "{}", stdout);
+    assert!(stdout.contains("Locals:\n  n = 5\nGlobals:\n  double = function(double)\n  total = 5"), "{}", stdout);
+    assert!(stdout.contains("main.lay:7: print answer"), "{}", stdout);
+    assert!(stdout.contains("answer = 10"), "{}", stdout);
+    assert!(stdout.contains("10\nProgram exited with code 0"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_debug_adapter_session() {
+    let dir = temp_project("layman_debug_dap", &[("main.lay", PROGRAM)]);
+    let file = dir.join("main.lay");
+    let path = file.to_str().unwrap();
+
+    let requests = [
+        ("initialize", json!({ "adapterID": "layman" })),
+        ("launch", json!({ "program": path })),
+        ("setBreakpoints", json!({ "source": { "path": path }, "breakpoints": [{ "line": 2 }] })),
+        ("configurationDone", json!({})),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("scopes", json!({ "frameId": 0 })),
+        ("variables", json!({ "variablesReference": 1 })),
+        ("evaluate", json!({ "expression": "total", "frameId": 1 })),
+        ("next", json!({ "threadId": 1 })),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("continue", json!({ "threadId": 1 })),
+    ];
+    let mut input = Vec::new();
+    for (seq, (command, arguments)) in requests.iter().enumerate() {
+        let body = json!({ "seq": seq + 1, "type": "request", "command": command, "arguments": arguments }).to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    let (stdout, code) = debug(&dir, &["--dap"], &input);
+    assert_eq!(code, Some(0));
+
+    let mut messages: Vec<Value> = Vec::new();
+    let mut rest = stdout.as_str();
+    while let Some((header, after)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        messages.push(serde_json::from_str(&after[..length]).unwrap());
+        rest = &after[length..];
+    }
+    let response = |seq: usize| {
+        let response = messages.iter().find(|m| m["request_seq"] == seq).unwrap_or_else(|| panic!("no response to {}", seq));
+        assert_eq!(response["success"], true, "{}", response);
+        &response["body"]
+    };
+    let events: Vec<&Value> = messages.iter().filter(|m| m["type"] == "event").collect();
+
+    assert_eq!(response(1)["supportsConfigurationDoneRequest"], true);
+    assert_eq!(response(3)["breakpoints"][0]["verified"], true);
+    let frames = &response(5)["stackFrames"];
+    assert_eq!(frames[0]["name"], "double");
+    assert_eq!(frames[0]["line"], 2);
+    assert_eq!(frames[1]["name"], "<main>");
+    assert_eq!(frames[1]["line"], 6);
+    assert_eq!(response(6)["scopes"][0]["name"], "Locals");
+    assert_eq!(response(7)["variables"][0], json!({ "name": "n", "value": "5", "variablesReference": 0 }));
+    assert_eq!(response(8)["result"], "5");
+    assert_eq!(response(10)["stackFrames"][0]["line"], 3);
+
+    let stops: Vec<&Value> = events.iter().filter(|e| e["event"] == "stopped").map(|e| &e["body"]["reason"]).collect();
+    assert_eq!(stops, [&json!("breakpoint"), &json!("step")]);
+    assert!(events.iter().any(|e| e["event"] == "output" && e["body"]["output"] == "10\n"));
+    let exited = events.iter().find(|e| e["event"] == "exited").expect("no exited event");
+    assert_eq!(exited["body"]["exitCode"], 0);
+    assert_eq!(events.last().unwrap()["event"], "terminated");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
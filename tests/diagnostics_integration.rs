@@ -0,0 +1,171 @@
+use layman_lib::ast::{Location, Node};
+use layman_lib::diagnostics::{codes, Diagnostic, Span};
+use layman_lib::evaluator::RuntimeError;
+use layman_lib::lexer::Lexer;
+use layman_lib::parser::Parser;
+use serde_json::Value;
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+#[test]
+fn test_render_puts_carets_labels_and_notes_under_the_source() {
+    let source = "the variable price is 4\n\tset price to \"four\"\n";
+    let diagnostic = Diagnostic::error(codes::TYPE_MISMATCH, "cannot assign String to variable 'price' which has type Number")
+        .with_span(Span::new("shop.lay", 2, 15, 6))
+        .with_label(Span::new("shop.lay", 1, 14, 5), "declared as a Number here")
+        .with_note("use `convert` to turn text into a number");
+
+    assert_eq!(
+        diagnostic.render(&|file| (file == "shop.lay").then(|| source.to_string())),
+        "\
+error[E0201]: cannot assign String to variable 'price' which has type Number
+ --> shop.lay:2:15
+  |
+2 | \tset price to \"four\"
+  | \t             ^^^^^^
+  |
+1 | the variable price is 4
+  |              ----- declared as a Number here
+  |
+  = note: use `convert` to turn text into a number
+"
+    );
+
+    // without the file there is still the message and the place
+    assert_eq!(
+        Diagnostic::error(codes::RUNTIME, "boom").with_span(Span::new("gone.lay", 3, 1, 1)).render(&|_| None),
+        "error[E0301]: boom\n --> gone.lay:3:1\n"
+    );
+
+    // runtime errors keep their place and note
+    let mut error = RuntimeError::new("Unknown function: pritn").with_note(Some("did you mean 'print'?".to_string()));
+    error.location = Some(Location { file: "main.lay".to_string(), line: 2, column: 0, source: None });
+    let diagnostic = Diagnostic::from_runtime_error(&error);
+    assert_eq!(diagnostic.message, "Unknown function: pritn");
+    assert_eq!(diagnostic.span, Some(Span::new("main.lay", 2, 1, 1)));
+    assert_eq!(diagnostic.notes, vec!["did you mean 'print'?"]);
+}
+
+#[test]
+fn test_parser_errors_name_the_token_and_point_at_it() {
+    let source = "the variable x is 1\nfor each n in [1, 2] do\n  print n\nend for\n";
+    let tokens = Lexer::new(source, "loop.lay".to_string()).tokenize().unwrap();
+    let error = Parser::new(tokens).parse().unwrap_err();
+
+    let diagnostic = Diagnostic::from_syntax_error(codes::SYNTAX, &error);
+    assert_eq!(diagnostic.message, "Unexpected token: '['");
+    assert_eq!(diagnostic.span, Some(Span::new("loop.lay", 2, 15, 1)));
+}
+
+#[test]
+fn test_parser_keeps_going_after_syntax_errors() {
+    let source = "\
+define function total that takes items as List of Number and returns Number
+  the variable sum is 0
+  for each price in items do
+    set sum to sum plus plus price
+  end for
+  return sum
+
+the variable a is 1 times times 2
+print call function total with a list containing 1, 2
+end if
+print \"done\"
+";
+    let tokens = Lexer::new(source, "shop.lay".to_string()).tokenize().unwrap();
+    let (program, errors) = Parser::new(tokens).parse_recovering();
+
+    let diagnostics: Vec<Diagnostic> =
+        errors.iter().map(|e| Diagnostic::from_syntax_error(codes::SYNTAX, e)).collect();
+    let found: Vec<(String, Option<Span>)> = diagnostics.into_iter().map(|d| (d.message, d.span)).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Unexpected token: 'plus'".to_string(), Some(Span::new("shop.lay", 4, 25, 4))),
+            ("Unexpected token: 'times'".to_string(), Some(Span::new("shop.lay", 8, 27, 5))),
+            (
+                "Unexpected 'end'. Unmatched closing block or redundant end token.".to_string(),
+                Some(Span::new("shop.lay", 10, 1, 3))
+            ),
+        ]
+    );
+
+    // the function, with the loop around its broken line, and both prints are still there
+    let Node::Program(program) = program else {
+        panic!("expected a program");
+    };
+    let kinds: Vec<&str> = program.statements.iter().map(|s| s.kind_name()).collect();
+    assert_eq!(kinds, vec!["FunctionDeclaration", "ExpressionStatement", "ExpressionStatement"]);
+    let Node::FunctionDeclaration(total) = &program.statements[0] else {
+        unreachable!();
+    };
+    let Node::Program(body) = total.body.as_ref() else {
+        panic!("expected a function body");
+    };
+    let kinds: Vec<&str> = body.statements.iter().map(|s| s.kind_name()).collect();
+    assert_eq!(kinds, vec!["DeclareStatement", "LoopStatement", "ReturnStatement"]);
+
+    // `parse` still fails on the first one
+    let tokens = Lexer::new(source, "shop.lay".to_string()).tokenize().unwrap();
+    assert_eq!(Parser::new(tokens).parse().unwrap_err(), errors[0]);
+}
+
+#[test]
+fn test_cli_prints_snippets_or_json_lines() {
+    let dir = temp_project("layman_diagnostics_cli", &[("main.lay", "the variable count is 3\nprint count plus 1\nprint missing\n")]);
+    let file = dir.join("main.lay");
+
+    let output = Command::new(layman_bin()).arg("check").arg(&file).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!(
+        "error[E0202]: Variable 'missing' not found\n --> {}:3:7\n  |\n3 | print missing\n  |       ^^^^^^^\n",
+        file.display()
+    );
+    assert!(stderr.contains(&expected), "{}", stderr);
+
+    let output = Command::new(layman_bin())
+        .args(["--message-format", "json", "run"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stderr);
+    let diagnostic: Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(diagnostic["code"], "E0202");
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["span"]["file"], file.display().to_string());
+    assert_eq!(diagnostic["span"]["start"], serde_json::json!({ "line": 3, "column": 7 }));
+    assert_eq!(diagnostic["span"]["end"], serde_json::json!({ "line": 3, "column": 14 }));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_runtime_errors_point_at_the_failing_expression() {
+    // a directory with a space in it, which a `file:line:column:` prefix could not carry
+    let dir = temp_project("layman diagnostics runtime", &[("main.lay", "the variable items is a list containing 1\nprint items[5]\n")]);
+    let file = dir.join("main.lay");
+
+    let output = Command::new(layman_bin()).args(["--message-format", "json", "run"]).arg(&file).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostic: Value = serde_json::from_str(String::from_utf8_lossy(&output.stderr).trim()).unwrap();
+    assert_eq!(diagnostic["code"], "E0301");
+    assert_eq!(diagnostic["message"], "List index 5 out of bounds (length 1)");
+    assert_eq!(diagnostic["span"]["file"], file.display().to_string());
+    assert_eq!(diagnostic["span"]["start"], serde_json::json!({ "line": 2, "column": 7 }));
+
+    // a missing import points at the import
+    fs::write(&file, "import \"nowhere\"\n").unwrap();
+    let output = Command::new(layman_bin()).args(["--message-format", "json", "run"]).arg(&file).output().unwrap();
+    let diagnostic: Value = serde_json::from_str(String::from_utf8_lossy(&output.stderr).trim()).unwrap();
+    assert_eq!(diagnostic["code"], "E0101");
+    assert_eq!(diagnostic["span"]["start"], serde_json::json!({ "line": 1, "column": 1 }));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+use common::layman_bin;
+
+/// a package of two modules, one importing the other
+fn write_package(dir: &Path, expected: &str) {
+    fs::create_dir_all(dir.join("src/geometry")).unwrap();
+    fs::write(
+        dir.join("src/geometry/canvas.lay"),
+        "\
+# part of the geometry package
+
+# something that can be drawn
+define class Canvas that has
+  property size which is Number
+
+  # how many pixels fit at a `scale`
+  define function pixels that takes self and scale and returns Number
+    return self.size times scale
+",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/main.lay"),
+        format!(
+            "\
+import file \"geometry/canvas.lay\" as canvas
+
+# a shape on the plane
+define type Shape as either Circle with radius of type Number or Square with side of type Number
+
+# the area of a rectangle
+#
+# ```
+# print call function area with 2, 3
+# => {}
+# ```
+define function area that takes width and height
+  return width times height
+
+print call function area with 4, 5
+",
+            expected
+        ),
+    )
+    .unwrap();
+}
+
+fn doc(dir: &Path, args: &[&str]) -> (String, Option<i32>) {
+    let output = Command::new(layman_bin()).current_dir(dir).arg("doc").args(args).output().expect("Failed to run layman doc");
+    (String::from_utf8(output.stdout).unwrap(), output.status.code())
+}
+
+#[test]
+fn test_doc_writes_linked_pages() {
+    let dir = std::env::temp_dir().join("layman_doc_pages");
+    let _ = fs::remove_dir_all(&dir);
+    write_package(&dir, "6");
+
+    let (stdout, code) = doc(&dir, &[]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("Documented 2 module(s) in docs"), "{}", stdout);
+    let index = fs::read_to_string(dir.join("docs/index.md")).unwrap();
+    assert!(index.contains("- [geometry/canvas](geometry.canvas.md): [Canvas](geometry.canvas.md#canvas)"), "{}", index);
+    let main = fs::read_to_string(dir.join("docs/main.md")).unwrap();
+    assert!(main.contains("Imports: [geometry/canvas](geometry.canvas.md)"), "{}", main);
+    assert!(main.contains("takes `width` as any and `height` as any, returns number\n\nthe area of a rectangle"), "{}", main);
+    assert!(main.contains("- `Circle` with `radius` as Number"), "{}", main);
+    let canvas = fs::read_to_string(dir.join("docs/geometry.canvas.md")).unwrap();
+    assert!(canvas.contains("- `size` as number"), "{}", canvas);
+    assert!(canvas.contains("#### Canvas.pixels <a id=\"canvas-pixels\"></a>\n\ntakes `scale` as any, returns number\n\nhow many pixels fit at a `scale`"), "{}", canvas);
+    // a comment above a blank line is not the class's documentation
+    assert!(canvas.contains("### Canvas <a id=\"canvas\"></a>\n\nsomething that can be drawn\n"), "{}", canvas);
+    assert!(!canvas.contains("geometry package"), "{}", canvas);
+
+    let (_, code) = doc(&dir, &["--format", "html", "--output", "site"]);
+    assert_eq!(code, Some(0));
+    let html = fs::read_to_string(dir.join("site/main.html")).unwrap();
+    assert!(html.contains("<h3 id=\"area\">area</h3>"), "{}", html);
+    assert!(html.contains("<a href=\"geometry.canvas.html\">geometry/canvas</a>"), "{}", html);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_doc_examples_run_as_tests() {
+    let dir = std::env::temp_dir().join("layman_doc_examples");
+    let _ = fs::remove_dir_all(&dir);
+    write_package(&dir, "6");
+    let (stdout, code) = doc(&dir, &["--test"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("test src/main.lay:8 (area) ... ok"), "{}", stdout);
+
+    write_package(&dir, "7");
+    let (stdout, code) = doc(&dir, &["--test"]);
+    assert_eq!(code, Some(1), "{}", stdout);
+    assert!(stdout.contains("test src/main.lay:8 (area) ... FAILED\n  expected:\n  7\n  printed:\n  6"), "{}", stdout);
+    assert!(stdout.contains("1 example(s): 0 passed, 1 failed"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,154 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+fn run_fmt(args: &[&str]) -> (String, String, Option<i32>) {
+    let output = Command::new(layman_bin())
+        .arg("fmt")
+        .args(args)
+        .output()
+        .expect("Failed to run layman fmt");
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code(),
+    )
+}
+
+const MESSY: &str = "\
+# totals for the shop
+
+
+ the variable prices is a list containing 3, 4   # in pounds
+define function total that takes items as List of Number and returns Number
+    the variable sum is 0
+
+
+    for each price in items do
+        set sum to sum plus price
+    end for
+    # done adding
+    return sum
+print call function total with argument prices
+if call function total with prices is greater than 5 then
+      print 'expensive'
+otherwise
+      print \"cheap\"
+end if
+the variable stock is a dictionary containing \"pears\" is 2
+stock[\"pears\"] = 3
+";
+
+const CANONICAL: &str = "\
+# totals for the shop
+
+the variable prices is a list containing 3, 4 # in pounds
+
+define function total that takes items as List of Number and returns Number
+  the variable sum is 0
+
+  for each price in items do
+    set sum to sum plus price
+  end for
+  # done adding
+  return sum
+
+print call function total with prices
+if call function total with prices is greater than 5 then
+  print \"expensive\"
+else
+  print \"cheap\"
+end if
+the variable stock is a dictionary containing \"pears\" is 2
+stock[\"pears\"] = 3
+";
+
+#[test]
+fn test_fmt_rewrites_to_canonical_layout_and_keeps_comments() {
+    let dir = temp_project("layman_fmt_canonical", &[("shop.lay", MESSY)]);
+    let file = dir.join("shop.lay");
+
+    let (stdout, stderr, code) = run_fmt(&[file.to_str().unwrap()]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+    assert!(stdout.contains("formatted"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), CANONICAL);
+
+    // formatting again changes nothing
+    let (stdout, _, code) = run_fmt(&[file.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "");
+    assert_eq!(fs::read_to_string(&file).unwrap(), CANONICAL);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt_check_lists_unformatted_files_without_writing() {
+    let dir = temp_project(
+        "layman_fmt_check",
+        &[("messy.lay", MESSY), ("tidy.lay", CANONICAL), ("notes.txt", "not layman")],
+    );
+
+    let (stdout, _, code) = run_fmt(&["--check", dir.to_str().unwrap()]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains("messy.lay"));
+    assert!(!stdout.contains("tidy.lay"));
+    assert_eq!(fs::read_to_string(dir.join("messy.lay")).unwrap(), MESSY);
+
+    let (_, _, code) = run_fmt(&["--check", dir.join("tidy.lay").to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt_leaves_files_that_do_not_parse() {
+    let broken = "the variable x is\nfor each n in do\n";
+    let dir = temp_project("layman_fmt_broken", &[("broken.lay", broken)]);
+    let file = dir.join("broken.lay");
+
+    let (_, stderr, code) = run_fmt(&[file.to_str().unwrap()]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("broken.lay"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), broken);
+
+    // the failure is a diagnostic, so --message-format json prints it as json
+    let (_, stderr, code) = run_fmt(&["--message-format", "json", file.to_str().unwrap()]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.lines().next().unwrap().starts_with("{\"code\":\"E0003\""), "{}", stderr);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt_keeps_prose_lines_and_preserves_meaning() {
+    let source = "\
+the following lines greet people.
+the variable names is a list containing \"Ann\", \"Bo\"
+for each name in names do
+    print \"Hello \" plus name
+end for
+";
+    let dir = temp_project("layman_fmt_prose", &[("greet.lay", source)]);
+    let file = dir.join("greet.lay");
+
+    let (_, stderr, code) = run_fmt(&[file.to_str().unwrap()]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "\
+the following lines greet people.
+the variable names is a list containing \"Ann\", \"Bo\"
+for each name in names do
+  print \"Hello \" plus name
+end for
+"
+    );
+
+    let output = Command::new(layman_bin()).arg(&file).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello Ann\nHello Bo\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use layman_lib::ast::Node;
+use layman_lib::evaluator::Evaluator;
+use layman_lib::lexer::Lexer;
+use layman_lib::parser::Parser;
+use layman_lib::stdlib::http::{request_from_args, Request, Response, Transport};
+use layman_lib::types::{Shared, Value};
+
+mod common;
+use common::layman_bin;
+
+/// serve `count` connections on a loopback port, answering each with the
+/// request line and body it received; returns the port
+fn echo_server(count: usize) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 1024];
+            // read the head, then as much body as Content-Length announces
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                raw.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&raw).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|l| l.strip_prefix("Content-Length: "))
+                        .map(|v| v.trim().parse::<usize>().unwrap())
+                        .unwrap_or(0);
+                    if raw.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            let text = String::from_utf8_lossy(&raw).to_string();
+            let request_line = text.lines().next().unwrap_or("").to_string();
+            let body = &text[text.find("\r\n\r\n").unwrap() + 4..];
+            let content_type = text
+                .lines()
+                .find_map(|l| l.strip_prefix("Content-Type: "))
+                .unwrap_or("none")
+                .to_string();
+            let reply = if request_line.contains("/missing") {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found".to_string()
+            } else if request_line.contains("/chunked") {
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n".to_string()
+            } else if request_line.contains("/json") {
+                let json = "{\"name\": \"Ada\", \"tags\": [\"x\", \"y\"], \"age\": 36}";
+                format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", json.len(), json)
+            } else {
+                let echo = format!("{} | {} | {}", request_line, content_type, body);
+                format!("HTTP/1.1 200 OK\r\nX-Echo: yes\r\nContent-Length: {}\r\n\r\n{}", echo.len(), echo)
+            };
+            stream.write_all(reply.as_bytes()).unwrap();
+        }
+    });
+    port
+}
+
+#[test]
+fn test_plain_http_against_loopback_server() {
+    let port = echo_server(5);
+    let temp_dir = std::env::temp_dir().join("layman_http_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    let program = temp_dir.join("client.lay");
+    fs::write(&program, format!("\
+import http as http
+the variable base is \"http://127.0.0.1:{port}\"
+the variable response is fetch base plus \"/hello?x=1\"
+print get status from response
+print get body from response
+the variable headers is get headers from response
+print headers[\"x-echo\"]
+the variable sent is send a POST to base plus \"/orders\" with body \"id=7\"
+print get body from sent
+the variable missing is fetch base plus \"/missing\"
+print get status from missing
+print get ok from missing
+print get body from fetch base plus \"/chunked\"
+the variable person is call function http.get_json with base plus \"/json\"
+print get name from person
+print get tags from person
+try
+    fetch \"http://127.0.0.1:1/unreachable\"
+catch error
+    print \"caught connection failure\"
+end try
+try
+    fetch \"https://example.com\"
+catch error
+    print error
+end try
+")).unwrap();
+
+    let output = Command::new(layman_bin())
+        .arg("run")
+        .arg(&program)
+        .output()
+        .expect("Failed to run layman run");
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    // the resolver logs imports on stdout
+    let stdout: String = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("DEBUG:"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_eq!(
+        stdout,
+        "200\n\
+         GET /hello?x=1 HTTP/1.1 | none | \n\
+         yes\n\
+         POST /orders HTTP/1.1 | text/plain; charset=utf-8 | id=7\n\
+         404\n\
+         false\n\
+         hello world\n\
+         Ada\n\
+         [x, y]\n\
+         caught connection failure\n\
+         GET https://example.com failed: https is not supported, only http:// URLs\n"
+    );
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+/// answers every request with its method, URL and body, recording what it saw
+struct StubTransport {
+    seen: Mutex<Vec<String>>,
+}
+
+impl Transport for StubTransport {
+    fn send(&self, request: &Request) -> Result<Response, String> {
+        let headers: Vec<String> = request.headers.iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        self.seen.lock().unwrap().push(format!("{} {} [{}]", request.method, request.url, headers.join(",")));
+        if request.url.ends_with("/down") {
+            return Err(format!("{} {} failed: connection refused", request.method, request.url));
+        }
+        Ok(Response {
+            status: 201,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: request.body.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[test]
+fn test_stub_transport_and_json_helpers() {
+    let source = "\
+the variable payload is a dictionary containing \"b\" is 2, \"a\" is \"one\"
+the variable text is call function __json_format with payload
+the variable headers is a dictionary containing \"Content-Type\" is \"application/json\"
+the variable response is send a PUT to \"http://api.test/items/1\" with body text and headers headers
+print get status from response
+print get body from response
+the variable parsed is call function __json_parse with get body from response
+print get a from parsed
+try
+    fetch \"http://api.test/down\"
+catch error
+    print error
+end try
+";
+    let tokens = Lexer::new(source, "main.lay".to_string()).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse().unwrap();
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let captured = output.clone();
+    let mut evaluator = Evaluator::with_output_callback(move |s: &str| {
+        captured.lock().unwrap().push(s.to_string());
+    });
+    let transport = Arc::new(StubTransport { seen: Mutex::new(Vec::new()) });
+    evaluator.set_transport(transport.clone());
+
+    if let Node::Program(program) = &ast {
+        for stmt in &program.statements {
+            evaluator.evaluate(stmt).unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        vec![
+            "201",
+            "{\"a\":\"one\",\"b\":2}",
+            "one",
+            "GET http://api.test/down failed: connection refused",
+        ]
+    );
+    assert_eq!(
+        *transport.seen.lock().unwrap(),
+        vec![
+            "PUT http://api.test/items/1 [Content-Type=application/json]",
+            "GET http://api.test/down []",
+        ]
+    );
+}
+
+#[test]
+fn test_urls_and_headers_that_would_split_the_request_are_refused() {
+    let source = "\
+try
+    fetch \"http://api.test/items HTTP/1.1\"
+catch error
+    print error
+end try
+";
+    let tokens = Lexer::new(source, "main.lay".to_string()).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse().unwrap();
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let captured = output.clone();
+    let mut evaluator = Evaluator::with_output_callback(move |s: &str| {
+        captured.lock().unwrap().push(s.to_string());
+    });
+    let transport = Arc::new(StubTransport { seen: Mutex::new(Vec::new()) });
+    evaluator.set_transport(transport.clone());
+
+    if let Node::Program(program) = &ast {
+        for stmt in &program.statements {
+            evaluator.evaluate(stmt).unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        vec!["Cannot send a request to \"http://api.test/items HTTP/1.1\": the URL contains a space or control character"]
+    );
+    assert!(transport.seen.lock().unwrap().is_empty());
+
+    let mut headers = HashMap::new();
+    headers.insert("X-Note".to_string(), Value::String("hi\r\nX-Injected: yes".to_string()));
+    let args = [
+        Value::String("get".to_string()),
+        Value::String("http://api.test/items".to_string()),
+        Value::Nothing,
+        Value::Dictionary(Shared::new(headers)),
+    ];
+    let error = request_from_args(&args).err().unwrap();
+    assert_eq!(error, "The request header \"X-Note\" contains a line break");
+}
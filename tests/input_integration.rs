@@ -0,0 +1,119 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use layman_lib::ast::Node;
+use layman_lib::evaluator::Evaluator;
+use layman_lib::lexer::Lexer;
+use layman_lib::parser::Parser;
+use layman_lib::stdlib::io::ScriptedInput;
+
+mod common;
+use common::layman_bin;
+
+const PROGRAM: &str = "\
+the variable name is ask \"Name? \"
+print \"Hello, \" plus name
+for each line in the lines of input do
+    print \"got \" plus line
+end for
+try
+    the variable extra is ask \"More? \"
+catch error
+    print \"no more input\"
+end try
+";
+
+fn run_with_stdin(program: &PathBuf, stdin: &str) -> (String, Option<i32>) {
+    let mut child = Command::new(layman_bin())
+        .arg("run")
+        .arg(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run layman run");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (String::from_utf8_lossy(&output.stdout).to_string(), output.status.code())
+}
+
+#[test]
+fn test_ask_and_lines_read_piped_stdin() {
+    let temp_dir = std::env::temp_dir().join("layman_input_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    let program = temp_dir.join("greet.lay");
+    fs::write(&program, PROGRAM).unwrap();
+
+    let (stdout, code) = run_with_stdin(&program, "Ada\nfirst\r\nsecond");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "Name? Hello, Ada\ngot first\ngot second\nMore? no more input\n");
+
+    // reading everything at once keeps the line endings
+    let program = temp_dir.join("all.lay");
+    fs::write(&program, "the variable text is read all input\nprint text\n").unwrap();
+    let (stdout, code) = run_with_stdin(&program, "a b\nc\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "a b\nc\n\n");
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_lines_of_input_are_handled_as_they_arrive() {
+    let temp_dir = std::env::temp_dir().join("layman_input_stream_test");
+    fs::create_dir_all(&temp_dir).unwrap();
+    let program = temp_dir.join("echo.lay");
+    fs::write(&program, "for each line in the lines of input do\n    print \"got \" plus line\nend for\n").unwrap();
+
+    let mut child = Command::new(layman_bin())
+        .arg("run")
+        .arg(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run layman run");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    // each line is answered while the input is still open
+    for line in ["first", "second"] {
+        writeln!(stdin, "{}", line).unwrap();
+        stdin.flush().unwrap();
+        let mut answer = String::new();
+        stdout.read_line(&mut answer).unwrap();
+        assert_eq!(answer, format!("got {}\n", line));
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_scripted_input_source() {
+    let tokens = Lexer::new(PROGRAM, "main.lay".to_string()).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse().unwrap();
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let captured = output.clone();
+    let mut evaluator = Evaluator::with_output_callback(move |s: &str| {
+        captured.lock().unwrap().push(s.to_string());
+    });
+    evaluator.set_input(Arc::new(ScriptedInput::new("Grace\nonly line\n")));
+
+    if let Node::Program(program) = &ast {
+        for stmt in &program.statements {
+            evaluator.evaluate(stmt).unwrap();
+        }
+    }
+
+    assert_eq!(
+        *output.lock().unwrap(),
+        vec!["Name? ", "Hello, Grace", "got only line", "More? ", "no more input"]
+    );
+}
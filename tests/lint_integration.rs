@@ -0,0 +1,154 @@
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+/// lint with JSON output; returns each finding as "line:column: severity[code]: message", stderr and the exit code
+fn run_lint(path: &PathBuf) -> (Vec<String>, String, Option<i32>) {
+    let output = Command::new(layman_bin())
+        .args(["lint", "--message-format", "json"])
+        .arg(path)
+        .output()
+        .expect("Failed to run layman lint");
+    let findings = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            let finding: Value = serde_json::from_str(line).unwrap();
+            let start = &finding["span"]["start"];
+            format!(
+                "{}:{}: {}[{}]: {}",
+                start["line"], start["column"], finding["severity"].as_str().unwrap(), finding["code"].as_str().unwrap(),
+                finding["message"].as_str().unwrap()
+            )
+        })
+        .collect();
+    (findings, String::from_utf8_lossy(&output.stderr).to_string(), output.status.code())
+}
+
+const SHOP: &str = "\
+from file \"prices.lay\" import tax, discount
+define function total that takes items as List of Number and unused as Number
+  the variable sum is 0
+  the variable spare is 1
+  for each price in items do
+    the variable sum is sum plus price
+  end for
+  return sum
+  print \"done\"
+
+if 1 is 1 then
+end if
+while true do
+  return call function total with a list containing 1, tax
+end while
+";
+
+#[test]
+fn test_lint_reports_each_rule_with_position() {
+    let dir = temp_project("layman_lint_rules", &[("shop.lay", SHOP)]);
+    let (findings, _, code) = run_lint(&dir.join("shop.lay"));
+    // warnings alone do not fail the run
+    assert_eq!(code, Some(0));
+
+    // `tax` is used, and `while true` is allowed
+    let expected = [
+        "1:1: warning[unused-import]: 'discount' is imported but never used",
+        "2:1: warning[missing-return-type]: function 'total' returns a value but does not declare its return type",
+        "2:1: warning[unused-parameter]: parameter 'unused' is never used",
+        "4:3: warning[unused-variable]: variable 'spare' is never used",
+        "6:5: warning[shadowing]: 'sum' hides the variable declared on line 3",
+        // the loop's `sum` is its own variable, so the outer one is never updated
+        "6:5: warning[unused-variable]: variable 'sum' is never used",
+        "9:3: warning[unreachable-code]: this code can never run",
+        "11:1: warning[constant-condition]: condition is made only of constants",
+        "11:1: warning[empty-block]: empty 'then' block",
+    ];
+    assert_eq!(findings, expected);
+
+    // people get the findings under their source lines, with a count
+    let output = Command::new(layman_bin()).arg("lint").arg(dir.join("shop.lay")).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("warning[shadowing]: 'sum' hides the variable declared on line 3\n --> "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("3 |   the variable sum is 0\n  |   --------------------- the hidden variable\n"), "{}", stdout);
+    assert!(stdout.ends_with("0 error(s), 9 warning(s)\n"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lint_allow_comments_silence_one_line() {
+    let source = "\
+define function greet that takes name and loud
+  # lint: allow unused-parameter
+  print \"hi\"
+  return 1 # lint: allow
+define function shout that takes words as String
+  print \"HEY\" # lint: allow unused-parameter
+";
+    let dir = temp_project("layman_lint_allow", &[("greet.lay", source)]);
+    let (findings, _, code) = run_lint(&dir.join("greet.lay"));
+    assert_eq!(code, Some(0));
+    // the comments sit on other lines than the findings, so they silence nothing
+    assert!(findings.contains(&"1:1: warning[unused-parameter]: parameter 'name' is never used".to_string()));
+    assert!(findings.contains(&"5:1: warning[unused-parameter]: parameter 'words' is never used".to_string()));
+
+    let silenced = "\
+# lint: allow unused-parameter, missing-return-type
+define function greet that takes name and returns String
+  return \"hi\"
+";
+    fs::write(dir.join("greet.lay"), silenced).unwrap();
+    let (findings, _, code) = run_lint(&dir.join("greet.lay"));
+    assert_eq!(code, Some(0));
+    assert!(findings.is_empty(), "{:?}", findings);
+
+    fs::write(dir.join("greet.lay"), "print 1 # lint: allow no-such-rule\n").unwrap();
+    let (_, stderr, code) = run_lint(&dir.join("greet.lay"));
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("unknown lint rule 'no-such-rule'"), "{}", stderr);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lint_severities_come_from_layman_toml() {
+    let manifest = "\
+[package]
+name = \"shop\"
+version = \"0.1.0\"
+
+[lint]
+shadowing = \"error\"
+unused-variable = \"off\"
+";
+    let source = "\
+the variable x is 1
+the variable x is 2
+print x
+define function f that takes n as Number and returns Number
+  the variable spare is n
+  return n
+";
+    let dir = temp_project("layman_lint_config", &[("layman.toml", manifest)]);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/main.lay"), source).unwrap();
+
+    let (findings, stderr, code) = run_lint(&dir);
+    assert_eq!(code, Some(1));
+    assert_eq!(findings, vec!["2:1: error[shadowing]: 'x' is declared again; it was already declared on line 1"]);
+    assert!(stderr.contains("1 lint error(s)"));
+
+    fs::write(dir.join("layman.toml"), manifest.replace("\"off\"", "\"loud\"")).unwrap();
+    let (_, stderr, code) = run_lint(&dir);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("lint rule 'unused-variable' must be \"off\", \"warning\" or \"error\""), "{}", stderr);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,190 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+mod common;
+use common::layman_bin;
+
+/// send a scripted session to `layman lsp`; returns every message it wrote and its exit code
+fn run_session(messages: &[Value]) -> (Vec<Value>, Option<i32>) {
+    let mut child = Command::new(layman_bin())
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run layman lsp");
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    let mut replies = Vec::new();
+    let mut rest = String::from_utf8(output.stdout).unwrap();
+    while let Some((header, after)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        replies.push(serde_json::from_str(&after[..length]).unwrap());
+        rest = after[length..].to_string();
+    }
+    (replies, output.status.code())
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn at(uri: &str, line: u64, character: u64) -> Value {
+    json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+}
+
+fn reply(replies: &[Value], id: u64) -> &Value {
+    &replies.iter().find(|r| r["id"] == id).unwrap_or_else(|| panic!("no reply to {}", id))["result"]
+}
+
+const PROGRAM: &str = "\
+import shapes
+define class Person that has
+  property name which is Text
+  property age which is Number
+the variable person is a new Person with
+  name is \"Ada\"
+  age is 36
+the variable total is call function area with 2, 3
+print person.name
+print total plus \"x\" minus 1
+";
+
+#[test]
+fn test_scripted_editor_session() {
+    let temp_dir = std::env::temp_dir().join("layman_lsp_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    let temp_dir = temp_dir.canonicalize().unwrap();
+    fs::write(
+        temp_dir.join("shapes.lay"),
+        "define function area that takes width as Number and height as Number and returns Number\n  return width times height\n",
+    )
+    .unwrap();
+    let uri = format!("file://{}", temp_dir.join("main.lay").display());
+    let shapes_uri = format!("file://{}", temp_dir.join("shapes.lay").display());
+
+    let (replies, code) = run_session(&[
+        request(1, "initialize", json!({})),
+        notification("initialized", json!({})),
+        notification("textDocument/didOpen", json!({
+            "textDocument": { "uri": uri, "languageId": "layman", "version": 1, "text": PROGRAM }
+        })),
+        request(2, "textDocument/hover", at(&uri, 9, 8)),
+        request(3, "textDocument/hover", at(&uri, 7, 37)),
+        request(4, "textDocument/hover", at(&uri, 1, 15)),
+        request(5, "textDocument/definition", at(&uri, 7, 37)),
+        request(6, "textDocument/definition", at(&uri, 0, 9)),
+        // while the line is half typed the last good analysis still answers
+        notification("textDocument/didChange", json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": PROGRAM.replace("print person.name", "print person.") }]
+        })),
+        request(7, "textDocument/completion", at(&uri, 8, 13)),
+        request(8, "textDocument/completion", at(&uri, 9, 6)),
+        request(9, "textDocument/formatting", json!({})),
+        request(10, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+    assert_eq!(code, Some(0));
+
+    assert_eq!(reply(&replies, 1)["capabilities"]["hoverProvider"], true);
+
+    let diagnostics: Vec<&Value> = replies.iter().filter(|r| r["method"] == "textDocument/publishDiagnostics").collect();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        diagnostics[0]["params"]["diagnostics"],
+        json!([{
+            "range": { "start": { "line": 9, "character": 17 }, "end": { "line": 9, "character": 20 } },
+            "severity": 1,
+            "code": "E0201",
+            "source": "layman",
+            "message": "minus operator requires number on left, got text",
+        }])
+    );
+    let parse_error = &diagnostics[1]["params"]["diagnostics"][0];
+    assert_eq!(parse_error["range"]["start"], json!({ "line": 8, "character": 13 }));
+
+    assert_eq!(reply(&replies, 2)["contents"]["value"], "```layman\nthe variable total: number\n```");
+    assert_eq!(reply(&replies, 3)["contents"]["value"], "```layman\nfunction area(number, number) -> number\n```");
+    assert_eq!(
+        reply(&replies, 4)["contents"]["value"],
+        "```layman\nclass Person\n  property age: number\n  property name: text\n```"
+    );
+    assert_eq!(reply(&replies, 5)["uri"], shapes_uri.as_str());
+    assert_eq!(reply(&replies, 5)["range"]["start"], json!({ "line": 0, "character": 0 }));
+    assert_eq!(reply(&replies, 6)["uri"], shapes_uri.as_str());
+
+    let labels = |id| -> Vec<String> {
+        reply(&replies, id).as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(labels(7), vec!["age", "name"]);
+    let names = labels(8);
+    for expected in ["area", "person", "total", "Person", "if", "the"] {
+        assert!(names.contains(&expected.to_string()), "missing {} in {:?}", expected, names);
+    }
+
+    let unsupported = replies.iter().find(|r| r["id"] == 9).unwrap();
+    assert_eq!(unsupported["error"]["code"], -32601);
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_exit_without_shutdown_fails() {
+    let (replies, code) = run_session(&[request(1, "initialize", json!({})), notification("exit", Value::Null)]);
+    assert_eq!(replies.len(), 1);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn test_names_resolve_within_their_own_function() {
+    let temp_dir = std::env::temp_dir().join("layman_lsp_scope_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    let uri = format!("file://{}", temp_dir.join("main.lay").display());
+    let program = "\
+the variable value is 5
+define function second that returns Text
+  the variable value is \"two\"
+  return value
+define function first that returns Number
+  return value times 2
+";
+
+    let (replies, code) = run_session(&[
+        request(1, "initialize", json!({})),
+        notification("textDocument/didOpen", json!({
+            "textDocument": { "uri": uri, "languageId": "layman", "version": 1, "text": program }
+        })),
+        request(2, "textDocument/hover", at(&uri, 3, 10)),
+        request(3, "textDocument/hover", at(&uri, 5, 10)),
+        request(4, "textDocument/definition", at(&uri, 5, 10)),
+        request(5, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+    assert_eq!(code, Some(0));
+
+    assert_eq!(reply(&replies, 2)["contents"]["value"], "```layman\nthe variable value: text\n```");
+    // the variable inside second is not visible from first
+    assert_eq!(reply(&replies, 3)["contents"]["value"], "```layman\nthe variable value: number\n```");
+    assert_eq!(reply(&replies, 4)["range"]["start"], json!({ "line": 0, "character": 0 }));
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+const PROGRAM: &str = "\
+define function square that takes n as Number and returns Number
+  return n times n
+
+define function work that takes n as Number and returns Number
+  the variable items is a list containing 1, 2, 3
+  for each i in items do
+    print call function square with i
+  end for
+  return n
+
+print call function work with 10
+print call function square with 4
+";
+
+#[test]
+fn test_profile_counts_calls_and_statements() {
+    let dir = temp_project("layman_profile_steps", &[("main.lay", PROGRAM)]);
+
+    let output = Command::new(layman_bin())
+        .current_dir(&dir)
+        .args(["profile", "--steps", "--folded", "stacks.txt", "main.lay"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "\
+1
+4
+9
+10
+16
+
+Function     Calls  Statements  Own statements
+work             1           9               6
+square           4           4               4
+<main>           1          12               2
+"
+    );
+    let folded = fs::read_to_string(dir.join("stacks.txt")).unwrap();
+    assert_eq!(folded, "<main> 2\n<main>;square 1\n<main>;work 6\n<main>;work;square 3\n");
+
+    // timed runs differ from run to run, but carry the same columns
+    let output = Command::new(layman_bin()).current_dir(&dir).args(["profile", "main.lay"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Own statements   Time (ms)   Own time (ms)"), "{}", stdout);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::layman_bin;
+
+const PROGRAM: &str = "\
+the variable args is the arguments of the program
+for each arg in args do
+    print arg
+end for
+the variable greeting is the environment variable \"LAYMAN_TEST_GREETING\"
+if greeting exists then
+    print greeting
+end if
+if args.size is 0 then
+    exit
+end if
+exit with code 7
+";
+
+#[test]
+fn test_arguments_environment_and_exit_code() {
+    let temp_dir = std::env::temp_dir().join("layman_program_context_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    let program = temp_dir.join("tool.lay");
+    fs::write(&program, PROGRAM).unwrap();
+
+    // 1. layman run file.lay -- args
+    let output = Command::new(layman_bin())
+        .arg("run")
+        .arg(&program)
+        .arg("--")
+        .arg("first")
+        .arg("two words")
+        .env("LAYMAN_TEST_GREETING", "hello")
+        .output()
+        .expect("Failed to run layman run");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "first\ntwo words\nhello\n");
+    assert_eq!(output.status.code(), Some(7));
+
+    // 2. no arguments and no variable: plain "exit" succeeds
+    let output = Command::new(layman_bin())
+        .arg("run")
+        .arg(&program)
+        .env_remove("LAYMAN_TEST_GREETING")
+        .output()
+        .expect("Failed to run layman run");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(output.status.code(), Some(0));
+
+    // 3. compiled executables receive all of their arguments
+    let executable = temp_dir.join("tool");
+    let status = Command::new(layman_bin())
+        .arg("compile")
+        .arg(&program)
+        .arg("--output")
+        .arg(&executable)
+        .status()
+        .expect("Failed to run layman compile");
+    assert!(status.success());
+    let output = Command::new(&executable)
+        .arg("--verbose")
+        .env_remove("LAYMAN_TEST_GREETING")
+        .output()
+        .expect("Failed to run compiled program");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "--verbose\n");
+    assert_eq!(output.status.code(), Some(7));
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
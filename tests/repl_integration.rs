@@ -0,0 +1,94 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+mod common;
+use common::layman_bin;
+
+/// feed a session to `layman repl` on piped stdin; returns stdout, stderr and the exit code
+fn run_session(session: &str) -> (String, String, Option<i32>) {
+    let mut child = Command::new(layman_bin())
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run layman repl");
+    child.stdin.take().unwrap().write_all(session.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn test_session_keeps_state_between_entries() {
+    let (stdout, stderr, code) = run_session(
+        "\
+the variable total is 2
+total plus 1
+:type total plus 1
+if total is 2 then
+  print \"two\"
+end if
+define function double that takes n as Number and returns Number
+  return n times 2
+
+call function double with total
+the variable broken is total minus \"a\"
+broken
+:reset
+total
+",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "3\nnumber\ntwo\n4\nSession reset\n");
+    // a rejected entry defines nothing, and :reset forgets everything
+    assert_eq!(
+        stderr,
+        "Error: Type errors:\n  <repl:6>:1:35: minus operator requires number on right, got text\n\
+         Error: Type errors:\n  <repl:7>:1:0: Variable 'broken' not found\n\
+         Error: Type errors:\n  <repl:1>:1:0: Variable 'total' not found\n"
+    );
+}
+
+#[test]
+fn test_load_ast_and_unknown_commands() {
+    let temp_dir = std::env::temp_dir().join("layman_repl_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+    let library = temp_dir.join("shapes.lay");
+    fs::write(&library, "define function square that takes n as Number and returns Number\n  return n times n\n").unwrap();
+
+    let (stdout, stderr, code) = run_session(&format!(
+        ":load {}\ncall function square with 7\n:ast 42\n:bogus\n:quit\nprint \"not reached\"\n",
+        library.display()
+    ));
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with("49\n{\n  \"Program\""), "stdout: {}", stdout);
+    assert!(stdout.contains("\"Number\": 42.0"), "stdout: {}", stdout);
+    assert!(!stdout.contains("not reached"));
+    assert_eq!(stderr, "Error: Unknown command ':bogus' (try :help)\n");
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_one_line_definitions_do_not_wait_for_more() {
+    let (stdout, stderr, code) = run_session(
+        "\
+define variable x is 5
+:type x
+define type Status as either Active or Inactive
+:type x plus 1
+x plus 1
+",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "number\nnumber\n6\n");
+}
@@ -0,0 +1,60 @@
+use layman_lib::suggest;
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::layman_bin;
+
+#[test]
+fn test_hints_pick_the_closest_name() {
+    assert_eq!(suggest::distance("pritn", "print"), 1);
+    assert_eq!(suggest::distance("totl", "total"), 1);
+    assert_eq!(suggest::distance("kitten", "sitting"), 3);
+
+    let names = ["total", "totals", "count", "price"];
+    assert_eq!(suggest::closest("totl", names), Some("total"));
+    assert_eq!(suggest::closest("Count", names), Some("count"));
+    assert_eq!(suggest::closest("weight", names), None);
+    // a single letter is one edit away from everything
+    assert_eq!(suggest::closest("x", ["y"]), None);
+
+    assert_eq!(suggest::hint("totl", names).as_deref(), Some("did you mean 'total'?"));
+    // keywords are the fallback for words that are not names at all
+    assert_eq!(suggest::hint("retrun", names).as_deref(), Some("did you mean 'return'?"));
+    assert_eq!(suggest::hint("let", names).as_deref(), Some("layman writes this as `the variable <name> is <value>`"));
+    assert_eq!(suggest::hint("weight", names), None);
+}
+
+#[test]
+fn test_check_suggests_names_members_and_phrasing() {
+    let dir = std::env::temp_dir().join("layman_suggest_cli");
+    fs::create_dir_all(&dir).unwrap();
+    let cases = [
+        // close matches are notes under the error
+        ("the variable total is 1\nprint totl\n", "Variable 'totl' not found\n"),
+        ("the variable total is 1\nprint totl\n", "= note: did you mean 'total'?"),
+        (
+            "define function greet that takes name as String\n  print name\ncall function gret with \"a\"\n",
+            "= note: did you mean 'greet'?",
+        ),
+        ("the variable items is a list containing 1\ncall ad on items with 2\n", "List has no method 'ad'\n"),
+        ("the variable items is a list containing 1\ncall ad on items with 2\n", "= note: did you mean 'add'?"),
+        // a list has the methods the evaluator gives it
+        ("the variable items is a list containing 1\nprint call count on items\ncall remove on items with 0\n", "List has no method 'remove'\n"),
+        ("define x is 5\n", "Expected 'variable' or 'constant'; did you mean `define variable x ...`?"),
+        (
+            "the variable items is a list containing 1\nfor each n of items do\n  print n\nend for\n",
+            "Expected 'in'; write `for each n in <list>`",
+        ),
+    ];
+    for (source, message) in cases {
+        let file = dir.join("main.lay");
+        fs::write(&file, source).unwrap();
+        let output = Command::new(layman_bin()).arg("check").arg(&file).output().unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{} gave {}", source, stderr);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::layman_bin;
+
+#[test]
+fn test_fixed_time_freezes_clock() {
+    let temp_dir = std::env::temp_dir().join("layman_fixed_time_test");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    let program = temp_dir.join("clock.lay");
+    fs::write(&program, "\
+print now
+print today
+print 3 days after today
+print 90 minutes before now
+the variable deadline is 2 weeks after today
+print deadline minus now
+if deadline is after now then
+    print \"deadline is ahead\"
+end if
+").unwrap();
+
+    // run twice: a frozen clock must give identical output
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let output = Command::new(layman_bin())
+            .arg("run")
+            .arg("--fixed-time")
+            .arg("2026-01-01T12:30:00Z")
+            .arg(&program)
+            .output()
+            .expect("Failed to run layman run --fixed-time");
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        outputs.push(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+
+    assert_eq!(outputs[0], outputs[1]);
+    assert_eq!(
+        outputs[0],
+        "2026-01-01T12:30:00Z\n\
+         2026-01-01\n\
+         2026-01-04\n\
+         2026-01-01T11:00:00Z\n\
+         13 days 11 hours 30 minutes\n\
+         deadline is ahead\n"
+    );
+
+    // an invalid timestamp is rejected before the program runs
+    let output = Command::new(layman_bin())
+        .arg("run")
+        .arg("--fixed-time")
+        .arg("yesterday")
+        .arg(&program)
+        .output()
+        .expect("Failed to run layman run --fixed-time");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --fixed-time 'yesterday'"));
+
+    fs::remove_dir_all(&temp_dir).unwrap();
+}
@@ -0,0 +1,94 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::{layman_bin, temp_project};
+
+const PROGRAM: &str = "\
+define function double that takes n as Number and returns Number
+  the variable result is n times 2
+  return result
+
+the variable total is 5
+the variable total is call function double with total
+if total is greater than 30 then
+  print \"big\"
+otherwise
+  print \"small\"
+end if
+total is 0
+";
+
+/// run the program with `--trace=<format>`; returns stdout and the trace
+fn trace(name: &str, format: &str) -> (String, String) {
+    let dir = temp_project(name, &[("main.lay", PROGRAM)]);
+    let output = Command::new(layman_bin())
+        .current_dir(&dir)
+        .args(["run", &format!("--trace={}", format), "main.lay"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    fs::remove_dir_all(&dir).unwrap();
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn test_trace_narrates_the_program_in_english() {
+    let (stdout, trace) = trace("layman_trace_english", "english");
+    assert_eq!(stdout, "small\n");
+    assert_eq!(
+        trace,
+        "\
+main.lay:5: the variable total is 5
+  total is now 5
+main.lay:6: the variable total is call function double with total
+  calling double with n = 5
+  main.lay:2: the variable result is n times 2
+    result is now 10
+  main.lay:3: return result
+  double returned 10
+  total changes from 5 to 10
+main.lay:7: if total is greater than 30 then
+  the condition is false, so the `otherwise` branch runs
+main.lay:10: print \"small\"
+main.lay:12: total is 0
+  total changes from 10 to 0
+"
+    );
+}
+
+#[test]
+fn test_trace_as_json_lines() {
+    let (_, trace) = trace("layman_trace_json", "json");
+    let events: Vec<Value> = trace.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(events.len(), 14);
+    assert_eq!(events[0], json!({ "event": "statement", "file": "main.lay", "line": 5, "text": "the variable total is 5" }));
+    assert_eq!(events[3], json!({ "event": "call", "function": "double", "arguments": [{ "name": "n", "value": 5 }] }));
+    assert_eq!(events[7], json!({ "event": "return", "function": "double", "value": 10 }));
+    assert_eq!(events[8], json!({ "event": "variable", "name": "total", "old": 5, "new": 10 }));
+    assert_eq!(events[10], json!({ "event": "branch", "file": "main.lay", "line": 7, "condition": false, "runs": "otherwise" }));
+}
+
+#[test]
+fn test_trace_lists_object_properties_by_name() {
+    let program = "\
+define class Point that has
+  property y which is Number
+  property x which is Number
+  property label which is String
+
+the variable p is a new Point with
+  y as 2
+  x as 1
+  label as \"a\"
+";
+    let dir = temp_project("layman_trace_object", &[("main.lay", program)]);
+    for format in ["english", "json"] {
+        let output = Command::new(layman_bin()).current_dir(&dir).args(["run", &format!("--trace={}", format), "main.lay"]).output().unwrap();
+        assert!(output.status.success());
+        let trace = String::from_utf8(output.stderr).unwrap();
+        assert!(trace.contains("Point object with label: a, x: 1, y: 2"), "{}", trace);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
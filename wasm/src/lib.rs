@@ -10,6 +10,7 @@ use layman_lib::evaluator;
 use layman_lib::typechecker;
 use layman_lib::resolver;
 use layman_lib::ast;
+use layman_lib::stdlib::io::ScriptedInput;
 
 // global output buffer for capturing print statements
 static OUTPUT: Mutex<String> = Mutex::new(String::new());
@@ -22,6 +23,12 @@ extern "C" {
 
 #[wasm_bindgen]
 pub fn compile_and_run(code: &str) -> Result<String, String> {
+    compile_and_run_with_input(code, "")
+}
+
+// run with `input` as the program's standard input (read by `ask` and `read all input`)
+#[wasm_bindgen]
+pub fn compile_and_run_with_input(code: &str, input: &str) -> Result<String, String> {
     // clear output buffer
     {
         let mut output = OUTPUT.lock().unwrap();
@@ -62,6 +69,7 @@ pub fn compile_and_run(code: &str) -> Result<String, String> {
         output.push_str(s);
         output.push('\n');
     });
+    eval.set_input(Arc::new(ScriptedInput::new(input)));
     
     if let ast::Node::Program(ref prog) = ast {
         for stmt in &prog.statements {